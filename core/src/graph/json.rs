@@ -43,3 +43,31 @@ fn synth_obj_to_json(obj: BTreeMap<String, Value>) -> Map<String, serde_json::Va
 fn synth_arr_to_json(arr: Vec<Value>) -> Vec<serde_json::Value> {
     arr.into_iter().map(synth_val_to_json).collect()
 }
+
+pub fn json_val_to_synth(val: serde_json::Value) -> Value {
+    match val {
+        serde_json::Value::Null => Value::Null(()),
+        serde_json::Value::Bool(b) => Value::Bool(b),
+        serde_json::Value::Number(n) => Value::Number(json_num_to_synth(n)),
+        serde_json::Value::String(s) => Value::String(s),
+        serde_json::Value::Array(arr) => {
+            Value::Array(arr.into_iter().map(json_val_to_synth).collect())
+        }
+        serde_json::Value::Object(obj) => Value::Object(
+            obj.into_iter()
+                .map(|(k, v)| (k, json_val_to_synth(v)))
+                .collect(),
+        ),
+    }
+}
+
+fn json_num_to_synth(n: serde_json::Number) -> Number {
+    if let Some(u64) = n.as_u64() {
+        Number::from(u64)
+    } else if let Some(i64) = n.as_i64() {
+        Number::from(i64)
+    } else {
+        // SAFETY: a JSON number is always one of u64, i64 or f64
+        Number::from(n.as_f64().unwrap())
+    }
+}
@@ -22,7 +22,9 @@ pub mod null;
 pub use null::NullNode;
 
 pub mod string;
-pub use string::{Format, FormatArgs, RandFaker, RandomString, StringNode, Truncated, UuidGen};
+pub use string::{
+    Format, FormatArgs, ObjectIdGen, RandFaker, RandomString, StringNode, Truncated, UuidGen,
+};
 
 pub mod date_time;
 pub use date_time::{DateTimeNode, RandomDateTime};
@@ -290,6 +290,104 @@ macro_rules! standard_float_range_step_impl {
 standard_float_range_step_impl! { f32 }
 standard_float_range_step_impl! { f64 }
 
+/// The number of times a draw outside of the bounds of a [`StandardDistribution`] is drawn
+/// again before it is clamped.
+const MAX_DRAWS: usize = 16;
+
+/// Samples numbers from a normal or log-normal distribution, within bounds and aligned to a step.
+pub struct StandardDistribution<N> {
+    variant: DistributionVariant,
+    low: f64,
+    high: f64,
+    step: Option<f64>,
+    _target: std::marker::PhantomData<N>,
+}
+
+impl<N> StandardDistribution<N> {
+    fn draw<R: Rng + ?Sized>(&self, rng: &mut R) -> f64 {
+        // Box-Muller transform. `u1` is in (0, 1] so that its logarithm is finite.
+        let u1 = 1. - rng.gen::<f64>();
+        let u2 = rng.gen::<f64>();
+        let z = (-2. * u1.ln()).sqrt() * (2. * std::f64::consts::PI * u2).cos();
+        match self.variant {
+            DistributionVariant::Normal { mean, std_dev } => mean + std_dev * z,
+            DistributionVariant::LogNormal { mu, sigma } => (mu + sigma * z).exp(),
+        }
+    }
+
+    fn sample_f64<R: Rng + ?Sized>(&self, rng: &mut R) -> f64 {
+        let in_bounds = |x: f64| self.low <= x && x <= self.high;
+        let mut x = self.draw(rng);
+        for _ in 1..MAX_DRAWS {
+            if in_bounds(x) {
+                break;
+            }
+            x = self.draw(rng);
+        }
+        x = x.max(self.low).min(self.high);
+
+        if let Some(step) = self.step {
+            let low = if self.low.is_finite() { self.low } else { 0. };
+            x = low + ((x - low) / step).round() * step;
+            if x > self.high {
+                x -= step;
+            }
+        }
+        x
+    }
+}
+
+macro_rules! standard_distribution_impl {
+    { $target:ty, $round:expr } => {
+        impl StandardDistribution<$target> {
+            pub fn try_from_distribution(distribution: DistributionContent<$target>) -> anyhow::Result<Self> {
+                let (location, scale) = match distribution.variant {
+                    DistributionVariant::Normal { mean, std_dev } => (mean, std_dev),
+                    DistributionVariant::LogNormal { mu, sigma } => (mu, sigma),
+                };
+                if !location.is_finite() || !scale.is_finite() || scale < 0. {
+                    return Err(anyhow!("{} distribution with location={} and scale={} is invalid: use finite values and a positive scale", stringify!($target), location, scale));
+                }
+
+                let low = distribution.low.map(|low| low as f64).unwrap_or(<$target>::MIN as f64);
+                let high = distribution.high.map(|high| high as f64).unwrap_or(<$target>::MAX as f64);
+                if low > high {
+                    return Err(anyhow!("{} distribution cannot have 'low'={} > 'high'={}", stringify!($target), low, high));
+                }
+
+                let step = distribution.step.map(|step| step as f64);
+                if let Some(step) = step {
+                    if !step.is_finite() || step <= 0. {
+                        return Err(anyhow!("{} distribution with step={} is invalid, use a positive value instead", stringify!($target), step));
+                    }
+                }
+
+                Ok(Self {
+                    variant: distribution.variant,
+                    low,
+                    high,
+                    step,
+                    _target: std::marker::PhantomData,
+                })
+            }
+        }
+
+        impl Distribution<$target> for StandardDistribution<$target> {
+            fn sample<R: Rng + ?Sized>(&self, rng: &mut R) -> $target {
+                let round: fn(f64) -> f64 = $round;
+                round(self.sample_f64(rng)) as $target
+            }
+        }
+    }
+}
+
+standard_distribution_impl! { u32, f64::round }
+standard_distribution_impl! { u64, f64::round }
+standard_distribution_impl! { i32, f64::round }
+standard_distribution_impl! { i64, f64::round }
+standard_distribution_impl! { f32, std::convert::identity }
+standard_distribution_impl! { f64, std::convert::identity }
+
 pub struct Incrementing<N = i64> {
     count: N,
     step: N,
@@ -376,6 +474,7 @@ macro_rules! number_node {
         $(
             $rand:ident (
                 $range:ident<$dist:ty> as $new_range:ident,
+                $distribution:ident as $new_distribution:ident,
                 $constant:ident as $new_constant:ident,
                 $(
                     $categorical:ident as $new_categorical:ident
@@ -400,6 +499,7 @@ macro_rules! number_node {
                 return Result<$ty, Error>,
                 pub enum $rand {
                     $range(OnceInfallible<Random<$ty, $dist>>),
+                    $distribution(OnceInfallible<Random<$ty, StandardDistribution<$ty>>>),
                     $constant(OnceInfallible<Yield<$ty>>),
                     $($categorical(OnceInfallible<Random<$ty, Categorical<$ty>>>),)?
                     $($incrementing(TryOnce<Incrementing<$ty>>),)?
@@ -412,6 +512,11 @@ macro_rules! number_node {
                     Ok(Self::$range(Random::new_with(dist).infallible().try_once()))
                 }
 
+                pub fn $new_distribution(distribution: DistributionContent<$ty>) -> Result<Self, anyhow::Error> {
+                    let dist = StandardDistribution::<$ty>::try_from_distribution(distribution)?;
+                    Ok(Self::$distribution(Random::new_with(dist).infallible().try_once()))
+                }
+
                 pub fn $new_constant(value: $ty) -> Self {
                     Self::$constant(Yield::wrap(value).infallible().try_once())
                 }
@@ -441,34 +546,40 @@ macro_rules! number_node {
 number_node!(
     RandomU64 (
         U64Range<StandardIntRangeStep<u64, u64>> as range,
+        U64Distribution as distribution,
         U64Constant as constant,
         U64Categorical as categorical,
         Incrementing as incrementing,
     ) for u64,
     RandomI64 (
         I64Range<StandardIntRangeStep<u64, i128>> as range,
+        I64Distribution as distribution,
         I64Constant as constant,
         I64Categorical as categorical,
         Incrementing as incrementing,
     ) for i64,
     RandomF64 (
         F64Range<StandardFloatRangeStep<f64>> as range,
+        F64Distribution as distribution,
         F64Constant as constant,,,
     ) for f64,
     RandomU32 (
         U32Range<StandardIntRangeStep<u32, u32>> as range,
+        U32Distribution as distribution,
         U32Constant as constant,
         U32Categorical as categorical,
         Incrementing as incrementing,
     ) for u32,
     RandomI32 (
         I32Range<StandardIntRangeStep<u32, i64>> as range,
+        I32Distribution as distribution,
         I32Constant as constant,
         I32Categorical as categorical,
         Incrementing as incrementing,
     ) for i32,
     RandomF32 (
         F32Range<StandardFloatRangeStep<f32>> as range,
+        F32Distribution as distribution,
         F32Constant as constant,,,
     ) for f32,
 );
@@ -4,6 +4,7 @@ use rand_regex::Regex as RandRegex;
 
 pub mod faker;
 pub mod format;
+pub mod object_id;
 pub mod serialized;
 pub mod truncated;
 pub mod uuid;
@@ -11,6 +12,7 @@ pub mod uuid;
 pub use self::uuid::UuidGen;
 pub use faker::{FakerArgs, Locale, RandFaker};
 pub use format::{Format, FormatArgs};
+pub use object_id::ObjectIdGen;
 pub use serialized::Serialized;
 pub use truncated::Truncated;
 
@@ -23,6 +25,7 @@ derive_generator! {
         Serialized(TryOnce<Serialized>)
         Categorical(OnceInfallible<Random<String, Categorical<String>>>)
         Uuid(OnceInfallible<UuidGen>),
+        ObjectId(OnceInfallible<ObjectIdGen>),
         Format(Format),
         Truncated(Truncated)
     }
@@ -58,6 +61,12 @@ impl From<UuidGen> for RandomString {
     }
 }
 
+impl From<ObjectIdGen> for RandomString {
+    fn from(object_id: ObjectIdGen) -> Self {
+        Self::ObjectId(object_id.infallible().try_once())
+    }
+}
+
 impl From<Truncated> for RandomString {
     fn from(trunc: Truncated) -> Self {
         Self::Truncated(trunc)
@@ -0,0 +1,95 @@
+use crate::graph::prelude::{Generator, GeneratorState, Rng};
+use synth_gen::Never;
+
+/// The largest value of the 3 byte counter of an ObjectId.
+const MAX_COUNTER: u32 = 0xff_ffff;
+
+/// The earliest timestamp of generated ObjectIds: 2020-01-01T00:00:00Z.
+const EPOCH: u32 = 1_577_836_800;
+
+/// The span of the timestamps generated ObjectIds start at after `EPOCH`: about 5 years.
+const SPAN: u32 = 5 * 365 * 24 * 60 * 60;
+
+/// Generates hex encoded [ObjectIds](https://docs.mongodb.com/manual/reference/method/ObjectId/):
+/// a 4 byte timestamp, 5 random bytes and a 3 byte counter. The timestamp starts at a random
+/// time after `EPOCH` and the random bytes don't change, so every ObjectId sorts after the
+/// previous ones. Both are drawn from the rng, so that the same seed generates the same ids.
+#[derive(Default)]
+pub struct ObjectIdGen {
+    state: Option<(u32, [u8; 5], u32)>,
+}
+
+impl ObjectIdGen {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl Generator for ObjectIdGen {
+    type Yield = String;
+    type Return = Never;
+
+    fn next<R: Rng>(&mut self, rng: &mut R) -> GeneratorState<Self::Yield, Self::Return> {
+        let (timestamp, random, counter) = self.state.get_or_insert_with(|| {
+            (
+                EPOCH + rng.gen_range(0..SPAN),
+                rng.gen(),
+                rng.gen_range(0..=MAX_COUNTER),
+            )
+        });
+
+        let mut bytes = [0; 12];
+        bytes[..4].copy_from_slice(&timestamp.to_be_bytes());
+        bytes[4..9].copy_from_slice(random);
+        bytes[9..].copy_from_slice(&counter.to_be_bytes()[1..]);
+
+        if *counter == MAX_COUNTER {
+            *counter = 0;
+            *timestamp = timestamp.wrapping_add(1);
+        } else {
+            *counter += 1;
+        }
+
+        GeneratorState::Yielded(bytes.iter().map(|byte| format!("{:02x}", byte)).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+
+    #[test]
+    fn object_ids_are_ordered() {
+        let mut rng = rand::rngs::StdRng::seed_from_u64(0);
+        let mut generator = ObjectIdGen {
+            state: Some((0x5f1d_7f0c, [1, 2, 3, 4, 5], MAX_COUNTER - 1)),
+        };
+
+        let ids: Vec<String> = (0..3)
+            .map(|_| generator.next(&mut rng).into_yielded().unwrap())
+            .collect();
+
+        assert_eq!(
+            ids,
+            vec![
+                "5f1d7f0c0102030405fffffe",
+                "5f1d7f0c0102030405ffffff",
+                "5f1d7f0d0102030405000000",
+            ]
+        );
+    }
+
+    #[test]
+    fn object_ids_are_seeded() {
+        let generate = || {
+            let mut rng = rand::rngs::StdRng::seed_from_u64(0);
+            let mut generator = ObjectIdGen::new();
+            (0..3)
+                .map(|_| generator.next(&mut rng).into_yielded().unwrap())
+                .collect::<Vec<String>>()
+        };
+
+        assert_eq!(generate(), generate());
+    }
+}
@@ -48,6 +48,27 @@ impl<T: CategoricalType> Categorical<T> {
         };
         self.total += 1;
     }
+
+    /// The categories and their number of occurrences.
+    pub fn iter(&self) -> impl Iterator<Item = (&T, u64)> {
+        self.seen.iter().map(|(t, occurrences)| (t, *occurrences))
+    }
+}
+
+impl<T: CategoricalType> FromIterator<(T, u64)> for Categorical<T> {
+    /// Collects categories and their number of occurrences. Categories which never occur are
+    /// left out.
+    fn from_iter<I: IntoIterator<Item = (T, u64)>>(iter: I) -> Self {
+        let mut categorical = Self {
+            seen: BTreeMap::new(),
+            total: 0,
+        };
+        for (t, occurrences) in iter.into_iter().filter(|(_, occurrences)| *occurrences > 0) {
+            *categorical.seen.entry(t).or_insert(0) += occurrences;
+            categorical.total += occurrences;
+        }
+        categorical
+    }
 }
 
 /// This struct purely serves as an intermediary to check invariants in the
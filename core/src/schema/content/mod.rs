@@ -22,11 +22,14 @@ mod r#bool;
 pub use self::r#bool::BoolContent;
 
 mod number;
-pub use number::{number_content, NumberContent, NumberContentKind, NumberKindExt, RangeStep};
+pub use number::{
+    number_content, DistributionContent, DistributionVariant, NumberContent, NumberContentKind,
+    NumberKindExt, RangeStep,
+};
 
 mod string;
 pub use string::{
-    FakerContent, FakerContentArgument, FormatContent, RegexContent, StringContent, Uuid,
+    FakerContent, FakerContentArgument, FormatContent, ObjectId, RegexContent, StringContent, Uuid,
 };
 
 mod date_time;
@@ -58,6 +61,9 @@ pub use unique::{UniqueAlgorithm, UniqueContent};
 pub mod hidden;
 pub use hidden::HiddenContent;
 
+pub mod pseudonymise;
+pub use pseudonymise::PseudonymiseContent;
+
 use prelude::*;
 
 use super::{FieldRef, Namespace};
@@ -254,6 +260,7 @@ content! {
         Series(SeriesContent),
         Unique(UniqueContent),
         Hidden(HiddenContent),
+        Pseudonymise(PseudonymiseContent),
     }
 }
 
@@ -333,6 +340,7 @@ impl Content {
         match self {
             Self::Unique(unique_content) => unique_content.content.accepts(value),
             Self::Hidden(_) => Ok(()),
+            Self::Pseudonymise(pseudonymise_content) => pseudonymise_content.content.accepts(value),
             Self::SameAs(_) => Ok(()),
             Self::OneOf(one_of_content) => {
                 let res: Vec<_> = one_of_content
@@ -419,6 +427,7 @@ impl Content {
             Content::Series(content) => format!("series::{}", content.kind()),
             Content::Unique(_) => "unique".to_string(),
             Content::Hidden(_) => "hidden".to_string(),
+            Content::Pseudonymise(_) => "pseudonymise".to_string(),
         }
     }
 }
@@ -547,6 +556,7 @@ impl Compile for Content {
             Self::Series(series_content) => series_content.compile(compiler),
             Self::Unique(unique_content) => unique_content.compile(compiler),
             Self::Hidden(hidden_content) => hidden_content.compile(compiler),
+            Self::Pseudonymise(pseudonymise_content) => pseudonymise_content.compile(compiler),
             Self::Null(_) => Ok(Graph::null()),
         }
     }
@@ -594,6 +604,12 @@ impl std::convert::TryFrom<f64> for Weight {
     }
 }
 
+impl From<usize> for Weight {
+    fn from(occurrences: usize) -> Self {
+        Self(occurrences as f64)
+    }
+}
+
 impl Default for Weight {
     fn default() -> Self {
         Self(1.0)
@@ -371,10 +371,175 @@ macro_rules! derive_hash {
 
 derive_hash!(i32, u32, i64, u64, f32, f64);
 
+/// Numbers drawn from a continuous distribution. Draws outside of `low` and `high` are drawn
+/// again, up to a limit past which they are clamped. Draws are aligned to `step`, counted from
+/// `low`, and rounded for integer subtypes.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+pub struct DistributionContent<N> {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub low: Option<N>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub high: Option<N>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub step: Option<N>,
+    #[serde(flatten)]
+    pub variant: DistributionVariant,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+#[serde(deny_unknown_fields)]
+pub enum DistributionVariant {
+    Normal {
+        mean: f64,
+        std_dev: f64,
+    },
+    /// The logarithms of the numbers follow a normal distribution of mean `mu` and standard
+    /// deviation `sigma`.
+    LogNormal {
+        mu: f64,
+        sigma: f64,
+    },
+}
+
+impl DistributionContent<u64> {
+    pub fn upcast(self, to: NumberContentKind) -> Result<NumberContent> {
+        match to {
+            NumberContentKind::U64 => Ok(number_content::U64::Distribution(self).into()),
+            NumberContentKind::I64 => {
+                let cast = self.try_cast(i64::try_from)?;
+                Ok(number_content::I64::Distribution(cast).into())
+            }
+            NumberContentKind::F64 => {
+                let cast = self.cast(|value| value as f64);
+                Ok(number_content::F64::Distribution(cast).into())
+            }
+        }
+    }
+}
+
+impl DistributionContent<i64> {
+    pub fn upcast(self, to: NumberContentKind) -> Result<NumberContent> {
+        match to {
+            NumberContentKind::U64 => Err(failed!(
+                target: Release,
+                "cannot downcast numerical subtypes"
+            )),
+            NumberContentKind::I64 => Ok(number_content::I64::Distribution(self).into()),
+            NumberContentKind::F64 => {
+                let cast = self.cast(|value| value as f64);
+                Ok(number_content::F64::Distribution(cast).into())
+            }
+        }
+    }
+}
+
+impl DistributionContent<f64> {
+    pub fn upcast(self, to: NumberContentKind) -> Result<NumberContent> {
+        match to {
+            NumberContentKind::U64 | NumberContentKind::I64 => Err(failed!(
+                target: Release,
+                "cannot downcast numerical subtypes"
+            )),
+            NumberContentKind::F64 => Ok(number_content::F64::Distribution(self).into()),
+        }
+    }
+}
+
+impl<N> DistributionContent<N> {
+    fn cast<F, M>(self, f: F) -> DistributionContent<M>
+    where
+        F: Fn(N) -> M,
+    {
+        self.try_cast::<_, _, std::convert::Infallible>(|value| Ok(f(value)))
+            .unwrap()
+    }
+
+    fn try_cast<F, M, E>(self, f: F) -> Result<DistributionContent<M>, E>
+    where
+        F: Fn(N) -> Result<M, E>,
+    {
+        Ok(DistributionContent::<M> {
+            low: self.low.map(&f).transpose()?,
+            high: self.high.map(&f).transpose()?,
+            step: self.step.map(&f).transpose()?,
+            variant: self.variant,
+        })
+    }
+}
+
+impl PartialEq for DistributionVariant {
+    fn eq(&self, other: &DistributionVariant) -> bool {
+        match (self, other) {
+            (
+                Self::Normal { mean, std_dev },
+                Self::Normal {
+                    mean: o_mean,
+                    std_dev: o_std_dev,
+                },
+            ) => mean == o_mean && std_dev == o_std_dev,
+            (
+                Self::LogNormal { mu, sigma },
+                Self::LogNormal {
+                    mu: o_mu,
+                    sigma: o_sigma,
+                },
+            ) => mu == o_mu && sigma == o_sigma,
+            _ => false,
+        }
+    }
+}
+
+impl Hash for DistributionVariant {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        std::mem::discriminant(self).hash(state);
+        match self {
+            Self::Normal { mean, std_dev } => {
+                mean.to_bits().hash(state);
+                std_dev.to_bits().hash(state);
+            }
+            Self::LogNormal { mu, sigma } => {
+                mu.to_bits().hash(state);
+                sigma.to_bits().hash(state);
+            }
+        }
+    }
+}
+
+macro_rules! derive_distribution_hash {
+    (float $t:ty) => {
+        impl Hash for DistributionContent<$t> {
+            fn hash<H: Hasher>(&self, state: &mut H) {
+                self.low.map(<$t>::to_bits).hash(state);
+                self.high.map(<$t>::to_bits).hash(state);
+                self.step.map(<$t>::to_bits).hash(state);
+                self.variant.hash(state);
+            }
+        }
+    };
+    ($($t:ty),*) => {
+        $(
+            impl Hash for DistributionContent<$t> {
+                fn hash<H: Hasher>(&self, state: &mut H) {
+                    self.low.hash(state);
+                    self.high.hash(state);
+                    self.step.hash(state);
+                    self.variant.hash(state);
+                }
+            }
+        )*
+    };
+}
+
+derive_distribution_hash!(i32, u32, i64, u64);
+derive_distribution_hash!(float f32);
+derive_distribution_hash!(float f64);
+
 number_content!(
     #[derive(PartialEq, Hash)]
     u32[is_u32, default_u32_range] as U32 {
         Range(RangeStep<u32>),
+        Distribution(crate::schema::DistributionContent<u32>),
         Categorical(Categorical<u32>),
         Constant(u32),
         Id(crate::schema::Id<u32>),
@@ -382,6 +547,7 @@ number_content!(
     #[derive(PartialEq, Hash)]
     u64[is_u64, default_u64_range] as U64 {
         Range(RangeStep<u64>),
+        Distribution(crate::schema::DistributionContent<u64>),
         Categorical(Categorical<u64>),
         Constant(u64),
         Id(crate::schema::Id<u64>),
@@ -389,6 +555,7 @@ number_content!(
     #[derive(PartialEq, Hash)]
     i32[is_i32, default_i32_range] as I32 {
         Range(RangeStep<i32>),
+        Distribution(crate::schema::DistributionContent<i32>),
         Categorical(Categorical<i32>),
         Constant(i32),
         Id(crate::schema::Id<i32>),
@@ -396,16 +563,19 @@ number_content!(
     #[derive(PartialEq, Hash)]
     i64[is_i64, default_i64_range] as I64 {
         Range(RangeStep<i64>),
+        Distribution(crate::schema::DistributionContent<i64>),
         Categorical(Categorical<i64>),
         Constant(i64),
         Id(crate::schema::Id<i64>),
     },
     f64[is_f64, default_f64_range] as F64 {
         Range(RangeStep<f64>),
+        Distribution(crate::schema::DistributionContent<f64>),
         Constant(f64),
     },
     f32[is_f32, default_f32_range] as F32 {
         Range(RangeStep<f32>),
+        Distribution(crate::schema::DistributionContent<f32>),
         Constant(f32),
     },
 );
@@ -416,6 +586,9 @@ impl Compile for NumberContent {
             Self::U64(u64_content) => {
                 let random_u64 = match u64_content {
                     number_content::U64::Range(range) => RandomU64::range(*range)?,
+                    number_content::U64::Distribution(distribution) => {
+                        RandomU64::distribution(*distribution)?
+                    }
                     number_content::U64::Categorical(categorical_content) => {
                         RandomU64::categorical(categorical_content.clone())
                     }
@@ -430,6 +603,9 @@ impl Compile for NumberContent {
             Self::I64(i64_content) => {
                 let random_i64 = match i64_content {
                     number_content::I64::Range(range) => RandomI64::range(*range)?,
+                    number_content::I64::Distribution(distribution) => {
+                        RandomI64::distribution(*distribution)?
+                    }
                     number_content::I64::Categorical(categorical_content) => {
                         RandomI64::categorical(categorical_content.clone())
                     }
@@ -443,6 +619,9 @@ impl Compile for NumberContent {
             Self::F64(f64_content) => {
                 let random_f64 = match f64_content {
                     number_content::F64::Range(range) => RandomF64::range(*range)?,
+                    number_content::F64::Distribution(distribution) => {
+                        RandomF64::distribution(*distribution)?
+                    }
                     number_content::F64::Constant(val) => RandomF64::constant(*val),
                 };
                 random_f64.into()
@@ -450,6 +629,9 @@ impl Compile for NumberContent {
             Self::U32(u32_content) => {
                 let random_u32 = match u32_content {
                     number_content::U32::Range(range) => RandomU32::range(*range)?,
+                    number_content::U32::Distribution(distribution) => {
+                        RandomU32::distribution(*distribution)?
+                    }
                     number_content::U32::Categorical(categorical_content) => {
                         RandomU32::categorical(categorical_content.clone())
                     }
@@ -463,6 +645,9 @@ impl Compile for NumberContent {
             Self::I32(i32_content) => {
                 let random_i32 = match i32_content {
                     number_content::I32::Range(range) => RandomI32::range(*range)?,
+                    number_content::I32::Distribution(distribution) => {
+                        RandomI32::distribution(*distribution)?
+                    }
                     number_content::I32::Categorical(categorical_content) => {
                         RandomI32::categorical(categorical_content.clone())
                     }
@@ -476,6 +661,9 @@ impl Compile for NumberContent {
             Self::F32(f32_content) => {
                 let random_f32 = match f32_content {
                     number_content::F32::Range(range) => RandomF32::range(*range)?,
+                    number_content::F32::Distribution(distribution) => {
+                        RandomF32::distribution(*distribution)?
+                    }
                     number_content::F32::Constant(val) => RandomF32::constant(*val),
                 };
                 random_f32.into()
@@ -593,6 +781,7 @@ impl number_content::U64 {
     pub fn upcast(self, to: NumberContentKind) -> Result<NumberContent> {
         match self {
             Self::Range(range) => range.upcast(to),
+            Self::Distribution(distribution) => distribution.upcast(to),
             Self::Categorical(cat) => cat.upcast(to),
             Self::Constant(val) => match to {
                 NumberContentKind::U64 => Ok(self.into()),
@@ -617,6 +806,7 @@ impl number_content::I64 {
     pub fn upcast(self, to: NumberContentKind) -> Result<NumberContent> {
         match self {
             Self::Range(range) => range.upcast(to),
+            Self::Distribution(distribution) => distribution.upcast(to),
             Self::Categorical(cat) => cat.upcast(to),
             Self::Constant(val) => match to {
                 NumberContentKind::U64 => Err(failed!(
@@ -638,6 +828,7 @@ impl number_content::F64 {
     pub fn upcast(self, to: NumberContentKind) -> Result<NumberContent> {
         match self {
             Self::Range(range) => range.upcast(to),
+            Self::Distribution(distribution) => distribution.upcast(to),
             Self::Constant(_) => match to {
                 NumberContentKind::U64 => Err(failed!(
                     target: Release,
@@ -657,6 +848,7 @@ impl Hash for number_content::F32 {
     fn hash<H: Hasher>(&self, state: &mut H) {
         match self {
             Self::Range(range) => range.hash(state),
+            Self::Distribution(distribution) => distribution.hash(state),
             Self::Constant(constant) => constant.to_bits().hash(state),
         }
     }
@@ -669,6 +861,10 @@ impl PartialEq for number_content::F32 {
                 Self::Range(o_range) => range == o_range,
                 _ => false,
             },
+            Self::Distribution(distribution) => match other {
+                Self::Distribution(o_distribution) => distribution == o_distribution,
+                _ => false,
+            },
             Self::Constant(constant) => match other {
                 Self::Constant(o_constant) => constant == o_constant,
                 _ => false,
@@ -681,6 +877,7 @@ impl Hash for number_content::F64 {
     fn hash<H: Hasher>(&self, state: &mut H) {
         match self {
             Self::Range(range) => range.hash(state),
+            Self::Distribution(distribution) => distribution.hash(state),
             Self::Constant(constant) => constant.to_bits().hash(state),
         }
     }
@@ -693,6 +890,10 @@ impl PartialEq for number_content::F64 {
                 Self::Range(o_range) => range == o_range,
                 _ => false,
             },
+            Self::Distribution(distribution) => match other {
+                Self::Distribution(o_distribution) => distribution == o_distribution,
+                _ => false,
+            },
             Self::Constant(constant) => match other {
                 Self::Constant(o_constant) => constant == o_constant,
                 _ => false,
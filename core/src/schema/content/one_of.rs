@@ -59,6 +59,14 @@ impl VariantContent {
             content: Box::new(content),
         }
     }
+
+    pub fn weight(&self) -> f64 {
+        self.weight.0
+    }
+
+    pub fn set_weight(&mut self, weight: Weight) {
+        self.weight = weight;
+    }
 }
 
 impl FromIterator<Content> for OneOfContent {
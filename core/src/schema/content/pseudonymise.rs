@@ -0,0 +1,73 @@
+use crate::compile::Compile;
+use crate::graph::json::synth_val_to_json;
+use crate::graph::Value;
+use crate::{Compiler, Content, Graph};
+use anyhow::Result;
+use hmac::{Hmac, Mac, NewMac};
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use synth_gen::prelude::*;
+
+/// Replaces real values with generated ones, such that the same input is always replaced by the
+/// same value. The inner content is seeded from an HMAC of the input keyed with `secret`, so the
+/// replacement is stable across tables and runs but the input can't be recovered from it without
+/// the secret.
+///
+/// Inputs only exist when masking a database. Elsewhere, values are generated by the inner
+/// content as usual.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Hash)]
+#[serde(deny_unknown_fields)]
+pub struct PseudonymiseContent {
+    /// The key of the HMAC. When omitted, it has to be given when masking.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub secret: Option<String>,
+    pub content: Box<Content>,
+}
+
+impl PseudonymiseContent {
+    /// The replacement of `input`, using `secret` as the key of the HMAC.
+    pub fn pseudonymise(&self, secret: &str, input: &Value) -> Result<Value> {
+        let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes())
+            .expect("HMAC accepts keys of any length");
+        // The JSON representation doesn't depend on the width of the column the input comes from
+        mac.update(synth_val_to_json(input.clone()).to_string().as_bytes());
+
+        let mut seed = <StdRng as SeedableRng>::Seed::default();
+        seed.copy_from_slice(&mac.finalize().into_bytes());
+        let mut rng = StdRng::from_seed(seed);
+
+        let mut model = Graph::from_content(&self.content)?.aggregate();
+        Ok(model.complete(&mut rng)?)
+    }
+}
+
+impl Compile for PseudonymiseContent {
+    fn compile<'a, C: Compiler<'a>>(&'a self, compiler: C) -> Result<Graph> {
+        self.content.compile(compiler)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn pseudonymise() {
+        let content: PseudonymiseContent = serde_json::from_value(json!({
+            "content": { "type": "string", "pattern": "[a-z]{12}@example\\.com" }
+        }))
+        .unwrap();
+
+        let alice = Value::String("alice@example.org".to_string());
+        let bob = Value::String("bob@example.org".to_string());
+
+        let masked = content.pseudonymise("secret", &alice).unwrap();
+        assert!(masked.as_string().unwrap().ends_with("@example.com"));
+        assert_eq!(content.pseudonymise("secret", &alice).unwrap(), masked);
+        assert_ne!(content.pseudonymise("secret", &bob).unwrap(), masked);
+        assert_ne!(content.pseudonymise("other", &alice).unwrap(), masked);
+    }
+}
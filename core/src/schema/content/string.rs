@@ -13,6 +13,7 @@ pub enum StringContent {
     Categorical(Categorical<String>),
     Serialized(SerializedContent),
     Uuid(Uuid),
+    ObjectId(ObjectId),
     Truncated(TruncatedContent),
     Format(FormatContent),
 }
@@ -20,6 +21,9 @@ pub enum StringContent {
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Hash)]
 pub struct Uuid;
 
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Hash)]
+pub struct ObjectId;
+
 impl StringContent {
     pub fn kind(&self) -> String {
         match self {
@@ -28,6 +32,7 @@ impl StringContent {
             Self::Categorical(_) => "categorical".to_string(),
             Self::Serialized(_) => "serialized".to_string(),
             Self::Uuid(_) => "uuid".to_string(),
+            Self::ObjectId(_) => "object_id".to_string(),
             Self::Truncated(_) => "truncated".to_string(),
             Self::Format(_) => "format".to_string(),
         }
@@ -62,6 +67,17 @@ impl RegexContent {
         let rand_regex = RandRegex::compile(pattern.as_str(), max_repeat)?;
         Ok(Self(pattern, rand_regex))
     }
+
+    /// Whether this is one of the patterns imported strings get before their values are looked
+    /// at: the default pattern, or alphanumeric strings up to the maximum length of a column.
+    /// Other patterns are constraints of the source or were written by hand.
+    pub fn is_placeholder(&self) -> bool {
+        lazy_static! {
+            static ref PLACEHOLDER: regex::Regex =
+                regex::Regex::new(r"^\[a-zA-Z0-9\](\*|\{0, \d+\})$").unwrap();
+        }
+        PLACEHOLDER.is_match(&self.0)
+    }
 }
 
 impl<'de> Deserialize<'de> for RegexContent {
@@ -255,6 +271,7 @@ impl Compile for StringContent {
                 RandomString::from(Truncated::new(content, length)).into()
             }
             StringContent::Uuid(_uuid) => RandomString::from(UuidGen {}).into(),
+            StringContent::ObjectId(_object_id) => RandomString::from(ObjectIdGen::new()).into(),
         };
         Ok(Graph::String(string_node))
     }
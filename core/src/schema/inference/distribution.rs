@@ -0,0 +1,365 @@
+use num::{Float, NumCast, ToPrimitive};
+use serde_json::Value;
+
+use super::samples::visit_sampled_fields;
+use crate::schema::number_content::{F32, F64, I32, I64, U32, U64};
+use crate::schema::{
+    Categorical, CategoricalType, Content, DistributionContent, DistributionVariant, NumberContent,
+    RangeStep,
+};
+
+/// Fewer sampled values than this say too little about their distribution to fit one.
+const MIN_SAMPLES: usize = 10;
+
+/// Integers with at most this many distinct values, each of which is sampled twice on average,
+/// are inferred as categoricals.
+const MAX_CATEGORIES: usize = 10;
+
+/// The most decimals looked for in floats to align them to a step.
+const MAX_DECIMALS: i32 = 6;
+
+/// Replaces the ranges of the number fields of a collection with content fitting their sampled
+/// values better:
+///
+/// - Integers with few distinct values become categoricals weighted by their number of
+///   occurrences.
+/// - Otherwise, a normal or log-normal distribution bounded by the smallest and largest value
+///   replaces the range if it fits the values better than a uniform distribution over the range.
+/// - Ranges and distributions get the step all values are aligned to, e.g. `5` for multiples of
+///   5 or `0.01` for prices.
+///
+/// `samples` is the array of sampled values of the collection.
+pub fn infer_distributions(name: &str, collection: &mut Content, samples: &Value) {
+    visit_sampled_fields(
+        name,
+        collection,
+        samples,
+        &mut |path, _, content, values| {
+            if let Content::Number(number) = content {
+                if let Some(inferred) = infer_number(number, values) {
+                    debug!(
+                        "{}: inferred {} from the sampled numbers",
+                        path,
+                        inferred.kind()
+                    );
+                    *number = inferred;
+                }
+            }
+        },
+    );
+}
+
+/// Content inferred for numbers of type `N`.
+enum Inferred<N: CategoricalType> {
+    Range(RangeStep<N>),
+    Distribution(DistributionContent<N>),
+    Categorical(Categorical<N>),
+}
+
+macro_rules! infer_numbers {
+    ($number:ident, $values:ident, $(($as:ident, $ty:ty, $infer:ident, $get:ident)),*) => {
+        match $number {
+            $(NumberContent::$as($as::Range(range)) => {
+                let values: Vec<$ty> = $values
+                    .iter()
+                    .filter_map(|value| value.$get())
+                    .filter_map(<$ty as NumCast>::from)
+                    .collect();
+                $infer(range, &values).map(|inferred| NumberContent::$as(match inferred {
+                    Inferred::Range(range) => $as::Range(range),
+                    Inferred::Distribution(distribution) => $as::Distribution(distribution),
+                    Inferred::Categorical(categorical) => $as::Categorical(categorical),
+                }))
+            })*
+            _ => None,
+        }
+    };
+}
+
+macro_rules! infer_floats {
+    ($number:ident, $values:ident, $(($as:ident, $ty:ty)),*) => {
+        match $number {
+            $(NumberContent::$as($as::Range(range)) => {
+                let values: Vec<$ty> = $values
+                    .iter()
+                    .filter_map(|value| value.as_f64())
+                    .filter_map(<$ty as NumCast>::from)
+                    .collect();
+                infer_float(range, &values).map(|inferred| NumberContent::$as(match inferred {
+                    Ok(range) => $as::Range(range),
+                    Err(distribution) => $as::Distribution(distribution),
+                }))
+            })*
+            _ => None,
+        }
+    };
+}
+
+fn infer_number(number: &NumberContent, values: &[&Value]) -> Option<NumberContent> {
+    infer_numbers!(
+        number,
+        values,
+        (U64, u64, infer_integer, as_u64),
+        (I64, i64, infer_integer, as_i64),
+        (U32, u32, infer_integer, as_u64),
+        (I32, i32, infer_integer, as_i64)
+    )
+    .or_else(|| infer_floats!(number, values, (F64, f64), (F32, f32)))
+}
+
+fn infer_integer<N>(range: &RangeStep<N>, values: &[N]) -> Option<Inferred<N>>
+where
+    N: CategoricalType + Copy + ToPrimitive + NumCast,
+{
+    let summary = Summary::of(values)?;
+    let step = summary
+        .gcd_step()
+        .filter(|step| *step > 1)
+        .and_then(N::from);
+
+    if summary.len() >= MIN_SAMPLES
+        && summary.distinct <= MAX_CATEGORIES
+        && summary.distinct * 2 <= summary.len()
+    {
+        let categorical = values.iter().map(|value| (*value, 1)).collect();
+        return Some(Inferred::Categorical(categorical));
+    }
+
+    if let Some(variant) = summary.best_fit() {
+        return Some(Inferred::Distribution(DistributionContent {
+            low: N::from(summary.min()),
+            high: N::from(summary.max()),
+            step,
+            variant,
+        }));
+    }
+
+    step.map(|step| {
+        Inferred::Range(RangeStep {
+            step: Some(step),
+            ..*range
+        })
+    })
+}
+
+/// The range with the step of the values, or a distribution fitting them. The step of the range,
+/// such as the scale of decimal columns, is kept if the values have too many decimals.
+fn infer_float<N>(
+    range: &RangeStep<N>,
+    values: &[N],
+) -> Option<Result<RangeStep<N>, DistributionContent<N>>>
+where
+    N: Float,
+{
+    let summary = Summary::of(values)?;
+    let step = summary.decimal_step().and_then(N::from).or(range.step);
+
+    match summary.best_fit() {
+        Some(variant) => Some(Err(DistributionContent {
+            low: N::from(summary.min()),
+            high: N::from(summary.max()),
+            step,
+            variant,
+        })),
+        None if step == range.step => None,
+        None => Some(Ok(RangeStep { step, ..*range })),
+    }
+}
+
+/// Summary statistics of sampled numbers.
+struct Summary {
+    sorted: Vec<f64>,
+    mean: f64,
+    std_dev: f64,
+    distinct: usize,
+}
+
+impl Summary {
+    /// `None` if there are fewer than 2 finite values.
+    fn of<N: ToPrimitive>(values: &[N]) -> Option<Self> {
+        let mut sorted: Vec<f64> = values
+            .iter()
+            .filter_map(ToPrimitive::to_f64)
+            .filter(|value| value.is_finite())
+            .collect();
+        if sorted.len() < 2 {
+            return None;
+        }
+        sorted.sort_by(|a, b| a.partial_cmp(b).unwrap());
+
+        let (mean, std_dev) = mean_std_dev(sorted.iter().copied());
+        let mut distinct = sorted.clone();
+        distinct.dedup();
+        Some(Self {
+            distinct: distinct.len(),
+            sorted,
+            mean,
+            std_dev,
+        })
+    }
+
+    fn len(&self) -> usize {
+        self.sorted.len()
+    }
+
+    fn min(&self) -> f64 {
+        self.sorted[0]
+    }
+
+    fn max(&self) -> f64 {
+        self.sorted[self.len() - 1]
+    }
+
+    /// The greatest common divisor of the differences between the values and the smallest one.
+    fn gcd_step(&self) -> Option<u64> {
+        let min = self.min();
+        self.sorted
+            .iter()
+            .map(|value| (value - min) as u64)
+            .fold(None, |gcd, diff| match gcd {
+                None if diff == 0 => None,
+                None => Some(diff),
+                Some(gcd) => Some(num::integer::gcd(gcd, diff)),
+            })
+    }
+
+    /// The power of 10 all values are multiples of, if they have at most `MAX_DECIMALS` decimals.
+    fn decimal_step(&self) -> Option<f64> {
+        (0..=MAX_DECIMALS).find_map(|decimals| {
+            let scale = 10f64.powi(decimals);
+            self.sorted
+                .iter()
+                .all(|value| {
+                    let scaled = value * scale;
+                    (scaled - scaled.round()).abs() <= 1e-6 * scaled.abs().max(1.)
+                })
+                .then(|| 1. / scale)
+        })
+    }
+
+    /// The normal or log-normal distribution which fits the values best, if any fits them better
+    /// than a uniform distribution between the smallest and largest value. Fits are compared
+    /// by their Kolmogorov-Smirnov statistic.
+    fn best_fit(&self) -> Option<DistributionVariant> {
+        if self.len() < MIN_SAMPLES || self.std_dev == 0. {
+            return None;
+        }
+
+        let (min, max) = (self.min(), self.max());
+        let mut best = None;
+        let mut best_statistic = self.ks_statistic(|x| (x - min) / (max - min));
+
+        let (mean, std_dev) = (self.mean, self.std_dev);
+        let normal = self.ks_statistic(|x| normal_cdf((x - mean) / std_dev));
+        if normal < best_statistic {
+            best = Some(DistributionVariant::Normal { mean, std_dev });
+            best_statistic = normal;
+        }
+
+        if min > 0. {
+            let (mu, sigma) = mean_std_dev(self.sorted.iter().map(|value| value.ln()));
+            if sigma > 0. {
+                let log_normal = self.ks_statistic(|x| normal_cdf((x.ln() - mu) / sigma));
+                if log_normal < best_statistic {
+                    best = Some(DistributionVariant::LogNormal { mu, sigma });
+                }
+            }
+        }
+
+        best
+    }
+
+    /// The largest distance between the empirical distribution of the values and `cdf`.
+    fn ks_statistic<F: Fn(f64) -> f64>(&self, cdf: F) -> f64 {
+        let n = self.len() as f64;
+        self.sorted
+            .iter()
+            .enumerate()
+            .map(|(i, value)| {
+                let expected = cdf(*value);
+                (expected - i as f64 / n)
+                    .abs()
+                    .max((expected - (i + 1) as f64 / n).abs())
+            })
+            .fold(0., f64::max)
+    }
+}
+
+fn mean_std_dev<I: Iterator<Item = f64> + Clone>(values: I) -> (f64, f64) {
+    let n = values.clone().count() as f64;
+    let mean = values.clone().sum::<f64>() / n;
+    let variance = values.map(|value| (value - mean).powi(2)).sum::<f64>() / (n - 1.);
+    (mean, variance.sqrt())
+}
+
+/// The cumulative distribution function of the standard normal distribution.
+fn normal_cdf(z: f64) -> f64 {
+    0.5 * (1. + erf(z / std::f64::consts::SQRT_2))
+}
+
+/// The error function, to within 1.5e-7 (Abramowitz and Stegun, 7.1.26).
+fn erf(x: f64) -> f64 {
+    let t = 1. / (1. + 0.3275911 * x.abs());
+    let polynomial = t
+        * (0.254829592
+            + t * (-0.284496736 + t * (1.421413741 + t * (-1.453152027 + t * 1.061405429))));
+    (1. - polynomial * (-x * x).exp()).copysign(x)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schema::{MergeStrategy, Namespace, OptionalMergeStrategy};
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+
+    #[test]
+    fn infer_distributions() {
+        // Heights are roughly normal, as sums of uniform values, with a single outlier
+        let mut rng = StdRng::seed_from_u64(0);
+        let rows: Vec<Value> = (0..200u64)
+            .map(|i| {
+                let height = match i {
+                    0 => 250.,
+                    _ => 170. + 10. * ((0..12).map(|_| rng.gen::<f64>()).sum::<f64>() - 6.),
+                };
+                json!({
+                    "height": (height * 100.).round() / 100.,
+                    "rating": i % 5 + 1,
+                    "score": i * 5,
+                    "ratio": i as f64 * 0.001_234_567_8,
+                })
+            })
+            .collect();
+        let samples = json!(rows);
+        let mut content = Namespace::collection(&rows[0]);
+        OptionalMergeStrategy
+            .try_merge(&mut content, &samples)
+            .unwrap();
+        // The scale of a decimal column with more decimals than looked for
+        let mut content = serde_json::to_value(&content).unwrap();
+        content["content"]["ratio"]["range"]["step"] = json!(1e-10);
+        let mut content: Content = serde_json::from_value(content).unwrap();
+
+        super::infer_distributions("people", &mut content, &samples);
+        let content = serde_json::to_value(&content).unwrap();
+        let fields = &content["content"];
+
+        let height = &fields["height"]["distribution"];
+        assert_eq!(height["high"], json!(250.));
+        assert_eq!(height["step"], json!(0.01));
+        assert!(height
+            .get("normal")
+            .or_else(|| height.get("log_normal"))
+            .is_some());
+
+        assert_eq!(
+            fields["rating"]["categorical"],
+            json!({ "1": 40, "2": 40, "3": 40, "4": 40, "5": 40 })
+        );
+
+        assert_eq!(fields["score"]["range"]["step"], json!(5));
+
+        assert_eq!(fields["ratio"]["range"]["step"], json!(1e-10));
+    }
+}
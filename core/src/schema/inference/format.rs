@@ -0,0 +1,278 @@
+use std::collections::BTreeSet;
+use std::net::IpAddr;
+
+use regex::Regex;
+use serde_json::Value;
+
+use super::samples::visit_sampled_fields;
+use super::shape::infer_string_shape;
+use super::{MergeStrategy, OptionalMergeStrategy};
+use crate::schema::{
+    ChronoValueFormatter, ChronoValueType, Content, DateTimeContent, FakerContent, RegexContent,
+    StringContent, Uuid,
+};
+
+lazy_static! {
+    static ref UUID: Regex = Regex::new(
+        r"^[0-9a-fA-F]{8}-[0-9a-fA-F]{4}-[0-9a-fA-F]{4}-[0-9a-fA-F]{4}-[0-9a-fA-F]{12}$"
+    )
+    .unwrap();
+    static ref EMAIL: Regex = Regex::new(r"^[^@\s]+@[^@\s]+\.[a-zA-Z]{2,}$").unwrap();
+    static ref URL: Regex =
+        Regex::new(r"^(https?|ftp)://([a-zA-Z0-9-]+\.)+([a-zA-Z]{2,})(:\d+)?([/?#]\S*)?$").unwrap();
+    static ref MAC: Regex = Regex::new(r"^([0-9a-fA-F]{2}[:-]){5}[0-9a-fA-F]{2}$").unwrap();
+    static ref ZIP: Regex = Regex::new(r"^\d{5}(-\d{4})?$").unwrap();
+    static ref PHONE: Regex =
+        Regex::new(r"^(\+\d{1,3}[ .-]?)?(\(\d{1,4}\)[ .-]?)?\d{2,4}([ .-]\d{2,4}){1,4}$").unwrap();
+}
+
+/// The formats of dates and times which are recognised, along with the type of their values.
+/// Formats without a fraction of seconds go first, as `%.f` also parses values without one.
+const DATE_TIME_FORMATS: &[(&str, ChronoValueType)] = &[
+    ("%Y-%m-%d", ChronoValueType::NaiveDate),
+    ("%Y-%m-%dT%H:%M:%S", ChronoValueType::NaiveDateTime),
+    ("%Y-%m-%dT%H:%M:%S%.f", ChronoValueType::NaiveDateTime),
+    ("%Y-%m-%dT%H:%M:%SZ", ChronoValueType::NaiveDateTime),
+    ("%Y-%m-%dT%H:%M:%S%.fZ", ChronoValueType::NaiveDateTime),
+    ("%Y-%m-%dT%H:%M:%S%:z", ChronoValueType::DateTime),
+    ("%Y-%m-%dT%H:%M:%S%.f%:z", ChronoValueType::DateTime),
+    ("%Y-%m-%d %H:%M:%S", ChronoValueType::NaiveDateTime),
+    ("%Y-%m-%d %H:%M:%S%.f", ChronoValueType::NaiveDateTime),
+    ("%Y-%m-%d %H:%M:%S%:z", ChronoValueType::DateTime),
+    ("%H:%M:%S", ChronoValueType::NaiveTime),
+];
+
+/// Replaces the content of the string fields of a collection whose sampled values all share a
+/// common format, such as UUIDs, dates, email addresses, URLs, IP addresses, zip codes or phone
+/// numbers, with content generating values of that format. The other fields get a pattern of the
+/// shape and length of their values. Only fields with the placeholder pattern of imported strings
+/// are replaced, so categoricals, the patterns of constraints such as MongoDB validators and
+/// hand-written content are kept. Text found in the values, such as the top level domains of URLs
+/// or fixed prefixes, is only copied into patterns if `keep_literals` is set.
+///
+/// `samples` is the array of sampled values of the collection.
+pub fn infer_formats(name: &str, collection: &mut Content, samples: &Value, keep_literals: bool) {
+    visit_sampled_fields(
+        name,
+        collection,
+        samples,
+        &mut |path, _, content, values| {
+            if let Content::String(StringContent::Pattern(pattern)) = content {
+                if !pattern.is_placeholder() {
+                    return;
+                }
+
+                let strings: Vec<&str> = values.iter().filter_map(|value| value.as_str()).collect();
+                if let Some(inferred) = infer_string_format(&strings, keep_literals)
+                    .or_else(|| infer_string_shape(&strings, keep_literals))
+                {
+                    debug!(
+                        "{}: inferred {} from the sampled strings",
+                        path,
+                        inferred.kind()
+                    );
+                    *content = inferred;
+                }
+            }
+        },
+    );
+}
+
+/// The content generating strings of the format all of the given values share, if any. The top
+/// level domains of URLs are only kept if `keep_literals` is set.
+pub fn infer_string_format(values: &[&str], keep_literals: bool) -> Option<Content> {
+    if values.is_empty() {
+        return None;
+    }
+
+    let all_match = |regex: &Regex| values.iter().all(|value| regex.is_match(value));
+
+    if all_match(&UUID) {
+        return Some(Content::String(StringContent::Uuid(Uuid)));
+    }
+
+    if let Some(date_time) = date_time_content(values) {
+        return Some(Content::DateTime(date_time));
+    }
+
+    if all_match(&EMAIL) {
+        return Some(faker("safe_email"));
+    }
+
+    if all_match(&URL) {
+        return url_pattern(values, keep_literals).and_then(pattern);
+    }
+
+    if let Some(generator) = ip_generator(values) {
+        return Some(faker(generator));
+    }
+
+    if all_match(&MAC) {
+        return Some(faker("mac_address"));
+    }
+
+    if all_match(&ZIP) {
+        let extended = values.iter().filter(|value| value.len() > 5).count();
+        let zip = match extended {
+            0 => r"[0-9]{5}",
+            n if n == values.len() => r"[0-9]{5}-[0-9]{4}",
+            _ => r"[0-9]{5}(-[0-9]{4})?",
+        };
+        return pattern(zip.to_string());
+    }
+
+    let is_phone = |value: &&str| {
+        let digits = value.chars().filter(char::is_ascii_digit).count();
+        PHONE.is_match(value) && (7..=15).contains(&digits)
+    };
+    if values.iter().all(is_phone) {
+        return Some(faker("phone_number"));
+    }
+
+    None
+}
+
+/// The first of the recognised date and time formats all values parse with, bounded by the
+/// earliest and latest value.
+fn date_time_content(values: &[&str]) -> Option<DateTimeContent> {
+    DATE_TIME_FORMATS.iter().find_map(|(format, type_)| {
+        let formatter = ChronoValueFormatter::new_with(format, Some(*type_));
+        if !values.iter().all(|value| formatter.parse(value).is_ok()) {
+            return None;
+        }
+
+        let mut content = DateTimeContent {
+            format: format.to_string(),
+            type_: *type_,
+            begin: None,
+            end: None,
+        };
+        for value in values {
+            OptionalMergeStrategy
+                .try_merge(&mut content, &value.to_string())
+                .ok()?;
+        }
+        Some(content)
+    })
+}
+
+/// A pattern generating URLs with the schemes of the given ones, and their top level domains if
+/// `keep_literals` is set. Schemes are one of a few known ones, so they are always kept.
+fn url_pattern(values: &[&str], keep_literals: bool) -> Option<String> {
+    let mut schemes = BTreeSet::new();
+    let mut domains = BTreeSet::new();
+    for value in values {
+        let captures = URL.captures(value)?;
+        schemes.insert(captures[1].to_lowercase());
+        domains.insert(captures[3].to_lowercase());
+    }
+
+    let domains = if keep_literals {
+        format!("({})", domains.into_iter().collect::<Vec<_>>().join("|"))
+    } else {
+        "[a-z]{2,3}".to_string()
+    };
+    Some(format!(
+        r"({})://[a-z][a-z0-9]{{2,11}}\.{}(/[a-z0-9-]{{1,12}}){{0,3}}",
+        schemes.into_iter().collect::<Vec<_>>().join("|"),
+        domains
+    ))
+}
+
+/// The faker generator of IP addresses of the versions of the given ones, if they all are.
+fn ip_generator(values: &[&str]) -> Option<&'static str> {
+    let mut v4 = false;
+    let mut v6 = false;
+    for value in values {
+        match value.parse::<IpAddr>().ok()? {
+            IpAddr::V4(_) => v4 = true,
+            IpAddr::V6(_) => v6 = true,
+        }
+    }
+
+    match (v4, v6) {
+        (true, false) => Some("ipv4"),
+        (false, true) => Some("ipv6"),
+        _ => Some("ip"),
+    }
+}
+
+fn faker(generator: &str) -> Content {
+    Content::String(StringContent::Faker(FakerContent {
+        generator: generator.to_string(),
+        locales: Vec::new(),
+        args: Default::default(),
+    }))
+}
+
+fn pattern(pattern: String) -> Option<Content> {
+    let regex = RegexContent::compile(pattern, 32).ok()?;
+    Some(Content::String(StringContent::Pattern(regex)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn inferred(values: &[&str]) -> Value {
+        serde_json::to_value(super::infer_string_format(values, true)).unwrap()
+    }
+
+    #[test]
+    fn infer_string_format() {
+        assert_eq!(
+            inferred(&["1b4e28ba-2fa1-11d2-883f-0016d3cca427"]),
+            json!({ "type": "string", "uuid": null })
+        );
+        assert_eq!(
+            inferred(&["2021-10-25", "2020-01-31"]),
+            json!({
+                "type": "date_time",
+                "format": "%Y-%m-%d",
+                "subtype": "naive_date",
+                "begin": "2020-01-31",
+                "end": "2021-10-25"
+            })
+        );
+        assert_eq!(
+            inferred(&["2021-10-25T10:30:00+01:00"])["subtype"],
+            "date_time"
+        );
+        assert_eq!(
+            inferred(&["2021-10-25 10:30:00", "2021-10-25 10:30:00.123"])["format"],
+            "%Y-%m-%d %H:%M:%S%.f"
+        );
+        assert_eq!(
+            inferred(&["alice@example.com", "bob@example.org"])["faker"]["generator"],
+            "safe_email"
+        );
+        assert_eq!(
+            inferred(&["https://example.com/about", "http://www.example.org"])["pattern"],
+            r"(http|https)://[a-z][a-z0-9]{2,11}\.(com|org)(/[a-z0-9-]{1,12}){0,3}"
+        );
+        assert_eq!(
+            serde_json::to_value(super::infer_string_format(
+                &["https://example.com/about", "http://www.example.org"],
+                false
+            ))
+            .unwrap()["pattern"],
+            r"(http|https)://[a-z][a-z0-9]{2,11}\.[a-z]{2,3}(/[a-z0-9-]{1,12}){0,3}"
+        );
+        assert_eq!(inferred(&["10.0.0.1", "::1"])["faker"]["generator"], "ip");
+        assert_eq!(
+            inferred(&["00:1B:44:11:3A:B7"])["faker"]["generator"],
+            "mac_address"
+        );
+        assert_eq!(
+            inferred(&["90210", "10001-1234"])["pattern"],
+            r"[0-9]{5}(-[0-9]{4})?"
+        );
+        assert_eq!(
+            inferred(&["+1 555-123-4567", "(020) 7946 0958"])["faker"]["generator"],
+            "phone_number"
+        );
+        assert_eq!(inferred(&["hello", "world"]), Value::Null);
+        assert_eq!(inferred(&["alice@example.com", "hello"]), Value::Null);
+        assert_eq!(inferred(&[]), Value::Null);
+    }
+}
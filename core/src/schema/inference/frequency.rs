@@ -0,0 +1,111 @@
+use std::collections::BTreeSet;
+
+use serde_json::Value;
+
+use super::samples::visit_sampled_content;
+use crate::schema::number_content::U64;
+use crate::schema::{ArrayContent, Content, NumberContent, Weight};
+
+/// Arrays with more distinct sampled lengths than this keep the range of their lengths.
+const MAX_LENGTHS: usize = 32;
+
+/// Replaces the default frequencies of the arrays and nullable fields of a collection with the
+/// frequencies of their sampled values:
+///
+/// - The length of arrays becomes a categorical of the sampled lengths, weighted by their number
+///   of occurrences, instead of a range over them. The length of the collection itself is kept.
+/// - The null and non null variants of nullable fields are weighted by the number of null and non
+///   null sampled values, so that fields are as often null as in the samples.
+///
+/// `samples` is the array of sampled values of the collection.
+pub fn infer_frequencies(name: &str, collection: &mut Content, samples: &Value) {
+    visit_sampled_content(
+        name,
+        collection,
+        samples,
+        &mut |path, _, content, values| match content {
+            Content::Array(ArrayContent { length, .. }) if path != name => {
+                if let Content::Number(NumberContent::U64(length @ U64::Range(_))) = length.as_mut()
+                {
+                    let lengths: Vec<u64> = values
+                        .iter()
+                        .filter_map(|value| value.as_array())
+                        .map(|array| array.len() as u64)
+                        .collect();
+                    let distinct = lengths.iter().collect::<BTreeSet<_>>().len();
+                    if distinct <= MAX_LENGTHS {
+                        debug!("{}: inferred the frequencies of the array lengths", path);
+                        *length =
+                            U64::Categorical(lengths.into_iter().map(|len| (len, 1)).collect());
+                    }
+                }
+            }
+            Content::OneOf(one_of) if one_of.is_nullable() => {
+                let nulls = values.iter().filter(|value| value.is_null()).count();
+                debug!(
+                    "{}: {} of {} sampled values are null",
+                    path,
+                    nulls,
+                    values.len()
+                );
+                for variant in one_of.variants.iter_mut() {
+                    let occurrences = if variant.content.is_null() {
+                        nulls
+                    } else {
+                        values.len() - nulls
+                    };
+                    variant.set_weight(Weight::from(occurrences));
+                }
+            }
+            _ => {}
+        },
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schema::{MergeStrategy, Namespace, OptionalMergeStrategy};
+
+    #[test]
+    fn infer_frequencies() {
+        let rows: Vec<Value> = (0..50)
+            .map(|i| {
+                let tags: Vec<u64> = (0..i % 3).collect();
+                let nickname = if i % 10 == 0 {
+                    Value::Null
+                } else {
+                    json!("bob")
+                };
+                json!({ "tags": tags, "nickname": nickname })
+            })
+            .collect();
+        let samples = json!(rows);
+        let mut content = Namespace::collection(&rows[1]);
+        OptionalMergeStrategy
+            .try_merge(&mut content, &samples)
+            .unwrap();
+
+        super::infer_frequencies("users", &mut content, &samples);
+        let content = serde_json::to_value(&content).unwrap();
+
+        assert!(content["length"].get("range").is_some());
+        assert_eq!(
+            content["content"]["tags"]["length"]["categorical"],
+            json!({ "0": 17, "1": 17, "2": 16 })
+        );
+
+        let variants = content["content"]["nickname"]["variants"]
+            .as_array()
+            .unwrap();
+        let weight = |type_: &str| {
+            variants
+                .iter()
+                .find(|variant| variant["type"] == type_)
+                .unwrap()["weight"]
+                .clone()
+        };
+        assert_eq!(weight("null"), json!(5.));
+        assert_eq!(weight("string"), json!(45.));
+    }
+}
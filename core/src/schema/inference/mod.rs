@@ -9,10 +9,25 @@ use std::fmt::Display;
 pub mod value;
 pub use value::ValueMergeStrategy;
 
+pub mod samples;
+pub use samples::{visit_sampled_content, visit_sampled_fields};
+
+pub mod format;
+pub use format::{infer_formats, infer_string_format};
+
+pub mod shape;
+pub use shape::infer_string_shape;
+
+pub mod distribution;
+pub use distribution::infer_distributions;
+
+pub mod frequency;
+pub use frequency::infer_frequencies;
+
 use super::{
     number_content, ArrayContent, BoolContent, Categorical, CategoricalType, ChronoValueFormatter,
-    Content, DateTimeContent, Id, NumberContent, NumberKindExt, ObjectContent, OneOfContent,
-    RangeStep, StringContent, ValueKindExt,
+    Content, DateTimeContent, DistributionContent, Id, NumberContent, NumberKindExt, ObjectContent,
+    OneOfContent, RangeStep, StringContent, ValueKindExt,
 };
 use crate::graph::prelude::content::number_content::{I32, I64};
 use crate::schema::UniqueContent;
@@ -126,6 +141,7 @@ impl MergeStrategy<StringContent, String> for OptionalMergeStrategy {
             StringContent::Faker(_) => Ok(()),
             StringContent::Serialized(_) => Ok(()), // we can probably do better here
             StringContent::Uuid(_) => Ok(()),
+            StringContent::ObjectId(_) => Ok(()),
             StringContent::Truncated(_) => Ok(()),
             StringContent::Format(_) => Ok(()),
         }
@@ -219,6 +235,26 @@ where
     }
 }
 
+/// Widens the bounds of the distribution to the value. Its parameters are left as they are.
+impl<N> MergeStrategy<DistributionContent<N>, N> for OptionalMergeStrategy
+where
+    N: PartialOrd + Copy,
+{
+    fn try_merge(self, master: &mut DistributionContent<N>, value: &N) -> Result<()> {
+        if let Some(low) = master.low.as_mut() {
+            if *value < *low {
+                *low = *value;
+            }
+        }
+        if let Some(high) = master.high.as_mut() {
+            if *value > *high {
+                *high = *value;
+            }
+        }
+        Ok(())
+    }
+}
+
 impl<N> MergeStrategy<Categorical<N>, N> for OptionalMergeStrategy
 where
     N: Copy + CategoricalType,
@@ -263,6 +299,9 @@ impl MergeStrategy<number_content::U64, u64> for OptionalMergeStrategy {
     fn try_merge(self, master: &mut number_content::U64, candidate: &u64) -> Result<()> {
         match master {
             number_content::U64::Range(range) => self.try_merge(range, candidate),
+            number_content::U64::Distribution(distribution) => {
+                self.try_merge(distribution, candidate)
+            }
             number_content::U64::Categorical(cat) => self.try_merge(cat, candidate),
             number_content::U64::Constant(cst) => self.try_merge(cst, candidate),
             number_content::U64::Id(id) => self.try_merge(id, candidate),
@@ -274,6 +313,9 @@ impl MergeStrategy<number_content::I64, i64> for OptionalMergeStrategy {
     fn try_merge(self, master: &mut number_content::I64, candidate: &i64) -> Result<()> {
         match master {
             number_content::I64::Range(range) => self.try_merge(range, candidate),
+            number_content::I64::Distribution(distribution) => {
+                self.try_merge(distribution, candidate)
+            }
             number_content::I64::Categorical(cat) => self.try_merge(cat, candidate),
             number_content::I64::Constant(cst) => self.try_merge(cst, candidate),
             I64::Id(id) => self.try_merge(id, candidate),
@@ -285,6 +327,9 @@ impl MergeStrategy<number_content::F64, f64> for OptionalMergeStrategy {
     fn try_merge(self, master: &mut number_content::F64, candidate: &f64) -> Result<()> {
         match master {
             number_content::F64::Range(range) => self.try_merge(range, candidate),
+            number_content::F64::Distribution(distribution) => {
+                self.try_merge(distribution, candidate)
+            }
             number_content::F64::Constant(cst) => self.try_merge(cst, candidate),
         }
     }
@@ -294,6 +339,9 @@ impl MergeStrategy<number_content::U32, u32> for OptionalMergeStrategy {
     fn try_merge(self, master: &mut number_content::U32, candidate: &u32) -> Result<()> {
         match master {
             number_content::U32::Range(range) => self.try_merge(range, candidate),
+            number_content::U32::Distribution(distribution) => {
+                self.try_merge(distribution, candidate)
+            }
             number_content::U32::Categorical(cat) => self.try_merge(cat, candidate),
             number_content::U32::Constant(cst) => self.try_merge(cst, candidate),
             number_content::U32::Id(id) => self.try_merge(id, candidate),
@@ -305,6 +353,9 @@ impl MergeStrategy<number_content::I32, i32> for OptionalMergeStrategy {
     fn try_merge(self, master: &mut number_content::I32, candidate: &i32) -> Result<()> {
         match master {
             number_content::I32::Range(range) => self.try_merge(range, candidate),
+            number_content::I32::Distribution(distribution) => {
+                self.try_merge(distribution, candidate)
+            }
             number_content::I32::Categorical(cat) => self.try_merge(cat, candidate),
             number_content::I32::Constant(cst) => self.try_merge(cst, candidate),
             I32::Id(id) => self.try_merge(id, candidate),
@@ -316,6 +367,9 @@ impl MergeStrategy<number_content::F32, f32> for OptionalMergeStrategy {
     fn try_merge(self, master: &mut number_content::F32, candidate: &f32) -> Result<()> {
         match master {
             number_content::F32::Range(range) => self.try_merge(range, candidate),
+            number_content::F32::Distribution(distribution) => {
+                self.try_merge(distribution, candidate)
+            }
             number_content::F32::Constant(cst) => self.try_merge(cst, candidate),
         }
     }
@@ -0,0 +1,95 @@
+use serde_json::Value;
+
+use crate::schema::{ArrayContent, UniqueContent};
+use crate::Content;
+
+/// Visits every field of an imported collection along with its sampled values, and the path to
+/// it. Nested objects and the elements of arrays are visited in turn, nullable fields through
+/// their non null variant and unique fields through their inner content. Fields without sampled
+/// values are skipped.
+///
+/// `samples` is the array of sampled values of the collection.
+pub fn visit_sampled_fields<F>(name: &str, collection: &mut Content, samples: &Value, visit: &mut F)
+where
+    F: FnMut(&str, &str, &mut Content, &[&Value]),
+{
+    visit_sampled_content(
+        name,
+        collection,
+        samples,
+        &mut |path, field, content, values| {
+            if !is_traversed(content) {
+                visit(path, field, content, values)
+            }
+        },
+    )
+}
+
+/// Like [`visit_sampled_fields`], but also visits the objects, arrays, nullable and unique
+/// fields the fields are nested in, before their content. The collection itself is visited
+/// first, with the array of sampled values as its only value.
+pub fn visit_sampled_content<F>(
+    name: &str,
+    collection: &mut Content,
+    samples: &Value,
+    visit: &mut F,
+) where
+    F: FnMut(&str, &str, &mut Content, &[&Value]),
+{
+    visit_sampled_node(name, name, collection, vec![samples], visit)
+}
+
+/// Whether the content of `content` is visited in turn.
+fn is_traversed(content: &Content) -> bool {
+    match content {
+        Content::Object(_) | Content::Array(_) | Content::Unique(_) => true,
+        Content::OneOf(one_of) => one_of.is_nullable(),
+        _ => false,
+    }
+}
+
+fn visit_sampled_node<F>(
+    path: &str,
+    field: &str,
+    content: &mut Content,
+    values: Vec<&Value>,
+    visit: &mut F,
+) where
+    F: FnMut(&str, &str, &mut Content, &[&Value]),
+{
+    if values.is_empty() {
+        return;
+    }
+
+    visit(path, field, content, &values);
+
+    match content {
+        Content::Object(object) => {
+            for (name, content) in object.fields.iter_mut() {
+                let values = values
+                    .iter()
+                    .filter_map(|value| value.as_object()?.get(name))
+                    .collect();
+                let path = format!("{}.{}", path, name);
+                visit_sampled_node(&path, name, content, values, visit);
+            }
+        }
+        Content::Array(ArrayContent { content, .. }) => {
+            let values = values
+                .iter()
+                .filter_map(|value| value.as_array())
+                .flatten()
+                .collect();
+            visit_sampled_node(path, field, content, values, visit);
+        }
+        Content::OneOf(one_of) if one_of.is_nullable() => {
+            for variant in one_of.variants.iter_mut() {
+                visit_sampled_node(path, field, &mut variant.content, values.clone(), visit);
+            }
+        }
+        Content::Unique(UniqueContent { content, .. }) => {
+            visit_sampled_node(path, field, content, values, visit)
+        }
+        _ => {}
+    }
+}
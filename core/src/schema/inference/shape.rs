@@ -0,0 +1,214 @@
+use std::collections::BTreeSet;
+
+use crate::schema::{Content, RegexContent, StringContent};
+
+/// Shapes with more tokens than this are too specific to the sampled values to be kept.
+const MAX_TOKENS: usize = 12;
+
+/// Characters other than letters and digits which can be part of the character class of the
+/// fallback pattern. Past this, the class is restricted to letters and digits.
+const MAX_OTHER_CHARS: usize = 16;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Class {
+    Digit,
+    Upper,
+    Lower,
+    /// Both upper and lower case letters
+    Letter,
+    /// A character which is not a letter or a digit, which is kept as is
+    Other(char),
+}
+
+impl Class {
+    fn of(c: char, split_case: bool) -> Self {
+        match c {
+            '0'..='9' => Self::Digit,
+            'A'..='Z' if split_case => Self::Upper,
+            'a'..='z' if split_case => Self::Lower,
+            'a'..='z' | 'A'..='Z' => Self::Letter,
+            c => Self::Other(c),
+        }
+    }
+
+    fn regex(self) -> String {
+        match self {
+            Self::Digit => "[0-9]".to_string(),
+            Self::Upper => "[A-Z]".to_string(),
+            Self::Lower => "[a-z]".to_string(),
+            Self::Letter => "[a-zA-Z]".to_string(),
+            Self::Other(c) => regex::escape(&c.to_string()),
+        }
+    }
+}
+
+/// The content generating strings of the shape of the given values, if any: a pattern made of the
+/// runs of digits, upper case and lower case letters of the values along with the other
+/// characters between them, e.g. `ORD-[0-9]{6}`. Runs which are the same in all values, such as
+/// fixed prefixes, are kept as is. Values which don't share a shape get a character class of
+/// the characters they are made of instead, e.g. `[a-z0-9 ]{3,40}`.
+///
+/// Generated strings are never longer than the longest value, so that they fit in the same
+/// columns. Runs which are the same in all values are only kept as is if `keep_literals` is set,
+/// and get the class of their characters otherwise, so that no sampled text ends up in the
+/// pattern.
+pub fn infer_string_shape(values: &[&str], keep_literals: bool) -> Option<Content> {
+    if values.is_empty() {
+        return None;
+    }
+
+    let pattern = shape_pattern(values, true, keep_literals)
+        .or_else(|| shape_pattern(values, false, keep_literals))
+        .unwrap_or_else(|| class_pattern(values));
+    let regex = RegexContent::compile(pattern, 32).ok()?;
+    Some(Content::String(StringContent::Pattern(regex)))
+}
+
+/// The runs of characters of the same class of the value. Other characters are runs of their own.
+fn tokenize(value: &str, split_case: bool) -> Vec<(Class, &str)> {
+    let mut tokens: Vec<(Class, &str)> = Vec::new();
+    let mut start = 0;
+    for (i, c) in value.char_indices() {
+        let class = Class::of(c, split_case);
+        if let Some((last, text)) = tokens.last_mut() {
+            if *last == class && !matches!(class, Class::Other(_)) {
+                *text = &value[start..i + c.len_utf8()];
+                continue;
+            }
+        }
+        start = i;
+        tokens.push((class, &value[i..i + c.len_utf8()]));
+    }
+    tokens
+}
+
+/// The pattern of the runs all values share, if they do and it can't generate strings longer
+/// than the longest value.
+fn shape_pattern(values: &[&str], split_case: bool, keep_literals: bool) -> Option<String> {
+    let tokenized: Vec<Vec<(Class, &str)>> = values
+        .iter()
+        .map(|value| tokenize(value, split_case))
+        .collect();
+
+    let first = &tokenized[0];
+    if first.len() > MAX_TOKENS
+        || tokenized.iter().any(|tokens| {
+            tokens.len() != first.len()
+                || tokens
+                    .iter()
+                    .zip(first.iter())
+                    .any(|((class, _), (first, _))| class != first)
+        })
+    {
+        return None;
+    }
+
+    let mut pattern = String::new();
+    let mut max_len = 0;
+    for (i, (class, text)) in first.iter().enumerate() {
+        let texts = tokenized.iter().map(|tokens| tokens[i].1);
+        // A single value says nothing about which of its runs are fixed
+        if keep_literals && values.len() > 1 && texts.clone().all(|other| other == *text) {
+            pattern.push_str(&regex::escape(text));
+            max_len += text.chars().count();
+            continue;
+        }
+
+        let lengths = texts.map(|text| text.chars().count());
+        let (min, max) = (lengths.clone().min()?, lengths.max()?);
+        pattern.push_str(&class.regex());
+        if !matches!(class, Class::Other(_)) {
+            pattern.push_str(&repetition(min, max));
+        }
+        max_len += max;
+    }
+
+    let longest = values.iter().map(|value| value.chars().count()).max()?;
+    (max_len <= longest).then(|| pattern)
+}
+
+/// A character class of the characters of all values, repeated as many times as they are long.
+fn class_pattern(values: &[&str]) -> String {
+    let chars: BTreeSet<char> = values.iter().flat_map(|value| value.chars()).collect();
+    let others: Vec<char> = chars
+        .iter()
+        .copied()
+        .filter(|c| !c.is_ascii_alphanumeric())
+        .collect();
+
+    let mut class = String::new();
+    if chars.iter().any(char::is_ascii_lowercase) {
+        class.push_str("a-z");
+    }
+    if chars.iter().any(char::is_ascii_uppercase) {
+        class.push_str("A-Z");
+    }
+    if chars.iter().any(char::is_ascii_digit) {
+        class.push_str("0-9");
+    }
+    if others.len() <= MAX_OTHER_CHARS {
+        others
+            .iter()
+            .for_each(|c| class.push_str(&regex::escape(&c.to_string())));
+    } else {
+        class = "a-zA-Z0-9".to_string();
+    }
+    if class.is_empty() {
+        return String::new();
+    }
+
+    let lengths = values.iter().map(|value| value.chars().count());
+    let (min, max) = (
+        lengths.clone().min().unwrap_or(0),
+        lengths.max().unwrap_or(0),
+    );
+    format!("[{}]{}", class, repetition(min, max))
+}
+
+fn repetition(min: usize, max: usize) -> String {
+    if min == 1 && max == 1 {
+        String::new()
+    } else if min == max {
+        format!("{{{}}}", min)
+    } else {
+        format!("{{{},{}}}", min, max)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn inferred(values: &[&str]) -> String {
+        match super::infer_string_shape(values, true) {
+            Some(Content::String(StringContent::Pattern(regex))) => regex.to_string(),
+            other => panic!("expected a pattern, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn infer_string_shape() {
+        assert_eq!(inferred(&["ORD-000123", "ORD-104567"]), "ORD\\-[0-9]{6}");
+        assert_eq!(inferred(&["ABC", "XYZ", "QRS"]), "[A-Z]{3}");
+        assert_eq!(inferred(&["0042", "17", "123456"]), "[0-9]{2,6}");
+        assert_eq!(inferred(&["Alice", "Bob"]), "[A-Z][a-z]{2,4}");
+        assert_eq!(inferred(&["McDonald", "Smith"]), "[a-zA-Z]{5,8}");
+        // Both runs vary in length, so the shape could be longer than any value
+        assert_eq!(inferred(&["AAA1", "A111"]), "[A-Z0-9]{4}");
+        assert_eq!(
+            inferred(&["hello world", "bye", "see you, soon"]),
+            "[a-z ,]{3,13}"
+        );
+        assert_eq!(inferred(&["hello"]), "[a-z]{5}");
+        assert!(super::infer_string_shape(&[], true).is_none());
+
+        let private = |values| match super::infer_string_shape(values, false) {
+            Some(Content::String(StringContent::Pattern(regex))) => regex.to_string(),
+            other => panic!("expected a pattern, got {:?}", other),
+        };
+        assert_eq!(
+            private(&["ORD-000123", "ORD-104567"]),
+            "[A-Z]{3}\\-[0-9]{6}"
+        );
+    }
+}
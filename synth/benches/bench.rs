@@ -1,7 +1,7 @@
 // we can ignore irrefutable patterns here, because we might run this with or without a feature
 #![allow(irrefutable_let_patterns)]
 
-use synth::cli::{Args, Cli};
+use synth::cli::{Args, Cli, GenerateCommand};
 
 fn bench_generate_1_to_stdout() {
     bench_generate_n_to_stdout(1);
@@ -18,7 +18,7 @@ fn bench_generate_10000_to_stdout() {
 fn bench_generate_n_to_stdout(size: usize) {
     async_std::task::block_on(async {
         let namespace = std::path::PathBuf::from("testing_harness/postgres/hospital_master");
-        let args = Args::Generate {
+        let args = Args::Generate(GenerateCommand {
             namespace,
             collection: None,
             size,
@@ -26,7 +26,16 @@ fn bench_generate_n_to_stdout(size: usize) {
             seed: Some(0),
             random: false,
             schema: None,
-        };
+            transactional: false,
+            mode: Default::default(),
+            create_tables: false,
+            dialect: None,
+            copy: false,
+            pool_size: None,
+            batch_size: None,
+            statement_timeout: None,
+            concurrency: None,
+        });
         Cli::new().unwrap().run(args).await.unwrap()
     });
 }
@@ -1,4 +1,15 @@
+use crate::datasource::ddl::SqlDialect;
+use crate::datasource::{LoadParams, SampleParams};
+
 pub struct DataSourceParams {
     pub uri: Option<String>, //perhaps uri is not a good name here as this could be a file path
     pub schema: Option<String>,
+    /// The dialect of `sql:` sinks
+    pub dialect: Option<SqlDialect>,
+    /// Whether `sql:` sinks should write `COPY` blocks
+    pub copy: bool,
+    /// The load put on database sinks and sources
+    pub load: LoadParams,
+    /// The rows sampled when importing from a database
+    pub sample: SampleParams,
 }
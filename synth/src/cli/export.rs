@@ -8,13 +8,20 @@ use std::path::PathBuf;
 use crate::cli::db_utils::DataSourceParams;
 use crate::cli::mongo::MongoExportStrategy;
 use crate::cli::mysql::MySqlExportStrategy;
-use crate::datasource::DataSource;
+use crate::cli::sql::SqlScriptExportStrategy;
+use crate::datasource::ddl::SqlDialect;
+use crate::datasource::{DataSource, ExportMode, Tables};
 use crate::sampler::{Sampler, SamplerOutput};
 use async_std::task;
 use synth_core::{Name, Namespace, Value};
 
 pub(crate) trait ExportStrategy {
     fn export(&self, params: ExportParams) -> Result<SamplerOutput>;
+
+    /// Inserts existing rows, such as a subset of another database, in the given order.
+    fn insert(&self, _params: InsertParams) -> Result<()> {
+        bail!("Rows can only be inserted into Postgres or MySQL")
+    }
 }
 
 pub struct ExportParams {
@@ -23,6 +30,39 @@ pub struct ExportParams {
     pub target: usize,
     pub seed: u64,
     pub ns_path: PathBuf,
+    /// Whether the whole export should run in a single transaction and roll back on failure
+    pub transactional: bool,
+    pub mode: ExportMode,
+    /// Whether the target tables should be created from the namespace if they do not exist
+    pub create_tables: bool,
+}
+
+pub struct InsertParams {
+    pub tables: Tables,
+    pub transactional: bool,
+    pub mode: ExportMode,
+}
+
+impl InsertParams {
+    /// Inserts the tables one at a time, as they are read.
+    pub(crate) fn insert_into<T: DataSource>(self, datasource: &T) -> Result<()> {
+        if self.mode == ExportMode::Drop {
+            bail!("The 'drop' export mode is only supported for MongoDB");
+        }
+
+        if self.transactional {
+            task::block_on(datasource.begin_transaction())
+                .context("Failed to start a transaction")?;
+        }
+
+        let inserted = write_tables(datasource, self.tables, self.mode);
+
+        if self.transactional {
+            end_transaction(datasource, inserted)
+        } else {
+            inserted
+        }
+    }
 }
 
 impl TryFrom<DataSourceParams> for Box<dyn ExportStrategy> {
@@ -33,23 +73,42 @@ impl TryFrom<DataSourceParams> for Box<dyn ExportStrategy> {
     /// We assume that these can be unambiguously identified for now.
     /// For example, `postgres://...` is not going to be a file on the FS
     fn try_from(params: DataSourceParams) -> Result<Self, Self::Error> {
+        let is_sql_script = matches!(params.uri, Some(ref uri) if uri.starts_with("sql:"));
+        if !is_sql_script && (params.dialect.is_some() || params.copy) {
+            bail!("The --dialect and --copy options can only be used with a 'sql:' sink");
+        }
+
         match params.uri {
             None => Ok(Box::new(StdoutExportStrategy)),
             Some(uri) => {
-                let export_strategy: Box<dyn ExportStrategy> = if uri.starts_with("postgres://")
-                    || uri.starts_with("postgresql://")
+                let export_strategy: Box<dyn ExportStrategy> = if let Some(path) =
+                    uri.strip_prefix("sql:")
                 {
+                    Box::new(SqlScriptExportStrategy {
+                        path: PathBuf::from(path),
+                        dialect: params.dialect.unwrap_or(SqlDialect::Postgres),
+                        copy: params.copy,
+                        batch_size: params.load.batch_size(),
+                    })
+                } else if uri.starts_with("postgres://") || uri.starts_with("postgresql://") {
                     Box::new(PostgresExportStrategy {
                         uri,
                         schema: params.schema,
+                        load: params.load,
                     })
                 } else if uri.starts_with("mongodb://") {
-                    Box::new(MongoExportStrategy { uri })
+                    Box::new(MongoExportStrategy {
+                        uri,
+                        batch_size: params.load.batch_size(),
+                    })
                 } else if uri.starts_with("mysql://") || uri.starts_with("mariadb://") {
-                    Box::new(MySqlExportStrategy { uri })
+                    Box::new(MySqlExportStrategy {
+                        uri,
+                        load: params.load,
+                    })
                 } else {
                     return Err(anyhow!(
-                            "Data sink not recognized. Was expecting one of 'mongodb' or 'postgres' or 'mysql' or 'mariadb'"
+                            "Data sink not recognized. Was expecting one of 'mongodb' or 'postgres' or 'mysql' or 'mariadb' or 'sql'"
                     ));
                 };
                 Ok(export_strategy)
@@ -62,32 +121,133 @@ pub(crate) fn create_and_insert_values<T: DataSource>(
     params: ExportParams,
     datasource: &T,
 ) -> Result<SamplerOutput> {
+    if params.mode == ExportMode::Drop {
+        bail!("The 'drop' export mode is only supported for MongoDB");
+    }
+
     let sampler = Sampler::try_from(&params.namespace)?;
     let values =
         sampler.sample_seeded(params.collection_name.clone(), params.target, params.seed)?;
 
-    match values {
-        SamplerOutput::Collection(ref collection) => insert_data(
-            datasource,
-            &params.collection_name.unwrap().to_string(),
-            collection,
-        ),
-        SamplerOutput::Namespace(ref namespace) => {
-            for (name, collection) in namespace {
-                insert_data(datasource, name, collection)?;
-            }
-            Ok(())
+    let collection_name = params.collection_name.map(|name| name.to_string());
+    let collections: Vec<(&str, &[Value])> = match values {
+        SamplerOutput::Collection(ref collection) => {
+            vec![(collection_name.as_deref().unwrap(), collection)]
         }
-    }?;
+        SamplerOutput::Namespace(ref namespace) => namespace
+            .iter()
+            .map(|(name, collection)| (name.as_str(), collection.as_slice()))
+            .collect(),
+    };
+
+    // Tables are created outside of the transaction as MySQL implicitly commits on DDL statements
+    if params.create_tables {
+        let collection_names: Vec<String> = collections
+            .iter()
+            .map(|(name, _)| name.to_string())
+            .collect();
+        task::block_on(datasource.create_collections(&params.namespace, &collection_names))
+            .context("Failed to create the target tables")?;
+    }
+
+    insert_collections(
+        datasource,
+        &params.namespace,
+        &collections,
+        params.mode,
+        params.transactional,
+    )?;
 
     Ok(values)
 }
 
+/// Checks the collections against their target tables and inserts them, in the given order.
+pub(crate) fn insert_collections<T: DataSource>(
+    datasource: &T,
+    namespace: &Namespace,
+    collections: &[(&str, &[Value])],
+    mode: ExportMode,
+    transactional: bool,
+) -> Result<()> {
+    task::block_on(datasource.check_collections(namespace, collections))?;
+
+    if transactional {
+        task::block_on(datasource.begin_transaction()).context("Failed to start a transaction")?;
+    }
+
+    let inserted = write_collections(datasource, collections, mode);
+
+    if transactional {
+        end_transaction(datasource, inserted)
+    } else {
+        inserted
+    }
+}
+
+/// Commits the transaction if all inserts succeeded, otherwise rolls it back and returns the
+/// original error.
+fn end_transaction<T: DataSource>(datasource: &T, inserted: Result<()>) -> Result<()> {
+    match inserted {
+        Ok(()) => task::block_on(datasource.commit_transaction())
+            .context("Failed to commit the transaction"),
+        Err(e) => {
+            if let Err(rollback_err) = task::block_on(datasource.rollback_transaction()) {
+                warn!("Failed to roll back the transaction: {:?}", rollback_err);
+            } else {
+                info!("Rolled back the transaction. No data was inserted.");
+            }
+            Err(e)
+        }
+    }
+}
+
+fn write_collections<T: DataSource>(
+    datasource: &T,
+    collections: &[(&str, &[Value])],
+    mode: ExportMode,
+) -> Result<()> {
+    if mode == ExportMode::Truncate {
+        let collection_names: Vec<String> = collections
+            .iter()
+            .map(|(name, _)| name.to_string())
+            .collect();
+        clear_collections(datasource, &collection_names)?;
+    }
+
+    collections
+        .iter()
+        .try_for_each(|(name, collection)| insert_data(datasource, name, collection, mode))
+}
+
+/// Checks and inserts every table as soon as it is read, such that only one table is held at a
+/// time.
+fn write_tables<T: DataSource>(datasource: &T, tables: Tables, mode: ExportMode) -> Result<()> {
+    if mode == ExportMode::Truncate {
+        clear_collections(datasource, &tables.names)?;
+    }
+
+    for table in tables.rows {
+        let (name, rows) = table?;
+        task::block_on(
+            datasource.check_collections(&Namespace::default(), &[(name.as_str(), &rows)]),
+        )?;
+        insert_data(datasource, &name, &rows, mode)?;
+    }
+
+    Ok(())
+}
+
+fn clear_collections<T: DataSource>(datasource: &T, collection_names: &[String]) -> Result<()> {
+    task::block_on(datasource.clear_collections(collection_names))
+        .context("Failed to clear the target collections")
+}
+
 fn insert_data<T: DataSource>(
     datasource: &T,
     collection_name: &str,
     collection: &[Value],
+    mode: ExportMode,
 ) -> Result<()> {
-    task::block_on(datasource.insert_data(collection_name, collection))
+    task::block_on(datasource.insert_data(collection_name, collection, mode))
         .with_context(|| format!("Failed to insert data for collection {}", collection_name))
 }
@@ -10,19 +10,43 @@ use synth_core::schema::Namespace;
 use synth_core::{Content, Name};
 
 use crate::cli::db_utils::DataSourceParams;
+use crate::cli::import_utils::refine_collection;
 use crate::cli::mongo::MongoImportStrategy;
 use crate::cli::mysql::MySqlImportStrategy;
+use crate::cli::pii::Finding;
 use crate::cli::postgres::PostgresImportStrategy;
 use crate::cli::stdf::{FileImportStrategy, StdinImportStrategy};
+use crate::datasource::{Privacy, Tables};
 
 pub trait ImportStrategy {
-    fn import(&self) -> Result<Namespace> {
-        ns_from_value(self.as_value()?)
+    /// Imports the namespace of the source, along with the fields which were replaced as they
+    /// look like personal data.
+    fn import(&self) -> Result<(Namespace, Vec<Finding>)> {
+        ns_from_value(self.as_value()?, self.privacy())
     }
-    fn import_collection(&self, _name: &Name) -> Result<Content> {
-        collection_from_value(&self.as_value()?)
+    fn import_collection(&self, name: &Name) -> Result<(Content, Vec<Finding>)> {
+        collection_from_value(name, &self.as_value()?, self.privacy())
     }
     fn as_value(&self) -> Result<Value>;
+    /// The noise to add to the statistics inferred from the value of the source, if any.
+    fn privacy(&self) -> Option<&Privacy> {
+        None
+    }
+    /// Collects a referentially intact subset of the source, starting from the rows of `root`
+    /// matching `condition`. Tables are returned in the order in which they can be inserted.
+    fn subset(
+        &self,
+        _root: &str,
+        _condition: &str,
+    ) -> Result<Vec<(String, Vec<synth_core::Value>)>> {
+        bail!("Subsets can only be collected from Postgres or MySQL")
+    }
+    /// Reads every table of the source, replacing the columns which are part of `masks` with
+    /// generated values. `secret` keys the pseudonymised columns which don't have their own.
+    /// Tables are read one at a time, in the order in which they can be inserted.
+    fn mask(&self, _masks: &Namespace, _seed: u64, _secret: Option<&str>) -> Result<Tables> {
+        bail!("Masking is only supported from Postgres or MySQL")
+    }
 }
 
 impl TryFrom<DataSourceParams> for Box<dyn ImportStrategy> {
@@ -30,20 +54,34 @@ impl TryFrom<DataSourceParams> for Box<dyn ImportStrategy> {
 
     fn try_from(params: DataSourceParams) -> Result<Self, Self::Error> {
         match params.uri {
-            None => Ok(Box::new(StdinImportStrategy)),
+            None => Ok(Box::new(StdinImportStrategy {
+                privacy: params.sample.privacy,
+            })),
             Some(uri) => {
                 let import_strategy: Box<dyn ImportStrategy> =
                     if uri.starts_with("postgres://") || uri.starts_with("postgresql://") {
                         Box::new(PostgresImportStrategy {
                             uri,
                             schema: params.schema,
+                            sample: params.sample,
+                            load: params.load,
                         })
                     } else if uri.starts_with("mongodb://") {
-                        Box::new(MongoImportStrategy { uri })
+                        Box::new(MongoImportStrategy {
+                            uri,
+                            sample: params.sample,
+                        })
                     } else if uri.starts_with("mysql://") || uri.starts_with("mariadb://") {
-                        Box::new(MySqlImportStrategy { uri })
+                        Box::new(MySqlImportStrategy {
+                            uri,
+                            sample: params.sample,
+                            load: params.load,
+                        })
                     } else if let Ok(path) = PathBuf::from_str(&uri) {
-                        Box::new(FileImportStrategy { from_file: path })
+                        Box::new(FileImportStrategy {
+                            from_file: path,
+                            privacy: params.sample.privacy,
+                        })
                     } else {
                         return Err(anyhow!(
                          "Data source not recognized. Was expecting one of 'mongodb' or 'postgres'"
@@ -55,13 +93,31 @@ impl TryFrom<DataSourceParams> for Box<dyn ImportStrategy> {
     }
 }
 
-fn collection_from_value(value: &Value) -> Result<Content> {
+/// The collection `name` of an imported namespace, along with the replaced fields of its own.
+pub(crate) fn take_collection(
+    (mut namespace, findings): (Namespace, Vec<Finding>),
+    name: &Name,
+) -> Option<(Content, Vec<Finding>)> {
+    let content = namespace.collections.remove(name)?;
+    let findings = findings
+        .into_iter()
+        .filter(|finding| finding.collection == name.as_ref())
+        .collect();
+    Some((content, findings))
+}
+
+fn collection_from_value(
+    name: &Name,
+    value: &Value,
+    privacy: Option<&Privacy>,
+) -> Result<(Content, Vec<Finding>)> {
     match value {
         Value::Array(values) => {
             let fst = values.get(0).unwrap_or(&Value::Null);
             let mut as_content = Namespace::collection(fst);
             OptionalMergeStrategy.try_merge(&mut as_content, value)?;
-            Ok(as_content)
+            let findings = refine_collection(name.as_ref(), &mut as_content, value, privacy);
+            Ok((as_content, findings))
         }
         unacceptable => Err(anyhow!(
             "Was expecting a collection, instead got `{}`",
@@ -70,16 +126,25 @@ fn collection_from_value(value: &Value) -> Result<Content> {
     }
 }
 
-fn ns_from_value(value: Value) -> Result<Namespace> {
+fn ns_from_value(value: Value, privacy: Option<&Privacy>) -> Result<(Namespace, Vec<Finding>)> {
     match value {
-        Value::Object(object) => object
-            .into_iter()
-            .map(|(name, value)| {
-                collection_from_value(&value)
-                    .and_then(|content| Ok((name.parse()?, content)))
-                    .with_context(|| anyhow!("While importing the collection `{}`", name))
-            })
-            .collect(),
+        Value::Object(object) => {
+            let mut namespace = Namespace::default();
+            let mut findings = Vec::new();
+            for (name, value) in object {
+                let (parsed, content) = Name::from_str(&name)
+                    .map_err(anyhow::Error::from)
+                    .and_then(|parsed| {
+                        let (content, collection_findings) =
+                            collection_from_value(&parsed, &value, privacy)?;
+                        findings.extend(collection_findings);
+                        Ok((parsed, content))
+                    })
+                    .with_context(|| anyhow!("While importing the collection `{}`", name))?;
+                namespace.collections.insert(parsed, content);
+            }
+            Ok((namespace, findings))
+        }
         unacceptable => Err(anyhow!(
             "Was expecting an object, instead got `{}`",
             unacceptable
@@ -1,12 +1,14 @@
 use crate::datasource::relational_datasource::{ColumnInfo, RelationalDataSource};
-use crate::datasource::DataSource;
+use crate::datasource::{DataSource, Privacy, SampleParams};
 use anyhow::{Context, Result};
 use async_std::task;
 use log::debug;
+use rand::thread_rng;
 use serde_json::Value;
 use std::convert::TryFrom;
 use std::str::FromStr;
 use synth_core::schema::content::number_content::U64;
+use synth_core::schema::inference::{infer_distributions, infer_formats, infer_frequencies};
 use synth_core::schema::{
     ArrayContent, FieldRef, NumberContent, ObjectContent, OptionalMergeStrategy, RangeStep,
     SameAsContent, UniqueContent,
@@ -14,6 +16,8 @@ use synth_core::schema::{
 use synth_core::{Content, Name, Namespace};
 
 use super::json::synth_val_to_json;
+use super::pii::{report_findings, scan_collection, Finding};
+use super::privacy::add_noise;
 
 #[derive(Debug)]
 pub(crate) struct Collection {
@@ -23,9 +27,12 @@ pub(crate) struct Collection {
 /// Wrapper around `FieldContent` since we cant' impl `TryFrom` on a struct in a non-owned crate
 struct FieldContentWrapper(Content);
 
+/// Imports the namespace of the tables of the datasource, along with the fields which were
+/// replaced as they look like personal data.
 pub(crate) fn build_namespace_import<T: DataSource + RelationalDataSource>(
     datasource: &T,
-) -> Result<Namespace> {
+    sample: &SampleParams,
+) -> Result<(Namespace, Vec<Finding>)> {
     let table_names = task::block_on(datasource.get_table_names())
         .with_context(|| "Failed to get table names".to_string())?;
 
@@ -41,9 +48,9 @@ pub(crate) fn build_namespace_import<T: DataSource + RelationalDataSource>(
     populate_namespace_foreign_keys(&mut namespace, datasource)?;
 
     info!("Building namespace values...");
-    populate_namespace_values(&mut namespace, &table_names, datasource)?;
+    let findings = populate_namespace_values(&mut namespace, &table_names, datasource, sample)?;
 
-    Ok(namespace)
+    Ok((namespace, findings))
 }
 
 fn populate_namespace_collections<T: DataSource + RelationalDataSource>(
@@ -128,20 +135,53 @@ fn populate_namespace_values<T: DataSource + RelationalDataSource>(
     namespace: &mut Namespace,
     table_names: &[String],
     datasource: &T,
-) -> Result<()> {
-    task::block_on(datasource.set_seed())?;
-
+    sample: &SampleParams,
+) -> Result<Vec<Finding>> {
+    let mut findings = Vec::new();
     for table in table_names {
-        let values = task::block_on(datasource.get_deterministic_samples(table))?;
+        // Reset the seed for every table, so that its samples don't depend on the other tables
+        task::block_on(datasource.set_seed(sample.seed))?;
+        let values = task::block_on(datasource.get_deterministic_samples(table, sample))?;
         let json_values: Vec<Value> = values.into_iter().map(synth_val_to_json).collect();
-        namespace.try_update(
-            OptionalMergeStrategy,
-            &Name::from_str(table).unwrap(),
-            &Value::from(json_values),
-        )?;
+        let name = Name::from_str(table).unwrap();
+        let json_values = Value::from(json_values);
+        namespace.try_update(OptionalMergeStrategy, &name, &json_values)?;
+
+        let collection = namespace.get_collection_mut(&name)?;
+        findings.extend(refine_collection(
+            table,
+            collection,
+            &json_values,
+            sample.privacy.as_ref(),
+        ));
     }
 
-    Ok(())
+    Ok(findings)
+}
+
+/// Refines the content merged from the sampled values of an imported collection, using all of
+/// them at once: fields which look like personal data are replaced by fakers, the other string
+/// fields get content of their format or shape, number fields get the distribution of their
+/// values, arrays and nullable fields get the frequencies of their lengths and null values and
+/// noise is added to the inferred statistics if `privacy` is given. Patterns don't copy sampled
+/// text if `privacy` is given. Returns the fields replaced by fakers.
+///
+/// `samples` is the array of sampled values of the collection.
+pub(crate) fn refine_collection(
+    name: &str,
+    collection: &mut Content,
+    samples: &Value,
+    privacy: Option<&Privacy>,
+) -> Vec<Finding> {
+    let findings = scan_collection(name, collection, samples);
+    report_findings(&findings);
+    infer_formats(name, collection, samples, privacy.is_none());
+    infer_distributions(name, collection, samples);
+    infer_frequencies(name, collection, samples);
+    if let Some(privacy) = privacy {
+        add_noise(name, collection, samples, privacy, &mut thread_rng());
+    }
+    findings
 }
 
 impl<T: RelationalDataSource + DataSource> TryFrom<(&T, Vec<ColumnInfo>)> for Collection {
@@ -173,10 +213,7 @@ impl<T: RelationalDataSource + DataSource> TryFrom<(&T, &ColumnInfo)> for FieldC
     type Error = anyhow::Error;
 
     fn try_from(column_meta: (&T, &ColumnInfo)) -> Result<Self> {
-        let data_type = &column_meta.1.data_type;
-        let mut content = column_meta
-            .0
-            .decode_to_content(data_type, column_meta.1.character_maximum_length)?;
+        let mut content = column_meta.0.decode_to_content(column_meta.1)?;
 
         if column_meta.1.is_nullable {
             content = content.into_nullable();
@@ -0,0 +1,400 @@
+use super::pii::Finding;
+use std::fmt::{self, Display, Formatter};
+use synth_core::schema::{ArrayContent, ObjectContent};
+use synth_core::{Content, Name, Namespace};
+
+/// A difference between a stored namespace and a freshly imported one.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Change {
+    AddedCollection(Name),
+    /// The collection no longer exists in the source. It is kept in the namespace.
+    DroppedCollection(Name),
+    AddedField(Name, String),
+    /// The field no longer exists in the source. It is kept in the namespace.
+    DroppedField(Name, String),
+    /// The field is replaced by the imported content, as its type changed.
+    ChangedType {
+        collection: Name,
+        field: String,
+        from: &'static str,
+        to: &'static str,
+    },
+    ChangedNullability {
+        collection: Name,
+        field: String,
+        nullable: bool,
+    },
+}
+
+impl Change {
+    /// Whether the namespace may need to be edited by hand because of this change.
+    pub(crate) fn needs_attention(&self) -> bool {
+        matches!(
+            self,
+            Self::DroppedCollection(_) | Self::DroppedField(..) | Self::ChangedType { .. }
+        )
+    }
+
+    /// Whether the content of `field` of `collection` was taken from the import by this change.
+    pub(crate) fn imports_field(&self, collection: &str, field: &str) -> bool {
+        match self {
+            Self::AddedCollection(name) => name.as_ref() == collection,
+            Self::AddedField(name, added) => name.as_ref() == collection && added == field,
+            Self::ChangedType {
+                collection: name,
+                field: changed,
+                ..
+            } => name.as_ref() == collection && changed == field,
+            _ => false,
+        }
+    }
+}
+
+impl Display for Change {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::AddedCollection(collection) => write!(f, "Added collection {}", collection),
+            Self::DroppedCollection(collection) => write!(
+                f,
+                "Collection {} no longer exists in the source. It was kept in the namespace",
+                collection
+            ),
+            Self::AddedField(collection, field) => {
+                write!(f, "Added field {}.{}", collection, field)
+            }
+            Self::DroppedField(collection, field) => write!(
+                f,
+                "Field {}.{} no longer exists in the source. It was kept in the namespace",
+                collection, field
+            ),
+            Self::ChangedType {
+                collection,
+                field,
+                from,
+                to,
+            } => write!(
+                f,
+                "The type of field {}.{} changed from {} to {}. Its content was replaced",
+                collection, field, from, to
+            ),
+            Self::ChangedNullability {
+                collection,
+                field,
+                nullable: true,
+            } => write!(f, "Field {}.{} became nullable", collection, field),
+            Self::ChangedNullability {
+                collection,
+                field,
+                nullable: false,
+            } => write!(f, "Field {}.{} is no longer nullable", collection, field),
+        }
+    }
+}
+
+/// Merges a freshly imported namespace into a stored one. New collections and fields are added,
+/// fields whose type changed are replaced and the nullability of the others is updated, while
+/// the content of unchanged fields is left as is. Collections and fields which are missing from
+/// the import are kept, and only reported.
+pub(crate) fn merge_namespace(stored: &mut Namespace, imported: Namespace) -> Vec<Change> {
+    let mut changes: Vec<Change> = stored
+        .keys()
+        .filter(|name| !imported.collection_exists(name))
+        .map(|name| Change::DroppedCollection(name.clone()))
+        .collect();
+
+    for (name, content) in imported {
+        match stored.collections.get_mut(&name) {
+            Some(stored_content) => {
+                changes.extend(merge_collection(&name, stored_content, content));
+            }
+            None => {
+                stored.collections.insert(name.clone(), content);
+                changes.push(Change::AddedCollection(name));
+            }
+        }
+    }
+
+    changes
+}
+
+/// Merges the freshly imported content of a collection into its stored content. Collections
+/// which are not arrays of objects on both sides are left untouched.
+pub(crate) fn merge_collection(
+    name: &Name,
+    stored: &mut Content,
+    imported: Content,
+) -> Vec<Change> {
+    let (stored, imported) = match (as_object_mut(stored), into_object(imported)) {
+        (Some(stored), Some(imported)) => (stored, imported),
+        _ => return Vec::new(),
+    };
+
+    let mut changes: Vec<Change> = stored
+        .fields
+        .keys()
+        .filter(|field| !imported.fields.contains_key(*field))
+        .map(|field| Change::DroppedField(name.clone(), field.clone()))
+        .collect();
+
+    for (field, content) in imported.fields {
+        match stored.fields.get_mut(&field) {
+            Some(stored_content) => {
+                changes.extend(merge_field(name, &field, stored_content, content));
+            }
+            None => {
+                stored.fields.insert(field.clone(), content);
+                changes.push(Change::AddedField(name.clone(), field));
+            }
+        }
+    }
+
+    changes
+}
+
+/// Logs the changes of a merge. Changes which may need the namespace to be edited by hand are
+/// logged as warnings.
+pub(crate) fn report_changes(changes: &[Change]) {
+    if changes.is_empty() {
+        info!("The namespace is up to date");
+    }
+
+    for change in changes {
+        if change.needs_attention() {
+            warn!("{}", change);
+        } else {
+            info!("{}", change);
+        }
+    }
+}
+
+/// The findings of an import whose replaced fields were saved by a merge. The other fields keep
+/// their stored content.
+pub(crate) fn merged_findings(findings: Vec<Finding>, changes: &[Change]) -> Vec<Finding> {
+    findings
+        .into_iter()
+        .filter(|finding| {
+            changes
+                .iter()
+                .any(|change| change.imports_field(&finding.collection, finding.top_level_field()))
+        })
+        .collect()
+}
+
+fn merge_field(
+    collection: &Name,
+    field: &str,
+    stored: &mut Content,
+    imported: Content,
+) -> Option<Change> {
+    let stored_type = value_type(stored.as_nullable().unwrap_or(stored));
+    let imported_type = value_type(imported.as_nullable().unwrap_or(&imported));
+
+    // Fields whose type can't be told apart, such as references, are customisations we keep
+    if let (Some(from), Some(to)) = (stored_type, imported_type) {
+        if !same_value_type(from, to) {
+            *stored = imported;
+            return Some(Change::ChangedType {
+                collection: collection.clone(),
+                field: field.to_string(),
+                from,
+                to,
+            });
+        }
+    }
+
+    let nullable = imported.is_nullable();
+    if stored.is_nullable() == nullable {
+        return None;
+    }
+
+    *stored = match stored.as_nullable() {
+        Some(content) => content.clone(),
+        None => stored.clone().into_nullable(),
+    };
+
+    Some(Change::ChangedNullability {
+        collection: collection.clone(),
+        field: field.to_string(),
+        nullable,
+    })
+}
+
+/// The type of the values generated by the content, if it can be told without following
+/// references.
+fn value_type(content: &Content) -> Option<&'static str> {
+    match content {
+        Content::Bool(_) => Some("bool"),
+        Content::Number(number) if number.is_f32() || number.is_f64() => Some("float"),
+        Content::Number(_) => Some("integer"),
+        Content::String(_) => Some("string"),
+        Content::DateTime(_) => Some("date_time"),
+        Content::Array(_) => Some("array"),
+        Content::Object(_) => Some("object"),
+        Content::Unique(unique) => value_type(&unique.content),
+        Content::Hidden(hidden) => value_type(&hidden.content),
+        Content::Pseudonymise(pseudonymise) => value_type(&pseudonymise.content),
+        _ => None,
+    }
+}
+
+/// Date times are generated as strings and imports infer them from the strings of every source, so
+/// a string written by hand and the date time inferred from its values are the same type.
+fn same_value_type(stored: &str, imported: &str) -> bool {
+    stored == imported
+        || matches!(
+            (stored, imported),
+            ("string", "date_time") | ("date_time", "string")
+        )
+}
+
+fn as_object_mut(collection: &mut Content) -> Option<&mut ObjectContent> {
+    match collection {
+        Content::Array(ArrayContent { content, .. }) => match content.as_mut() {
+            Content::Object(object) => Some(object),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+fn into_object(collection: Content) -> Option<ObjectContent> {
+    match collection {
+        Content::Array(ArrayContent { content, .. }) => match *content {
+            Content::Object(object) => Some(object),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::pii::PiiKind;
+    use serde_json::json;
+    use std::str::FromStr;
+    use synth_core::schema::FieldRef;
+
+    fn namespace(value: serde_json::Value) -> Namespace {
+        serde_json::from_value(value).unwrap()
+    }
+
+    fn collection(fields: serde_json::Value) -> serde_json::Value {
+        let mut content = json!({ "type": "object" });
+        content
+            .as_object_mut()
+            .unwrap()
+            .extend(fields.as_object().unwrap().clone());
+        json!({
+            "type": "array",
+            "length": { "type": "number", "constant": 1 },
+            "content": content
+        })
+    }
+
+    #[test]
+    fn merge_namespace() {
+        let mut stored = namespace(json!({
+            "users": collection(json!({
+                "id": { "type": "number", "id": {} },
+                "name": { "type": "string", "faker": { "generator": "name" } },
+                "age": { "type": "number", "range": { "low": 0, "high": 100, "step": 1 } },
+                "nickname": { "type": "string", "pattern": "[a-z]{5}" },
+                "manager": { "type": "same_as", "ref": "users.content.id" },
+                "joined": { "type": "string", "pattern": "20[0-9]{2}" },
+                "seen": { "type": "date_time", "format": "%Y-%m-%d", "subtype": "naive_date" }
+            })),
+            "logs": collection(json!({ "id": { "type": "number", "id": {} } }))
+        }));
+
+        let imported_age = json!({ "type": "string", "pattern": "[0-9]{2}" });
+        let imported = namespace(json!({
+            "users": collection(json!({
+                "id": { "type": "number", "id": {} },
+                "name": {
+                    "type": "one_of",
+                    "variants": [{ "type": "string", "pattern": "[a-z]{3}" }, { "type": "null" }]
+                },
+                "age": imported_age,
+                "email": { "type": "string", "pattern": "[a-z]+@example.com" },
+                "manager": { "type": "number", "range": { "low": 0, "high": 1, "step": 1 } },
+                "joined": { "type": "date_time", "format": "%Y-%m-%d", "subtype": "naive_date" },
+                "seen": { "type": "string", "pattern": "[a-zA-Z0-9]*" }
+            })),
+            "orders": collection(json!({ "id": { "type": "number", "id": {} } }))
+        }));
+
+        let changes = super::merge_namespace(&mut stored, imported.clone());
+        let users = Name::from_str("users").unwrap();
+
+        assert_eq!(
+            changes,
+            vec![
+                Change::DroppedCollection(Name::from_str("logs").unwrap()),
+                Change::AddedCollection(Name::from_str("orders").unwrap()),
+                Change::DroppedField(users.clone(), "nickname".to_string()),
+                Change::ChangedType {
+                    collection: users.clone(),
+                    field: "age".to_string(),
+                    from: "integer",
+                    to: "string"
+                },
+                Change::AddedField(users.clone(), "email".to_string()),
+                Change::ChangedNullability {
+                    collection: users,
+                    field: "name".to_string(),
+                    nullable: true
+                },
+            ]
+        );
+
+        let field = |name: &str| {
+            serde_json::to_value(
+                stored
+                    .get_s_node(&FieldRef::new(&format!("users.content.{}", name)).unwrap())
+                    .unwrap(),
+            )
+            .unwrap()
+        };
+        assert_eq!(field("age"), imported_age);
+        assert_eq!(field("manager")["type"], "same_as");
+        assert_eq!(field("nickname")["pattern"], "[a-z]{5}");
+        assert_eq!(field("joined")["pattern"], "20[0-9]{2}");
+        assert_eq!(field("seen")["type"], "date_time");
+        let name = stored
+            .get_s_node(&FieldRef::new("users.content.name").unwrap())
+            .unwrap()
+            .as_nullable()
+            .unwrap();
+        assert_eq!(
+            serde_json::to_value(name).unwrap()["faker"]["generator"],
+            "name"
+        );
+        assert!(stored.collection_exists(&Name::from_str("logs").unwrap()));
+        assert!(stored.collection_exists(&Name::from_str("orders").unwrap()));
+
+        // Only the replaced fields which were saved are reported
+        let finding = |collection: &str, field: &str| Finding {
+            collection: collection.to_string(),
+            field: field.to_string(),
+            kind: PiiKind::Email,
+            matched: 1,
+            sampled: 1,
+        };
+        let findings = vec![
+            finding("users", "users.name"),
+            finding("users", "users.email"),
+            finding("orders", "orders.contact.email"),
+        ];
+        let saved: Vec<String> = merged_findings(findings, &changes)
+            .into_iter()
+            .map(|finding| finding.field)
+            .collect();
+        assert_eq!(saved, vec!["users.email", "orders.contact.email"]);
+
+        // Merging the same import again only reports what was dropped
+        let changes = super::merge_namespace(&mut stored, imported);
+        assert_eq!(changes.len(), 2);
+        assert!(changes.iter().all(Change::needs_attention));
+    }
+}
@@ -1,16 +1,26 @@
 mod export;
 mod import;
 mod import_utils;
+mod merge;
 mod mongo;
 mod mysql;
+mod pii;
 mod postgres;
+mod privacy;
+mod project;
+mod sql;
 mod stdf;
 mod store;
 
 use crate::cli::db_utils::DataSourceParams;
-use crate::cli::export::{ExportParams, ExportStrategy};
+use crate::cli::export::{ExportParams, ExportStrategy, InsertParams};
 use crate::cli::import::ImportStrategy;
+use crate::cli::merge::{merge_collection, merge_namespace, merged_findings, report_changes};
+use crate::cli::pii::print_findings_summary;
+use crate::cli::project::ProjectConfig;
 use crate::cli::store::Store;
+use crate::datasource::ddl::{create_table_statements, SqlDialect};
+use crate::datasource::{ExportMode, LoadParams, Privacy, SampleParams, SampleStrategy};
 use crate::version::print_version_message;
 
 use anyhow::{Context, Result};
@@ -18,7 +28,7 @@ use rand::RngCore;
 use serde::Serialize;
 use std::cell::Cell;
 use std::convert::TryInto;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::process::exit;
 use structopt::clap::AppSettings;
 use structopt::StructOpt;
@@ -37,6 +47,9 @@ pub mod telemetry;
 #[cfg(feature = "telemetry")]
 use telemetry::{TelemetryContext, TelemetryExportStrategy};
 
+/// The environment variable holding the secret keying the pseudonyms of `synth mask`.
+const MASK_SECRET_VAR: &str = "SYNTH_MASK_SECRET";
+
 pub struct Cli {
     store: Store,
     export_strategy: Cell<Option<Box<dyn ExportStrategy>>>,
@@ -79,36 +92,36 @@ impl Cli {
         }
     }
 
+    /// The secret keying pseudonyms, read from the secret file if given or else from the
+    /// environment. It is never passed on the command line, which would leak it to the shell
+    /// history and the process list.
+    fn mask_secret(secret_file: Option<&Path>) -> Result<Option<String>> {
+        match secret_file {
+            Some(path) => {
+                let secret = std::fs::read_to_string(path).with_context(|| {
+                    format!("Failed to read the secret from {}", path.display())
+                })?;
+                Ok(Some(secret.trim_end_matches(&['\r', '\n'][..]).to_string()))
+            }
+            None => Ok(std::env::var(MASK_SECRET_VAR).ok()),
+        }
+    }
+
+    /// Fills the settings which are not given on the command line from the project config file.
+    fn load_params(load: LoadParams) -> Result<LoadParams> {
+        load.validate()?;
+        let project_dir = std::env::current_dir().context("Failed to get the current directory")?;
+        Ok(load.or(ProjectConfig::read(&project_dir)?.load))
+    }
+
     pub async fn run(&self, args: Args) -> Result<()> {
         match args {
             Args::Init { .. } => Ok(()),
-            Args::Generate {
-                ref namespace,
-                ref collection,
-                size,
-                ref to,
-                seed,
-                random,
-                schema,
-            } => self.generate(
-                namespace.clone(),
-                collection.clone(),
-                size,
-                to.clone(),
-                Self::derive_seed(random, seed)?,
-                schema,
-            ),
-            Args::Import {
-                ref namespace,
-                ref collection,
-                ref from,
-                ref schema,
-            } => self.import(
-                namespace.clone(),
-                collection.clone(),
-                from.clone(),
-                schema.clone(),
-            ),
+            Args::Generate(cmd) => self.generate(cmd),
+            Args::Import(cmd) => self.import(cmd),
+            Args::Subset(cmd) => self.subset(cmd),
+            Args::Mask(cmd) => self.mask(cmd),
+            Args::Schema(cmd) => self.schema(cmd),
             #[cfg(feature = "telemetry")]
             Args::Telemetry(cmd) => self.telemetry(cmd),
             Args::Version => {
@@ -135,39 +148,101 @@ impl Cli {
         }
     }
 
-    fn import(
-        &self,
-        path: PathBuf,
-        collection: Option<Name>,
-        from: Option<String>,
-        schema: Option<String>,
-    ) -> Result<()> {
-        // TODO: If ns exists and no collection: break
-        // If collection and ns exists and collection exists: break
+    fn schema(&self, cmd: SchemaCommand) -> Result<()> {
+        match cmd {
+            SchemaCommand::Ddl { namespace, dialect } => {
+                let ns = self.store.get_ns(namespace.clone()).with_context(|| {
+                    format!("Unable to open the namespace \"{}\"", namespace.display())
+                })?;
+
+                for statement in create_table_statements(&ns, dialect)? {
+                    println!("{}\n", statement);
+                }
+
+                Ok(())
+            }
+        }
+    }
 
-        let import_strategy: Box<dyn ImportStrategy> =
-            DataSourceParams { uri: from, schema }.try_into()?;
+    fn import(&self, cmd: ImportCommand) -> Result<()> {
+        let path = cmd.namespace;
+        let collection = cmd.collection;
+        let merge = cmd.merge;
+
+        let import_strategy: Box<dyn ImportStrategy> = DataSourceParams {
+            uri: cmd.from,
+            schema: cmd.schema,
+            dialect: None,
+            copy: false,
+            sample: SampleParams {
+                size: cmd.sample_size,
+                strategy: cmd.sample_strategy,
+                seed: cmd.seed.unwrap_or(0),
+                privacy: cmd.privacy,
+            },
+            load: Self::load_params(LoadParams {
+                pool_size: cmd.pool_size,
+                statement_timeout: cmd.statement_timeout,
+                ..Default::default()
+            })?,
+        }
+        .try_into()?;
 
         if let Some(collection) = collection {
-            if self.store.collection_exists(&path, &collection) {
-                return Err(anyhow!("The collection `{}` already exists. Will not import into an existing collection.",Store::relative_collection_path(&path, &collection).display()));
+            if merge && self.store.collection_exists(&path, &collection) {
+                let mut content = self.store.get_collection_path(&path, &collection)?;
+                let (imported, findings) = import_strategy.import_collection(&collection)?;
+                let changes = merge_collection(&collection, &mut content, imported);
+                report_changes(&changes);
+                self.store
+                    .save_collection_path(&path, collection, content)?;
+                print_findings_summary(&merged_findings(findings, &changes));
+
+                #[cfg(feature = "telemetry")]
+                self.telemetry_context.borrow_mut().set_num_collections(1);
+
+                Ok(())
+            } else if self.store.collection_exists(&path, &collection) {
+                Err(anyhow!("The collection `{}` already exists. Will not import into an existing collection. Use --merge to update it instead.",Store::relative_collection_path(&path, &collection).display()))
             } else {
-                let content = import_strategy.import_collection(&collection)?;
+                let (content, findings) = import_strategy.import_collection(&collection)?;
                 self.store
                     .save_collection_path(&path, collection, content)?;
+                print_findings_summary(&findings);
 
                 #[cfg(feature = "telemetry")]
                 self.telemetry_context.borrow_mut().set_num_collections(1);
 
                 Ok(())
             }
+        } else if merge && self.store.ns_exists(&path) {
+            let mut ns = self
+                .store
+                .get_ns(path.clone())
+                .with_context(|| format!("Unable to open the namespace \"{}\"", path.display()))?;
+            let (imported, findings) = import_strategy.import()?;
+            let changes = merge_namespace(&mut ns, imported);
+            report_changes(&changes);
+
+            #[cfg(feature = "telemetry")]
+            TelemetryExportStrategy::fill_telemetry_pre(
+                Rc::clone(&self.telemetry_context),
+                &ns,
+                None,
+                path.clone(),
+            )?;
+
+            self.store.save_ns_path(path, ns)?;
+            print_findings_summary(&merged_findings(findings, &changes));
+
+            Ok(())
         } else if self.store.ns_exists(&path) {
             Err(anyhow!(
-                "The directory at `{}` already exists. Will not import into an existing directory.",
+                "The directory at `{}` already exists. Will not import into an existing directory. Use --merge to update it instead.",
                 path.display()
             ))
         } else {
-            let ns = import_strategy.import()?;
+            let (ns, findings) = import_strategy.import()?;
 
             #[cfg(feature = "telemetry")]
             TelemetryExportStrategy::fill_telemetry_pre(
@@ -178,20 +253,90 @@ impl Cli {
             )?;
 
             self.store.save_ns_path(path, ns)?;
+            print_findings_summary(&findings);
 
             Ok(())
         }
     }
 
-    fn generate(
-        &self,
-        ns_path: PathBuf,
-        collection: Option<Name>,
-        target: usize,
-        to: Option<String>,
-        seed: u64,
-        schema: Option<String>,
-    ) -> Result<()> {
+    fn subset(&self, cmd: SubsetCommand) -> Result<()> {
+        let load = Self::load_params(LoadParams::default())?;
+
+        let import_strategy: Box<dyn ImportStrategy> = DataSourceParams {
+            uri: Some(cmd.from),
+            schema: None,
+            dialect: None,
+            copy: false,
+            sample: SampleParams::default(),
+            load,
+        }
+        .try_into()?;
+
+        let export_strategy: Box<dyn ExportStrategy> = DataSourceParams {
+            uri: Some(cmd.to),
+            schema: None,
+            dialect: None,
+            copy: false,
+            sample: SampleParams::default(),
+            load,
+        }
+        .try_into()?;
+
+        let condition = cmd.condition.unwrap_or_else(|| "TRUE".to_string());
+        let collections = import_strategy
+            .subset(&cmd.root, &condition)
+            .context("Failed to collect the subset")?;
+
+        export_strategy.insert(InsertParams {
+            tables: collections.into(),
+            transactional: cmd.transactional,
+            mode: cmd.mode,
+        })
+    }
+
+    fn mask(&self, cmd: MaskCommand) -> Result<()> {
+        let masks = self
+            .store
+            .get_ns(cmd.ns.clone())
+            .with_context(|| format!("Unable to open the namespace \"{}\"", cmd.ns.display()))?;
+        let load = Self::load_params(LoadParams::default())?;
+
+        let import_strategy: Box<dyn ImportStrategy> = DataSourceParams {
+            uri: Some(cmd.from),
+            schema: None,
+            dialect: None,
+            copy: false,
+            sample: SampleParams::default(),
+            load,
+        }
+        .try_into()?;
+
+        let export_strategy: Box<dyn ExportStrategy> = DataSourceParams {
+            uri: Some(cmd.to),
+            schema: None,
+            dialect: None,
+            copy: false,
+            sample: SampleParams::default(),
+            load,
+        }
+        .try_into()?;
+
+        let secret = Self::mask_secret(cmd.secret_file.as_deref())?;
+        let tables = import_strategy
+            .mask(&masks, cmd.seed.unwrap_or(0), secret.as_deref())
+            .context("Failed to mask the source")?;
+
+        export_strategy.insert(InsertParams {
+            tables,
+            transactional: cmd.transactional,
+            mode: cmd.mode,
+        })
+    }
+
+    fn generate(&self, cmd: GenerateCommand) -> Result<()> {
+        let seed = Self::derive_seed(cmd.random, cmd.seed)?;
+        let ns_path = cmd.namespace;
+
         let namespace = self.store.get_ns(ns_path.clone()).context(format!(
             "Unable to open the namespace \"{}\"",
             ns_path
@@ -199,18 +344,35 @@ impl Cli {
                 .expect("The provided namespace is not a valid UTF-8 string")
         ))?;
 
-        self.export_strategy
-            .set(Some(DataSourceParams { uri: to, schema }.try_into()?));
+        self.export_strategy.set(Some(
+            DataSourceParams {
+                uri: cmd.to,
+                schema: cmd.schema,
+                dialect: cmd.dialect,
+                copy: cmd.copy,
+                sample: SampleParams::default(),
+                load: Self::load_params(LoadParams {
+                    pool_size: cmd.pool_size,
+                    batch_size: cmd.batch_size,
+                    statement_timeout: cmd.statement_timeout,
+                    concurrency: cmd.concurrency,
+                })?,
+            }
+            .try_into()?,
+        ));
 
         #[cfg(feature = "telemetry")]
         self.set_telemetry_export_strategy();
 
         let params = ExportParams {
             namespace,
-            collection_name: collection,
-            target,
+            collection_name: cmd.collection,
+            target: cmd.size,
             seed,
             ns_path: ns_path.clone(),
+            transactional: cmd.transactional,
+            mode: cmd.mode,
+            create_tables: cmd.create_tables,
         };
 
         self.export_strategy
@@ -249,73 +411,275 @@ pub enum Args {
         init_path: Option<PathBuf>,
     },
     #[structopt(about = "Generate data from a namespace", alias = "gen")]
-    Generate {
-        #[structopt(
-            help = "The namespace directory from which to read schema files",
-            parse(from_os_str)
-        )]
-        #[serde(skip)]
-        namespace: PathBuf,
-        #[structopt(long, help = "The specific collection from which to generate")]
-        #[serde(skip)]
-        collection: Option<Name>,
-        #[structopt(long, help = "the number of samples", default_value = "1")]
-        size: usize,
-        #[structopt(
-            long,
-            help = "The sink into which to generate data. Can be a postgres uri, a mongodb uri. If not specified, data will be written to stdout"
-        )]
-        #[serde(skip)]
-        to: Option<String>,
-        #[structopt(
-            long,
-            help = "an unsigned 64 bit integer seed to be used as a seed for generation"
-        )]
-        seed: Option<u64>,
-        #[structopt(
-            long,
-            help = "generation will use a random seed - this cannot be used with --seed"
-        )]
-        random: bool,
-        #[structopt(
-            long,
-            help = "(Postgres only) Specify the schema into which to generate. Defaults to 'public'."
-        )]
-        #[serde(skip)]
-        schema: Option<String>,
-    },
+    Generate(GenerateCommand),
     #[structopt(about = "Import data from an external source")]
-    Import {
+    Import(ImportCommand),
+    #[structopt(about = "Copy a referentially intact subset of a database into another one")]
+    Subset(SubsetCommand),
+    #[structopt(about = "Copy a database into another one, masking columns with generated values")]
+    Mask(MaskCommand),
+    #[structopt(about = "Inspect the schema of a namespace")]
+    Schema(SchemaCommand),
+    #[cfg(feature = "telemetry")]
+    #[structopt(about = "Toggle anonymous usage data collection")]
+    Telemetry(TelemetryCommand),
+    #[structopt(about = "Version information")]
+    Version,
+}
+
+#[derive(StructOpt, Serialize)]
+pub struct ImportCommand {
+    #[structopt(
+        help = "The namespace directory into which to save imported schema files",
+        parse(from_os_str)
+    )]
+    #[serde(skip)]
+    pub namespace: PathBuf,
+    #[structopt(
+        long,
+        help = "The name of a collection into which the data will be imported"
+    )]
+    #[serde(skip)]
+    pub collection: Option<Name>,
+    #[structopt(
+        long,
+        help = "The source from which to import data. Can be a postgres uri, a mongodb uri, a mysql/mariadb uri or a path to a JSON file / directory. If not specified, data will be read from stdin"
+    )]
+    #[serde(skip)]
+    pub from: Option<String>,
+    #[structopt(
+        long,
+        help = "(Postgres only) Specify the schema from which to import. Defaults to 'public'."
+    )]
+    #[serde(skip)]
+    pub schema: Option<String>,
+    #[structopt(
+        long,
+        help = "(Postgres, MySQL and MongoDB only) The number of rows or documents to sample from every table or collection to infer its schema",
+        default_value = "10"
+    )]
+    pub sample_size: usize,
+    #[structopt(
+        long,
+        help = "(Postgres, MySQL and MongoDB only) How rows are sampled: 'random' picks rows uniformly at random, 'first' picks the first rows and 'tablesample' picks an approximate number of random rows without scanning the whole table. MongoDB samples with '$sample' for both 'random' and 'tablesample'",
+        default_value = "random",
+        possible_values = &SampleStrategy::VARIANTS
+    )]
+    pub sample_strategy: SampleStrategy,
+    #[structopt(
+        long,
+        help = "an unsigned 64 bit integer seed used to pick the sampled rows. Not supported by MongoDB"
+    )]
+    pub seed: Option<u64>,
+    #[structopt(
+        long,
+        help = "(Postgres and MySQL only) The maximum number of connections to the database. Defaults to 3"
+    )]
+    pub pool_size: Option<u32>,
+    #[structopt(
+        long,
+        help = "(Postgres and MySQL only) The time after which statements are cancelled, in milliseconds"
+    )]
+    pub statement_timeout: Option<u64>,
+    #[structopt(
+        long,
+        help = "Merge the import into an existing namespace or collection: new collections and fields are added, fields whose type changed are replaced and the content of the others is kept. Collections and fields which no longer exist are reported but kept"
+    )]
+    pub merge: bool,
+    #[structopt(
+        long,
+        help = "Add differentially private noise to the inferred statistics, as 'epsilon=<epsilon>[,threshold=<threshold>][,clamp=<low>..<high>]'. Categorical weights get Laplace noise of scale 1/epsilon and categories below the threshold (5 by default) are left out. Numeric ranges are bounded by noisy 5th and 95th percentiles over the domain of their type, narrowed by the clamp if given, instead of the sampled extremes. Epsilon is spent on each noised statistic separately, not once in total: the privacy loss of a collection adds up over its fields"
+    )]
+    pub privacy: Option<Privacy>,
+}
+
+#[derive(StructOpt, Serialize)]
+pub struct SubsetCommand {
+    #[structopt(
+        long,
+        help = "The database from which to copy rows. Can be a postgres uri or a mysql/mariadb uri"
+    )]
+    #[serde(skip)]
+    pub from: String,
+    #[structopt(
+        long,
+        help = "The database into which to insert the rows. Can be a postgres uri or a mysql/mariadb uri"
+    )]
+    #[serde(skip)]
+    pub to: String,
+    #[structopt(long, help = "The table from which to start collecting rows")]
+    #[serde(skip)]
+    pub root: String,
+    #[structopt(
+        long = "where",
+        help = "The SQL condition which the rows of the root table have to match. Defaults to all rows"
+    )]
+    #[serde(skip)]
+    pub condition: Option<String>,
+    #[structopt(
+        long,
+        help = "Insert the whole subset in a single transaction which is rolled back if any insert fails"
+    )]
+    pub transactional: bool,
+    #[structopt(
+        long,
+        help = "How to write into tables which already contain data: 'append' inserts alongside existing rows, 'truncate' deletes all existing rows first, 'replace' replaces rows with conflicting primary keys and 'upsert' updates them",
+        default_value = "append",
+        possible_values = &ExportMode::RELATIONAL_VARIANTS
+    )]
+    pub mode: ExportMode,
+}
+
+#[derive(StructOpt, Serialize)]
+pub struct MaskCommand {
+    #[structopt(
+        long,
+        help = "The database from which to copy rows. Can be a postgres uri or a mysql/mariadb uri"
+    )]
+    #[serde(skip)]
+    pub from: String,
+    #[structopt(
+        long,
+        help = "The database into which to insert the masked rows. Can be a postgres uri or a mysql/mariadb uri"
+    )]
+    #[serde(skip)]
+    pub to: String,
+    #[structopt(
+        long,
+        help = "The namespace directory whose collections mark the columns to mask, and the content generating their values",
+        parse(from_os_str)
+    )]
+    #[serde(skip)]
+    pub ns: PathBuf,
+    #[structopt(
+        long,
+        help = "an unsigned 64 bit integer seed to be used as a seed for generation. Defaults to 0 if unspecified"
+    )]
+    pub seed: Option<u64>,
+    #[structopt(
+        long,
+        help = "A file containing the secret keying the pseudonyms of pseudonymised columns whose content doesn't set one. Defaults to the SYNTH_MASK_SECRET environment variable",
+        parse(from_os_str)
+    )]
+    #[serde(skip)]
+    pub secret_file: Option<PathBuf>,
+    #[structopt(
+        long,
+        help = "Insert all rows in a single transaction which is rolled back if any insert fails"
+    )]
+    pub transactional: bool,
+    #[structopt(
+        long,
+        help = "How to write into tables which already contain data: 'append' inserts alongside existing rows, 'truncate' deletes all existing rows first, 'replace' replaces rows with conflicting primary keys and 'upsert' updates them",
+        default_value = "append",
+        possible_values = &ExportMode::RELATIONAL_VARIANTS
+    )]
+    pub mode: ExportMode,
+}
+
+#[derive(StructOpt, Serialize)]
+pub struct GenerateCommand {
+    #[structopt(
+        help = "The namespace directory from which to read schema files",
+        parse(from_os_str)
+    )]
+    #[serde(skip)]
+    pub namespace: PathBuf,
+    #[structopt(long, help = "The specific collection from which to generate")]
+    #[serde(skip)]
+    pub collection: Option<Name>,
+    #[structopt(long, help = "the number of samples", default_value = "1")]
+    pub size: usize,
+    #[structopt(
+        long,
+        help = "The sink into which to generate data. Can be a postgres uri, a mongodb uri, a mysql/mariadb uri or 'sql:<path>' to write a SQL script. If not specified, data will be written to stdout"
+    )]
+    #[serde(skip)]
+    pub to: Option<String>,
+    #[structopt(
+        long,
+        help = "an unsigned 64 bit integer seed to be used as a seed for generation"
+    )]
+    pub seed: Option<u64>,
+    #[structopt(
+        long,
+        help = "generation will use a random seed - this cannot be used with --seed"
+    )]
+    pub random: bool,
+    #[structopt(
+        long,
+        help = "(Postgres only) Specify the schema into which to generate. Defaults to 'public'."
+    )]
+    #[serde(skip)]
+    pub schema: Option<String>,
+    #[structopt(
+        long,
+        help = "(Postgres and MySQL only) Run the whole generation in a single transaction which is rolled back if any insert fails"
+    )]
+    pub transactional: bool,
+    #[structopt(
+        long,
+        help = "(Postgres, MySQL and MongoDB only) How to write into tables which already contain data: 'append' inserts alongside existing rows, 'truncate' deletes all existing rows first, 'replace' replaces rows with conflicting primary keys, 'upsert' updates them and 'drop' (MongoDB only) drops the collections first",
+        default_value = "append",
+        possible_values = &ExportMode::VARIANTS
+    )]
+    pub mode: ExportMode,
+    #[structopt(
+        long,
+        help = "(Postgres and MySQL only) Create the target tables from the namespace if they do not exist yet"
+    )]
+    pub create_tables: bool,
+    #[structopt(
+        long,
+        help = "(SQL scripts only) The SQL dialect of the script. Defaults to 'postgres'.",
+        possible_values = &SqlDialect::VARIANTS
+    )]
+    pub dialect: Option<SqlDialect>,
+    #[structopt(
+        long,
+        help = "(SQL scripts with the postgres dialect only) Write COPY blocks instead of INSERT statements. The script then has to be applied with psql"
+    )]
+    pub copy: bool,
+    #[structopt(
+        long,
+        help = "(Postgres and MySQL only) The maximum number of connections to the database. Defaults to 3"
+    )]
+    pub pool_size: Option<u32>,
+    #[structopt(
+        long,
+        help = "(Postgres, MySQL, MongoDB and SQL scripts only) The number of rows or documents inserted by a single statement. Defaults to 1000"
+    )]
+    pub batch_size: Option<usize>,
+    #[structopt(
+        long,
+        help = "(Postgres and MySQL only) The time after which statements are cancelled, in milliseconds"
+    )]
+    pub statement_timeout: Option<u64>,
+    #[structopt(
+        long,
+        help = "(Postgres and MySQL only) The maximum number of inserts running at the same time. Defaults to the pool size"
+    )]
+    pub concurrency: Option<usize>,
+}
+
+#[derive(StructOpt, Serialize)]
+pub enum SchemaCommand {
+    #[structopt(about = "Print the CREATE TABLE statements for the collections of a namespace")]
+    Ddl {
         #[structopt(
-            help = "The namespace directory into which to save imported schema files",
+            help = "The namespace directory from which to read schema files",
             parse(from_os_str)
         )]
         #[serde(skip)]
         namespace: PathBuf,
         #[structopt(
             long,
-            help = "The name of a collection into which the data will be imported"
+            help = "The SQL dialect of the statements",
+            default_value = "postgres",
+            possible_values = &SqlDialect::VARIANTS
         )]
-        #[serde(skip)]
-        collection: Option<Name>,
-        #[structopt(
-            long,
-            help = "The source from which to import data. Can be a postgres uri, a mongodb uri, a mysql/mariadb uri or a path to a JSON file / directory. If not specified, data will be read from stdin"
-        )]
-        #[serde(skip)]
-        from: Option<String>,
-        #[structopt(
-            long,
-            help = "(Postgres only) Specify the schema from which to import. Defaults to 'public'."
-        )]
-        #[serde(skip)]
-        schema: Option<String>,
+        dialect: SqlDialect,
     },
-    #[cfg(feature = "telemetry")]
-    #[structopt(about = "Toggle anonymous usage data collection")]
-    Telemetry(TelemetryCommand),
-    #[structopt(about = "Version information")]
-    Version,
 }
 
 #[cfg(feature = "telemetry")]
@@ -1,37 +1,49 @@
 use crate::cli::export::{ExportParams, ExportStrategy};
-use crate::cli::import::ImportStrategy;
+use crate::cli::import::{take_collection, ImportStrategy};
+use crate::cli::import_utils::refine_collection;
+use crate::cli::pii::Finding;
+use crate::datasource::{ExportMode, Privacy, SampleParams, SampleStrategy};
 use crate::sampler::{Sampler, SamplerOutput};
-use anyhow::Result;
-use chrono::{DateTime, Utc};
-use mongodb::bson::Bson;
-use mongodb::options::FindOptions;
+use anyhow::{Context, Result};
+use chrono::{DateTime, TimeZone, Utc};
+use mongodb::bson::{doc, oid, Bson};
+use mongodb::error::{BulkWriteFailure, ErrorKind};
+use mongodb::options::{FindOptions, InsertManyOptions};
+use mongodb::sync::{Collection, Database};
 use mongodb::{bson::Document, options::ClientOptions, sync::Client};
 use serde_json::Value as JsonValue;
 use std::collections::BTreeMap;
 use std::convert::TryFrom;
+use std::mem::discriminant;
 use std::str::FromStr;
 use synth_core::graph::prelude::content::number_content::U64;
 use synth_core::graph::prelude::number_content::I64;
 use synth_core::graph::prelude::{ChronoValue, Number, NumberContent, ObjectContent, RangeStep};
 use synth_core::schema::number_content::F64;
 use synth_core::schema::{
-    ArrayContent, BoolContent, Categorical, ChronoValueType, DateTimeContent, RegexContent,
-    StringContent,
+    ArrayContent, BoolContent, Categorical, ChronoValueType, DateTimeContent, HiddenContent,
+    ObjectId, RegexContent, SameAsContent, StringContent, UniqueContent, VariantContent,
 };
 use synth_core::{Content, Name, Namespace, Value};
 
 #[derive(Clone, Debug)]
 pub struct MongoExportStrategy {
     pub uri: String,
+    /// The number of documents sent in every insert
+    pub batch_size: usize,
 }
 
 #[derive(Clone, Debug)]
 pub struct MongoImportStrategy {
     pub uri: String,
+    pub sample: SampleParams,
 }
 
+/// The format of imported `DateTime` and `Timestamp` values.
+const DATE_TIME_FORMAT: &str = "%Y-%m-%dT%H:%M:%S%z";
+
 impl ImportStrategy for MongoImportStrategy {
-    fn import(&self) -> Result<Namespace> {
+    fn import(&self) -> Result<(Namespace, Vec<Finding>)> {
         let client_options = ClientOptions::parse(&self.uri)?;
 
         info!("Connecting to database at {} ...", &self.uri);
@@ -40,79 +52,468 @@ impl ImportStrategy for MongoImportStrategy {
 
         let db_name = parse_db_name(&self.uri)?;
 
-        // 0: Initialise empty Namespace
         let mut namespace = Namespace::default();
+        let mut findings = Vec::new();
         let database = client.database(db_name);
 
-        // 1: First pass - create master schema
-        for collection_name in database.list_collection_names(None)? {
+        for specification in database.list_collections(None, None)? {
+            let specification = specification?;
+            let collection_name = specification.name;
             let collection = database.collection(&collection_name);
 
-            // This may be useful later
-            // let count = collection.estimated_document_count(None)?;
+            // 1: Start from the constraints of the `$jsonSchema` validator, if any
+            let validator = match specification
+                .options
+                .validator
+                .as_ref()
+                .and_then(|validator| validator.get_document("$jsonSchema").ok())
+            {
+                Some(json_schema) => match json_schema_to_content(json_schema) {
+                    Ok(Some(Content::Object(object))) => Some(object),
+                    Ok(_) => None,
+                    Err(e) => {
+                        warn!(
+                            "Could not import the validator of collection {}: {}",
+                            collection_name, e
+                        );
+                        None
+                    }
+                },
+                None => None,
+            };
 
-            if let Ok(Some(some_obj)) = collection.find_one(None, None) {
-                let as_array = Content::Array(ArrayContent::from_content_default_length(
-                    doc_to_content(&some_obj),
-                ));
-                namespace.put_collection(&Name::from_str(&collection_name)?, as_array)?;
-            } else {
+            let sample = self.sample_documents(&collection)?;
+            if sample.is_empty() && validator.is_none() {
                 info!("Collection {} is empty. Skipping...", collection_name);
                 continue;
             }
+
+            let name = Name::from_str(&collection_name)?;
+            findings.extend(import_sampled_collection(
+                &mut namespace,
+                &name,
+                validator,
+                sample,
+                self.sample.privacy.as_ref(),
+            )?);
+
+            // 4: Make the fields of unique indexes unique
+            let unique_fields = unique_index_fields(&database, &collection_name)?;
+            if let Content::Array(ArrayContent { content, .. }) =
+                namespace.get_collection_mut(&name)?
+            {
+                for field in unique_fields {
+                    match object_field_mut(content, &field) {
+                        Some(content) => *content = into_unique(content.clone()),
+                        None => debug!(
+                            "Unique field {} of collection {} was not imported",
+                            field, collection_name
+                        ),
+                    }
+                }
+            }
         }
 
-        // 2: Run an ingest step with 10 documents
-        for collection_name in database.list_collection_names(None)? {
-            let collection = database.collection(&collection_name);
+        Ok((namespace, findings))
+    }
 
-            // This may be useful later
-            // let count = collection.estimated_document_count(None)?;
+    fn import_collection(&self, name: &Name) -> Result<(Content, Vec<Finding>)> {
+        take_collection(self.import()?, name)
+            .ok_or_else(|| anyhow!("Could not find table '{}' in MongoDb database.", name))
+    }
 
-            let mut find_options = FindOptions::default();
-            find_options.limit = Some(10);
+    fn as_value(&self) -> Result<JsonValue> {
+        unreachable!()
+    }
+}
 
-            let mut random_sample: Vec<Document> = collection
-                .find(None, find_options)?
-                .collect::<Result<Vec<Document>, _>>()?;
+/// Puts the collection `name` into the namespace, starting from the content of its validator
+/// and completed and refined from the sampled documents. Returns the fields replaced by fakers.
+fn import_sampled_collection(
+    namespace: &mut Namespace,
+    name: &Name,
+    validator: Option<ObjectContent>,
+    sample: Vec<Document>,
+    privacy: Option<&Privacy>,
+) -> Result<Vec<Finding>> {
+    // 2: Add every field of every sampled document missing from the master schema
+    let mut master = validator.unwrap_or_default();
+    for doc in sample.iter() {
+        union_doc_fields(&mut master, doc);
+    }
 
-            random_sample.iter_mut().for_each(|doc| {
-                doc.remove("_id");
-            });
+    namespace.put_collection(
+        name,
+        Content::Array(ArrayContent::from_content_default_length(Content::Object(
+            master,
+        ))),
+    )?;
+
+    // 3: Merge the sampled documents, which makes the fields missing from some of them
+    // nullable and widens the bounds of the others. The content of the fields is then
+    // refined from all of the sampled documents
+    if sample.is_empty() {
+        return Ok(Vec::new());
+    }
 
-            namespace.default_try_update(
-                &Name::from_str(&collection_name)?,
-                &serde_json::to_value(random_sample)?,
-            )?;
+    let as_json: Vec<JsonValue> = sample
+        .into_iter()
+        .map(|doc| bson_to_json(Bson::Document(doc)))
+        .collect();
+
+    let as_json = JsonValue::Array(as_json);
+    namespace.default_try_update(name, &as_json)?;
+    let collection = namespace.get_collection_mut(name)?;
+    Ok(refine_collection(
+        name.as_ref(),
+        collection,
+        &as_json,
+        privacy,
+    ))
+}
+
+impl MongoImportStrategy {
+    fn sample_documents(&self, collection: &Collection<Document>) -> Result<Vec<Document>> {
+        let documents = match self.sample.strategy {
+            SampleStrategy::First => {
+                let mut find_options = FindOptions::default();
+                find_options.limit = Some(self.sample.size as i64);
+
+                collection
+                    .find(None, find_options)?
+                    .collect::<Result<Vec<Document>, _>>()?
+            }
+            // `$sample` can't be seeded, and only avoids scanning the whole collection when the
+            // sample is small compared to the collection.
+            SampleStrategy::Random | SampleStrategy::TableSample => collection
+                .aggregate(
+                    vec![doc! { "$sample": { "size": self.sample.size as i64 } }],
+                    None,
+                )?
+                .collect::<Result<Vec<Document>, _>>()?,
+        };
+
+        Ok(documents)
+    }
+}
+
+/// Returns the fields which make up a unique index on their own. The `_id` index is unique too, but
+/// isn't reported as such.
+fn unique_index_fields(database: &Database, collection_name: &str) -> Result<Vec<String>> {
+    let result = database.run_command(doc! { "listIndexes": collection_name }, None)?;
+    let indexes = result
+        .get_document("cursor")
+        .and_then(|cursor| cursor.get_array("firstBatch"))
+        .context("Unexpected result of listIndexes")?;
+
+    let mut fields = Vec::new();
+    for index in indexes.iter().filter_map(Bson::as_document) {
+        if !index.get_bool("unique").unwrap_or(false) {
+            continue;
         }
 
-        Ok(namespace)
+        let keys = index.get_document("key")?;
+        match keys.keys().collect::<Vec<_>>().as_slice() {
+            [field] => fields.push(field.to_string()),
+            _ => info!(
+                "Unique compound index {} of collection {} is not supported. Skipping...",
+                index.get_str("name").unwrap_or_default(),
+                collection_name
+            ),
+        }
     }
 
-    fn import_collection(&self, name: &Name) -> Result<Content> {
-        self.import()?
-            .collections
-            .remove(name)
-            .ok_or_else(|| anyhow!("Could not find table '{}' in MongoDb database.", name))
+    Ok(fields)
+}
+
+/// Finds the content of a field given its dotted path, looking into nullable objects.
+fn object_field_mut<'a>(content: &'a mut Content, path: &str) -> Option<&'a mut Content> {
+    path.split('.').try_fold(content, |content, name| {
+        let object =
+            match content {
+                Content::Object(object) => object,
+                Content::OneOf(one_of) => one_of.variants.iter_mut().find_map(|variant| {
+                    match variant.content.as_mut() {
+                        Content::Object(object) => Some(object),
+                        _ => None,
+                    }
+                })?,
+                _ => return None,
+            };
+        object.fields.get_mut(name)
+    })
+}
+
+/// Wraps content with `UniqueContent`. Only the non-null variants of nullable content are made
+/// unique, so that several documents may leave the field out.
+fn into_unique(content: Content) -> Content {
+    match content {
+        Content::OneOf(mut one_of) => {
+            for variant in one_of.variants.iter_mut() {
+                if !variant.content.is_null() {
+                    *variant.content = into_unique(*variant.content.clone());
+                }
+            }
+            Content::OneOf(one_of)
+        }
+        content @ Content::Unique(_) => content,
+        content => Content::Unique(UniqueContent {
+            algorithm: Default::default(),
+            content: Box::new(content),
+        }),
     }
+}
 
-    fn as_value(&self) -> Result<JsonValue> {
-        unreachable!()
+/// Translates a `$jsonSchema` validator into content. Returns `None` for schemas which don't
+/// constrain the type of values, which are left to be inferred from documents.
+fn json_schema_to_content(schema: &Document) -> Result<Option<Content>> {
+    if let Ok(values) = schema.get_array("enum") {
+        if let Some(content) = enum_to_content(values) {
+            return Ok(Some(content));
+        }
     }
+
+    let types: Vec<&str> = match schema.get("bsonType").or_else(|| schema.get("type")) {
+        Some(Bson::String(type_)) => vec![type_.as_str()],
+        Some(Bson::Array(types)) => types.iter().filter_map(Bson::as_str).collect(),
+        Some(other) => bail!("Invalid type {}", other),
+        // Without a type, the keywords tell which type is meant
+        None if schema.contains_key("properties") || schema.contains_key("required") => {
+            vec!["object"]
+        }
+        None if schema.contains_key("items") => vec!["array"],
+        None if schema.contains_key("pattern") || schema.contains_key("minLength") => {
+            vec!["string"]
+        }
+        None if schema.contains_key("minimum") || schema.contains_key("maximum") => {
+            vec!["number"]
+        }
+        None => return Ok(None),
+    };
+
+    let mut is_nullable = false;
+    let mut variants = Vec::new();
+    for type_ in types {
+        match type_ {
+            "null" => is_nullable = true,
+            type_ => match json_schema_type_to_content(type_, schema)? {
+                Some(content) => variants.push(content),
+                None => return Ok(None),
+            },
+        }
+    }
+
+    let content = match variants.len() {
+        0 => Content::null(),
+        1 => variants.remove(0),
+        _ => Content::OneOf(variants.into_iter().collect()),
+    };
+
+    if is_nullable {
+        Ok(Some(content.into_nullable()))
+    } else {
+        Ok(Some(content))
+    }
+}
+
+fn json_schema_type_to_content(type_: &str, schema: &Document) -> Result<Option<Content>> {
+    let content = match type_ {
+        "object" => {
+            let mut object = ObjectContent::default();
+            let required: Vec<&str> = schema
+                .get_array("required")
+                .map(|required| required.iter().filter_map(Bson::as_str).collect())
+                .unwrap_or_default();
+
+            if let Ok(properties) = schema.get_document("properties") {
+                for (name, property) in properties {
+                    let property = property
+                        .as_document()
+                        .ok_or_else(|| anyhow!("Invalid schema of property {}", name))?;
+                    let content = json_schema_to_content(property)
+                        .with_context(|| anyhow!("Invalid schema of property {}", name))?;
+
+                    if let Some(content) = content {
+                        let content = if required.contains(&name.as_str()) {
+                            content
+                        } else {
+                            content.into_nullable()
+                        };
+                        object.fields.insert(name.clone(), content);
+                    }
+                }
+            }
+
+            Content::Object(object)
+        }
+        "array" => {
+            let content = match schema.get_document("items") {
+                Ok(items) => json_schema_to_content(items)?,
+                Err(_) => None,
+            };
+            let min_items = schema.get("minItems").and_then(bson_to_f64);
+            let max_items = schema.get("maxItems").and_then(bson_to_f64);
+
+            Content::Array(ArrayContent {
+                length: Box::new(Content::Number(NumberContent::U64(U64::Range(RangeStep {
+                    low: min_items.map(|min| min as u64),
+                    high: max_items.map(|max| max as u64),
+                    step: Some(1),
+                    include_low: true,
+                    include_high: max_items.is_some(),
+                })))),
+                // Samples replace the null content of unconstrained items
+                content: Box::new(content.unwrap_or_else(Content::null)),
+            })
+        }
+        "string" => {
+            let min_length = schema.get("minLength").and_then(bson_to_f64);
+            let max_length = schema.get("maxLength").and_then(bson_to_f64);
+
+            match schema.get_str("pattern") {
+                Ok(pattern) => match RegexContent::pattern(pattern.to_string()) {
+                    Ok(regex) => Content::String(StringContent::Pattern(regex)),
+                    Err(e) => {
+                        warn!("Unsupported pattern {}: {}", pattern, e);
+                        return Ok(None);
+                    }
+                },
+                Err(_) if min_length.is_some() || max_length.is_some() => {
+                    let min_length = min_length.unwrap_or(0.) as u64;
+                    let max_length = max_length.map(|max| max as u64).unwrap_or(min_length + 16);
+                    Content::String(StringContent::Pattern(
+                        RegexContent::pattern(format!(
+                            "[a-zA-Z0-9]{{{},{}}}",
+                            min_length, max_length
+                        ))
+                        .context("pattern will always compile")?,
+                    ))
+                }
+                Err(_) => Content::String(StringContent::default()),
+            }
+        }
+        "int" | "long" => {
+            let (low, include_low) = json_schema_bound(schema, "minimum", "exclusiveMinimum");
+            let (high, include_high) = json_schema_bound(schema, "maximum", "exclusiveMaximum");
+            Content::Number(NumberContent::I64(I64::Range(RangeStep {
+                low: low.map(|low| low.ceil() as i64),
+                high: high.map(|high| high.floor() as i64),
+                step: Some(1),
+                include_low,
+                include_high,
+            })))
+        }
+        "double" | "decimal" | "number" => {
+            let (low, include_low) = json_schema_bound(schema, "minimum", "exclusiveMinimum");
+            let (high, include_high) = json_schema_bound(schema, "maximum", "exclusiveMaximum");
+            Content::Number(NumberContent::F64(F64::Range(RangeStep {
+                low,
+                high,
+                step: None,
+                include_low,
+                include_high,
+            })))
+        }
+        "bool" | "boolean" => Content::Bool(BoolContent::default()),
+        "date" | "timestamp" => Content::DateTime(DateTimeContent {
+            format: DATE_TIME_FORMAT.to_string(),
+            type_: ChronoValueType::DateTime,
+            begin: None,
+            end: None,
+        }),
+        "objectId" => Content::String(StringContent::ObjectId(ObjectId)),
+        // Other types, such as binary data, are inferred from documents
+        _ => return Ok(None),
+    };
+
+    Ok(Some(content))
+}
+
+/// Returns a bound of a number and whether it is inclusive.
+fn json_schema_bound(schema: &Document, key: &str, exclusive_key: &str) -> (Option<f64>, bool) {
+    let bound = schema.get(key).and_then(bson_to_f64);
+    let is_exclusive = schema.get_bool(exclusive_key).unwrap_or(false);
+    (bound, bound.is_some() && !is_exclusive)
 }
 
-fn doc_to_content(doc: &Document) -> Content {
-    let mut root = BTreeMap::new();
+/// Translates the values of `enum` into categorical content, when they are all strings or all
+/// integers.
+fn enum_to_content(values: &[Bson]) -> Option<Content> {
+    if let Some(values) = values.iter().map(Bson::as_str).collect::<Option<Vec<_>>>() {
+        let mut categorical = Categorical::default();
+        values
+            .into_iter()
+            .for_each(|value| categorical.push(value.to_string()));
+        return Some(Content::String(StringContent::Categorical(categorical)));
+    }
+
+    let values = values
+        .iter()
+        .map(|value| match value {
+            Bson::Int32(i) => Some(*i as i64),
+            Bson::Int64(i) => Some(*i),
+            _ => None,
+        })
+        .collect::<Option<Vec<_>>>()?;
+    let mut categorical = Categorical::default();
+    values.into_iter().for_each(|value| categorical.push(value));
+    Some(Content::Number(NumberContent::I64(I64::Categorical(
+        categorical,
+    ))))
+}
 
-    // Notice this `filter` here is a hack as we don't support id's out of the box.
-    for (name, bson) in doc.iter().filter(|(name, _)| name.as_str() != "_id") {
-        let content = bson_to_content(bson);
-        root.insert(name.clone(), content);
+fn bson_to_f64(bson: &Bson) -> Option<f64> {
+    match bson {
+        Bson::Double(d) => Some(*d),
+        Bson::Int32(i) => Some(*i as f64),
+        Bson::Int64(i) => Some(*i as f64),
+        Bson::Decimal128(d) => Some(decimal128_to_f64(d.bytes())),
+        _ => None,
+    }
+}
+
+/// Adds the fields of `doc` which are missing from `object`. Fields holding values of different
+/// types across documents become a `one_of` of these types.
+fn union_doc_fields(object: &mut ObjectContent, doc: &Document) {
+    for (name, bson) in doc.iter() {
+        match object.fields.get_mut(name) {
+            Some(content) => union_content(content, bson),
+            None => {
+                object.fields.insert(name.clone(), bson_to_content(bson));
+            }
+        }
+    }
+}
+
+fn union_content(content: &mut Content, bson: &Bson) {
+    match (content, bson) {
+        // Merging the sampled documents makes the content nullable
+        (_, Bson::Null) => {}
+        (content @ Content::Null(_), bson) => *content = bson_to_content(bson).into_nullable(),
+        (Content::Object(object), Bson::Document(doc)) => union_doc_fields(object, doc),
+        (Content::Array(array), Bson::Array(elements)) => {
+            for element in elements {
+                union_content(&mut array.content, element);
+            }
+        }
+        (Content::OneOf(one_of), bson) => {
+            let other = bson_to_content(bson);
+            match one_of
+                .variants
+                .iter_mut()
+                .find(|variant| discriminant(variant.content.as_ref()) == discriminant(&other))
+            {
+                Some(variant) => union_content(&mut variant.content, bson),
+                None => one_of.variants.push(VariantContent::new(other)),
+            }
+        }
+        (content, bson) => {
+            let other = bson_to_content(bson);
+            if discriminant(content) != discriminant(&other) {
+                *content = Content::OneOf(vec![content.clone(), other].into_iter().collect());
+            }
+        }
     }
-    Content::Object(ObjectContent {
-        fields: root,
-        ..Default::default()
-    })
 }
 
 fn bson_to_content(bson: &Bson) -> Content {
@@ -124,15 +525,29 @@ fn bson_to_content(bson: &Bson) -> Content {
         )))),
         Bson::String(_) => Content::String(StringContent::default()),
         Bson::Array(array) => {
-            let length = Content::Number(NumberContent::U64(U64::Constant(array.len() as u64)));
-            let content_iter = array.iter().map(bson_to_content);
+            let length = Content::Number(NumberContent::U64(U64::Range(RangeStep::new(
+                array.len() as u64,
+                array.len() as u64 + 1,
+                1,
+            ))));
+
+            // Empty arrays leave a null content, which is replaced by the elements of the next
+            // arrays.
+            let mut content = Content::null();
+            for element in array {
+                union_content(&mut content, element);
+            }
 
             Content::Array(ArrayContent {
                 length: Box::new(length),
-                content: Box::new(Content::OneOf(content_iter.collect())),
+                content: Box::new(content),
             })
         }
-        Bson::Document(doc) => doc_to_content(doc),
+        Bson::Document(doc) => {
+            let mut object = ObjectContent::default();
+            union_doc_fields(&mut object, doc);
+            Content::Object(object)
+        }
         Bson::Boolean(_) => Content::Bool(BoolContent::Categorical(Categorical::default())),
         Bson::Null => Content::null(),
         Bson::RegularExpression(regex) => Content::String(StringContent::Pattern(
@@ -154,8 +569,22 @@ fn bson_to_content(bson: &Bson) -> Content {
             *i + 1,
             1,
         )))),
-        Bson::DateTime(_) => Content::DateTime(DateTimeContent {
-            format: "".to_string(),
+        Bson::Decimal128(d) => {
+            let d = decimal128_to_f64(d.bytes());
+            Content::Number(NumberContent::F64(F64::Range(RangeStep::new(
+                d,
+                d + 1.,
+                0.1,
+            ))))
+        }
+        Bson::ObjectId(_) => Content::String(StringContent::ObjectId(ObjectId)),
+        // Binary data is imported as hex strings of the same length
+        Bson::Binary(binary) => Content::String(StringContent::Pattern(
+            RegexContent::pattern(format!("([0-9a-f]{{2}}){{{}}}", binary.bytes.len()))
+                .unwrap_or_default(),
+        )),
+        Bson::DateTime(_) | Bson::Timestamp(_) => Content::DateTime(DateTimeContent {
+            format: DATE_TIME_FORMAT.to_string(),
             type_: ChronoValueType::DateTime,
             begin: None,
             end: None,
@@ -166,87 +595,324 @@ fn bson_to_content(bson: &Bson) -> Content {
     }
 }
 
+/// Converts a BSON value to the JSON value merged into the content built by `bson_to_content`.
+fn bson_to_json(bson: Bson) -> JsonValue {
+    match bson {
+        Bson::Double(d) => JsonValue::from(d),
+        Bson::String(s) => JsonValue::String(s),
+        Bson::Array(array) => JsonValue::Array(array.into_iter().map(bson_to_json).collect()),
+        Bson::Document(doc) => JsonValue::Object(
+            doc.into_iter()
+                .map(|(name, bson)| (name, bson_to_json(bson)))
+                .collect(),
+        ),
+        Bson::Boolean(b) => JsonValue::Bool(b),
+        Bson::Null => JsonValue::Null,
+        Bson::RegularExpression(regex) => JsonValue::String(regex.pattern),
+        Bson::JavaScriptCode(code) => JsonValue::String(code),
+        Bson::JavaScriptCodeWithScope(code) => JsonValue::String(code.code),
+        Bson::Int32(i) => JsonValue::from(i),
+        Bson::Int64(i) => JsonValue::from(i),
+        Bson::Decimal128(d) => JsonValue::from(decimal128_to_f64(d.bytes())),
+        Bson::ObjectId(oid) => JsonValue::String(oid.to_hex()),
+        Bson::Binary(binary) => JsonValue::String(
+            binary
+                .bytes
+                .iter()
+                .map(|byte| format!("{:02x}", byte))
+                .collect(),
+        ),
+        Bson::DateTime(dt) => {
+            JsonValue::String(dt.to_chrono().format(DATE_TIME_FORMAT).to_string())
+        }
+        Bson::Timestamp(ts) => JsonValue::String(
+            Utc.timestamp(ts.time as i64, 0)
+                .format(DATE_TIME_FORMAT)
+                .to_string(),
+        ),
+        other => JsonValue::String(other.to_string()),
+    }
+}
+
+/// Decodes the bytes of a `Decimal128`, which is an IEEE 754-2008 decimal in the binary integer
+/// decimal encoding, as the closest `f64`.
+fn decimal128_to_f64(bytes: [u8; 16]) -> f64 {
+    let mut low = [0; 8];
+    let mut high = [0; 8];
+    low.copy_from_slice(&bytes[..8]);
+    high.copy_from_slice(&bytes[8..]);
+    let low = u64::from_le_bytes(low);
+    let high = u64::from_le_bytes(high);
+
+    let sign = if high >> 63 == 1 { -1. } else { 1. };
+    let (exponent, coefficient) = match (high >> 58) & 0x1f {
+        0x1f => return f64::NAN,
+        0x1e => return sign * f64::INFINITY,
+        // The coefficient of the other encoding is always larger than the maximum of 10^34 - 1,
+        // which makes it zero
+        combination if combination >> 3 == 0b11 => return sign * 0.,
+        _ => (
+            (high >> 49) & 0x3fff,
+            (((high & ((1 << 49) - 1)) as u128) << 64) | low as u128,
+        ),
+    };
+
+    let value = format!("{}e{}", coefficient, exponent as i64 - 6176)
+        .parse::<f64>()
+        .unwrap_or_default();
+    sign * value
+}
+
 impl ExportStrategy for MongoExportStrategy {
     fn export(&self, params: ExportParams) -> Result<SamplerOutput> {
-        let mut client = Client::with_uri_str(&self.uri)?;
+        if params.transactional {
+            bail!("Transactional exports are not supported for MongoDB");
+        }
+
+        if params.create_tables {
+            bail!("Creating tables is not supported for MongoDB");
+        }
+
+        if matches!(params.mode, ExportMode::Replace | ExportMode::Upsert) {
+            bail!(
+                "The '{}' export mode is not supported for MongoDB",
+                params.mode
+            );
+        }
+
+        let client = Client::with_uri_str(&self.uri)?;
+        let database = client.database(parse_db_name(&self.uri)?);
         let sampler = Sampler::try_from(&params.namespace)?;
         let output =
             sampler.sample_seeded(params.collection_name.clone(), params.target, params.seed)?;
 
-        match output {
-            SamplerOutput::Collection(ref values) => self.insert_data(
-                &params.collection_name.unwrap().to_string(),
-                values,
-                &mut client,
-            ),
-            SamplerOutput::Namespace(ref namespace) => {
-                for (name, values) in namespace {
-                    self.insert_data(name, values, &mut client)?;
+        let collections: Vec<(Name, &[Value])> = match output {
+            SamplerOutput::Collection(ref values) => {
+                vec![(params.collection_name.clone().unwrap(), values.as_slice())]
+            }
+            SamplerOutput::Namespace(ref namespace) => namespace
+                .iter()
+                .map(|(name, values)| Ok((Name::from_str(name)?, values.as_slice())))
+                .collect::<Result<_>>()?,
+        };
+
+        for (name, _) in collections.iter() {
+            let collection = database.collection::<Document>(name.as_ref());
+            match params.mode {
+                ExportMode::Truncate => {
+                    let deleted = collection.delete_many(doc! {}, None)?.deleted_count;
+                    info!("Deleted {} documents from collection {}", deleted, name);
                 }
-                Ok(())
+                ExportMode::Drop => {
+                    collection.drop(None)?;
+                    info!("Dropped collection {}", name);
+                }
+                _ => {}
             }
-        }?;
+        }
+
+        let mut failures = Vec::new();
+        for (name, values) in collections.iter() {
+            failures.extend(self.insert_data(name, values, &params.namespace, &database)?);
+        }
+
+        if !failures.is_empty() {
+            for failure in failures.iter() {
+                warn!(
+                    "Could not insert document {} into collection {}: {}",
+                    failure.index, failure.collection, failure.message
+                );
+            }
+            bail!(
+                "{} documents could not be inserted. The other documents were inserted",
+                failures.len()
+            );
+        }
 
         Ok(output)
     }
 }
 
+/// A document which the server refused to insert.
+#[derive(Debug)]
+struct InsertFailure {
+    collection: String,
+    /// The index of the document among the generated documents of its collection
+    index: usize,
+    message: String,
+}
+
 impl MongoExportStrategy {
+    /// Inserts the documents in batches of `batch_size`. The documents of a batch are inserted in
+    /// no particular order, such that a failing document doesn't stop the insertion of the others.
     fn insert_data(
         &self,
-        collection_name: &str,
+        collection_name: &Name,
         collection: &[Value],
-        client: &mut Client,
-    ) -> Result<()> {
-        let db_name = parse_db_name(&self.uri)?;
+        namespace: &Namespace,
+        database: &Database,
+    ) -> Result<Vec<InsertFailure>> {
+        let content = match namespace.get_collection(collection_name)? {
+            Content::Array(ArrayContent { content, .. }) => Some(content.as_ref()),
+            _ => None,
+        };
 
         let mut docs = Vec::new();
 
         for value in collection {
-            docs.push(match value_to_bson(value.clone()) {
+            docs.push(match value_to_bson(value.clone(), content, namespace)? {
                 Bson::Document(doc) => doc,
                 _ => bail!("invalid bson document"),
             });
         }
 
-        let n_values = docs.len();
+        let target = database.collection::<Document>(collection_name.as_ref());
+        let mut options = InsertManyOptions::default();
+        options.ordered = Some(false);
 
-        client
-            .database(db_name)
-            .collection(collection_name)
-            .insert_many(docs, None)?;
+        let mut failures = Vec::new();
+        for (batch_index, batch) in docs.chunks(self.batch_size).enumerate() {
+            let offset = batch_index * self.batch_size;
+            match target.insert_many(batch, options.clone()) {
+                Ok(_) => {}
+                Err(e) => match *e.kind {
+                    ErrorKind::BulkWrite(BulkWriteFailure {
+                        write_errors: Some(write_errors),
+                        write_concern_error: None,
+                        ..
+                    }) => {
+                        failures.extend(write_errors.into_iter().map(|write_error| InsertFailure {
+                            collection: collection_name.to_string(),
+                            index: offset + write_error.index,
+                            message: write_error.message,
+                        }))
+                    }
+                    kind => {
+                        return Err(anyhow!("{}", kind)).with_context(|| {
+                            format!(
+                                "Failed to insert documents {} to {} into collection {}",
+                                offset,
+                                offset + batch.len() - 1,
+                                collection_name
+                            )
+                        })
+                    }
+                },
+            }
+        }
 
         info!(
             "Inserted {} rows into collection {} ...",
-            n_values, collection_name
+            docs.len() - failures.len(),
+            collection_name
         );
 
-        Ok(())
+        Ok(failures)
     }
 }
 
-fn value_to_bson(value: Value) -> Bson {
-    match value {
+/// Reference chains longer than this are assumed to be cycles.
+const MAX_REFERENCE_DEPTH: usize = 32;
+
+/// Converts a generated value to BSON. `content` is the content which generated the value, if
+/// known. It tells the strings generated by `object_id` apart from other strings.
+fn value_to_bson(value: Value, content: Option<&Content>, namespace: &Namespace) -> Result<Bson> {
+    let content = match content {
+        Some(content) => Some(resolve_content(content, namespace, &value)?),
+        None => None,
+    };
+
+    let bson = match value {
         Value::Null(_) => Bson::Null,
         Value::Bool(b) => Bson::Boolean(b),
         Value::Number(n) => number_to_bson(n),
-        Value::String(s) => Bson::String(s),
+        Value::String(s) => match content {
+            Some(Content::String(StringContent::ObjectId(_))) => Bson::ObjectId(
+                oid::ObjectId::parse_str(&s)
+                    .with_context(|| anyhow!("'{}' is not a valid ObjectId", s))?,
+            ),
+            _ => Bson::String(s),
+        },
         Value::DateTime(dt) => date_time_to_bson(dt.value), //TODO: format instead?
-        Value::Object(obj) => object_to_bson(obj),
-        Value::Array(arr) => array_to_bson(arr),
+        Value::Object(obj) => object_to_bson(obj, content, namespace)?,
+        Value::Array(arr) => array_to_bson(arr, content, namespace)?,
+    };
+
+    Ok(bson)
+}
+
+/// Finds the content which generated `value` behind references, unique and hidden content. Only
+/// the non-null variant of nullable content and the `object_id` variants of other `one_of`s are
+/// looked into.
+fn resolve_content<'a>(
+    mut content: &'a Content,
+    namespace: &'a Namespace,
+    value: &Value,
+) -> Result<&'a Content> {
+    for _ in 0..MAX_REFERENCE_DEPTH {
+        content = match content {
+            Content::Unique(UniqueContent { content, .. })
+            | Content::Hidden(HiddenContent { content }) => content,
+            Content::SameAs(SameAsContent { ref_ }) => namespace.get_s_node(ref_)?,
+            Content::OneOf(one_of) => match one_of.as_nullable() {
+                Some(content) => content,
+                None => {
+                    let is_object_id = |variant: &&VariantContent| {
+                        matches!(value, Value::String(s) if oid::ObjectId::parse_str(s).is_ok())
+                            && matches!(
+                                resolve_content(&variant.content, namespace, value),
+                                Ok(Content::String(StringContent::ObjectId(_)))
+                            )
+                    };
+                    match one_of.variants.iter().find(is_object_id) {
+                        Some(variant) => &variant.content,
+                        None => return Ok(content),
+                    }
+                }
+            },
+            _ => return Ok(content),
+        };
     }
+
+    bail!("Could not resolve the content of {:?}", content)
 }
 
-fn array_to_bson(array: Vec<Value>) -> Bson {
-    Bson::Array(array.into_iter().map(value_to_bson).collect())
+fn array_to_bson(
+    array: Vec<Value>,
+    content: Option<&Content>,
+    namespace: &Namespace,
+) -> Result<Bson> {
+    let content = match content {
+        Some(Content::Array(ArrayContent { content, .. })) => Some(content.as_ref()),
+        _ => None,
+    };
+
+    Ok(Bson::Array(
+        array
+            .into_iter()
+            .map(|value| value_to_bson(value, content, namespace))
+            .collect::<Result<_>>()?,
+    ))
 }
 
-fn object_to_bson(obj: BTreeMap<String, Value>) -> Bson {
+fn object_to_bson(
+    obj: BTreeMap<String, Value>,
+    content: Option<&Content>,
+    namespace: &Namespace,
+) -> Result<Bson> {
+    let fields = match content {
+        Some(Content::Object(ObjectContent { fields, .. })) => Some(fields),
+        _ => None,
+    };
+
     let obj = obj
         .into_iter()
-        .map(|(name, value)| (name, value_to_bson(value)))
-        .collect();
-    Bson::Document(obj)
+        .map(|(name, value)| {
+            let content = fields.and_then(|fields| fields.get(&name));
+            Ok((name, value_to_bson(value, content, namespace)?))
+        })
+        .collect::<Result<_>>()?;
+    Ok(Bson::Document(obj))
 }
 
 fn date_time_to_bson(datetime: ChronoValue) -> Bson {
@@ -284,3 +950,288 @@ fn parse_db_name(uri: &str) -> Result<&str> {
         .last()
         .ok_or_else(|| anyhow!("Cannot export data. No database name specified in the uri"))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mongodb::bson::oid::ObjectId;
+    use mongodb::bson::Decimal128;
+    use synth_core::schema::FieldRef;
+
+    #[test]
+    fn decimal128_to_f64() {
+        let decimal = |high: u64, low: u64| {
+            let mut bytes = [0; 16];
+            bytes[..8].copy_from_slice(&low.to_le_bytes());
+            bytes[8..].copy_from_slice(&high.to_le_bytes());
+            super::decimal128_to_f64(Decimal128::from_bytes(bytes).bytes())
+        };
+
+        // 15 * 10^-1
+        assert_eq!(decimal(0x303e_0000_0000_0000, 15), 1.5);
+        // -15 * 10^-1
+        assert_eq!(decimal(0xb03e_0000_0000_0000, 15), -1.5);
+        // 12 * 10^0
+        assert_eq!(decimal(0x3040_0000_0000_0000, 12), 12.);
+        assert_eq!(decimal(0x7800_0000_0000_0000, 0), f64::INFINITY);
+        assert!(decimal(0x7c00_0000_0000_0000, 0).is_nan());
+    }
+
+    #[test]
+    fn union_doc_fields() {
+        let mut object = ObjectContent::default();
+        for doc in [
+            doc! { "_id": ObjectId::new(), "a": "x", "b": 1, "c": null },
+            doc! { "_id": ObjectId::new(), "a": 2, "c": { "d": true } },
+            doc! { "_id": ObjectId::new(), "a": "y", "c": { "e": 1.5 } },
+        ] {
+            super::union_doc_fields(&mut object, &doc);
+        }
+
+        assert!(matches!(
+            object.fields.get("_id").unwrap(),
+            Content::String(StringContent::ObjectId(_))
+        ));
+
+        match object.fields.get("a").unwrap() {
+            Content::OneOf(one_of) => {
+                assert_eq!(one_of.variants.len(), 2);
+                assert!(matches!(*one_of.variants[0].content, Content::String(_)));
+                assert!(matches!(*one_of.variants[1].content, Content::Number(_)));
+            }
+            other => panic!("expected a one_of, found {:?}", other),
+        }
+
+        assert!(matches!(
+            object.fields.get("b").unwrap(),
+            Content::Number(_)
+        ));
+
+        match object.fields.get("c").unwrap().as_nullable() {
+            Some(Content::Object(c)) => {
+                assert!(c.fields.contains_key("d"));
+                assert!(c.fields.contains_key("e"));
+            }
+            other => panic!("expected a nullable object, found {:?}", other),
+        }
+    }
+
+    #[test]
+    fn bson_to_json() {
+        let oid = ObjectId::parse_str("5f1d7f0c9d3b2a0001a1b2c3").unwrap();
+        let doc = doc! {
+            "oid": oid,
+            "binary": Bson::Binary(mongodb::bson::Binary {
+                subtype: mongodb::bson::spec::BinarySubtype::Generic,
+                bytes: vec![0, 15, 255],
+            }),
+            "timestamp": Bson::Timestamp(mongodb::bson::Timestamp { time: 0, increment: 1 }),
+        };
+
+        assert_eq!(
+            super::bson_to_json(Bson::Document(doc)),
+            serde_json::json!({
+                "oid": "5f1d7f0c9d3b2a0001a1b2c3",
+                "binary": "000fff",
+                "timestamp": "1970-01-01T00:00:00+0000",
+            })
+        );
+    }
+
+    #[test]
+    fn merge_sampled_documents() {
+        let docs = vec![doc! { "a": "x", "b": 1 }, doc! { "a": "y", "c": [1, 2, 3] }];
+
+        let mut master = ObjectContent::default();
+        docs.iter()
+            .for_each(|doc| super::union_doc_fields(&mut master, doc));
+
+        let name = Name::from_str("collection").unwrap();
+        let mut namespace = Namespace::default();
+        namespace
+            .put_collection(
+                &name,
+                Content::Array(ArrayContent::from_content_default_length(Content::Object(
+                    master,
+                ))),
+            )
+            .unwrap();
+        let as_json: Vec<JsonValue> = docs
+            .into_iter()
+            .map(|doc| super::bson_to_json(Bson::Document(doc)))
+            .collect();
+        namespace
+            .default_try_update(&name, &JsonValue::Array(as_json))
+            .unwrap();
+
+        let collection = match namespace.get_collection(&name).unwrap() {
+            Content::Array(array) => match *array.content.clone() {
+                Content::Object(object) => object,
+                other => panic!("expected an object, found {:?}", other),
+            },
+            other => panic!("expected an array, found {:?}", other),
+        };
+        assert!(!collection.fields.get("a").unwrap().is_nullable());
+        assert!(collection.fields.get("b").unwrap().is_nullable());
+        assert!(collection.fields.get("c").unwrap().is_nullable());
+    }
+
+    #[test]
+    fn import_validator_pattern() {
+        let schema = doc! {
+            "bsonType": "object",
+            "required": ["sku", "note"],
+            "properties": {
+                "sku": { "bsonType": "string", "pattern": "[A-Z]{3}-[0-9]{4}" },
+                "note": { "bsonType": "string" }
+            }
+        };
+        let validator = match super::json_schema_to_content(&schema).unwrap() {
+            Some(Content::Object(object)) => object,
+            other => panic!("expected an object, found {:?}", other),
+        };
+        let docs: Vec<Document> = (0..10)
+            .map(|i| doc! { "sku": format!("ABC-000{}", i), "note": format!("ORD-{}", i) })
+            .collect();
+
+        let name = Name::from_str("products").unwrap();
+        let mut namespace = Namespace::default();
+        super::import_sampled_collection(&mut namespace, &name, Some(validator), docs, None)
+            .unwrap();
+
+        let collection = serde_json::to_value(namespace.get_collection(&name).unwrap()).unwrap();
+        // The constraint of the validator is kept, while the unconstrained field is refined
+        assert_eq!(collection["content"]["sku"]["pattern"], "[A-Z]{3}-[0-9]{4}");
+        assert_eq!(collection["content"]["note"]["pattern"], "ORD\\-[0-9]");
+    }
+
+    #[test]
+    fn value_to_bson() {
+        let mut namespace = Namespace::default();
+        let object_id = Content::String(StringContent::ObjectId(ObjectId));
+        let users = Name::from_str("users").unwrap();
+        let mut user = ObjectContent::default();
+        user.fields.insert("_id".to_string(), object_id);
+        namespace
+            .put_collection(
+                &users,
+                Content::Array(ArrayContent::from_content_default_length(Content::Object(
+                    user,
+                ))),
+            )
+            .unwrap();
+
+        let mut post = ObjectContent::default();
+        post.fields.insert(
+            "user_id".to_string(),
+            Content::SameAs(SameAsContent {
+                ref_: FieldRef::new("users.content._id").unwrap(),
+            })
+            .into_nullable(),
+        );
+        post.fields.insert(
+            "title".to_string(),
+            Content::String(StringContent::default()),
+        );
+
+        let hex = "5f1d7f0c9d3b2a0001a1b2c3";
+        let value = Value::Object(
+            vec![
+                ("user_id".to_string(), Value::String(hex.to_string())),
+                ("title".to_string(), Value::String(hex.to_string())),
+            ]
+            .into_iter()
+            .collect(),
+        );
+
+        assert_eq!(
+            super::value_to_bson(value, Some(&Content::Object(post)), &namespace).unwrap(),
+            Bson::Document(doc! {
+                "title": hex,
+                "user_id": oid::ObjectId::parse_str(hex).unwrap(),
+            })
+        );
+    }
+
+    #[test]
+    fn json_schema_to_content() {
+        let schema = doc! {
+            "bsonType": "object",
+            "required": ["name", "age"],
+            "properties": {
+                "name": { "bsonType": "string", "pattern": "[a-z]{3}" },
+                "age": { "bsonType": "int", "minimum": 18, "maximum": 99, "exclusiveMaximum": true },
+                "score": { "bsonType": ["double", "null"], "minimum": 0 },
+                "status": { "enum": ["active", "inactive"] },
+                "tags": { "bsonType": "array", "maxItems": 3, "items": { "bsonType": "string" } },
+                "data": { "bsonType": "binData" },
+            }
+        };
+
+        let object = match super::json_schema_to_content(&schema).unwrap() {
+            Some(Content::Object(object)) => object,
+            other => panic!("expected an object, found {:?}", other),
+        };
+
+        assert!(matches!(
+            object.fields.get("name").unwrap(),
+            Content::String(StringContent::Pattern(_))
+        ));
+        match object.fields.get("age").unwrap() {
+            Content::Number(NumberContent::I64(I64::Range(range))) => {
+                assert_eq!(range.low, Some(18));
+                assert_eq!(range.high, Some(99));
+                assert!(range.include_low);
+                assert!(!range.include_high);
+            }
+            other => panic!("expected an i64 range, found {:?}", other),
+        }
+        match object.fields.get("score").unwrap().as_nullable() {
+            Some(Content::Number(NumberContent::F64(F64::Range(range)))) => {
+                assert_eq!(range.low, Some(0.));
+                assert_eq!(range.high, None);
+            }
+            other => panic!("expected a nullable f64 range, found {:?}", other),
+        }
+        assert!(matches!(
+            object.fields.get("status").unwrap().as_nullable(),
+            Some(Content::String(StringContent::Categorical(_)))
+        ));
+        match object.fields.get("tags").unwrap().as_nullable() {
+            Some(Content::Array(array)) => {
+                assert!(matches!(*array.content, Content::String(_)));
+            }
+            other => panic!("expected a nullable array, found {:?}", other),
+        }
+        assert!(!object.fields.contains_key("data"));
+    }
+
+    #[test]
+    fn unique_fields() {
+        let mut address = ObjectContent::default();
+        address.fields.insert(
+            "email".to_string(),
+            Content::String(StringContent::default()),
+        );
+        let mut user = ObjectContent::default();
+        user.fields.insert(
+            "address".to_string(),
+            Content::Object(address).into_nullable(),
+        );
+        let mut content = Content::Object(user);
+
+        let email = object_field_mut(&mut content, "address.email").unwrap();
+        *email = into_unique(email.clone().into_nullable());
+        assert!(object_field_mut(&mut content, "address.phone").is_none());
+
+        match object_field_mut(&mut content, "address.email")
+            .unwrap()
+            .as_nullable()
+        {
+            Some(Content::Unique(unique)) => {
+                assert!(matches!(*unique.content, Content::String(_)))
+            }
+            other => panic!("expected nullable unique content, found {:?}", other),
+        }
+    }
+}
@@ -1,46 +1,94 @@
-use crate::cli::export::{create_and_insert_values, ExportParams, ExportStrategy};
-use crate::cli::import::ImportStrategy;
+use crate::cli::export::{create_and_insert_values, ExportParams, ExportStrategy, InsertParams};
+use crate::cli::import::{take_collection, ImportStrategy};
 use crate::cli::import_utils::build_namespace_import;
-use crate::datasource::mysql_datasource::MySqlDataSource;
-use crate::datasource::DataSource;
+use crate::cli::pii::Finding;
+use crate::datasource::mask::mask_source;
+use crate::datasource::mysql_datasource::{MySqlConnectParams, MySqlDataSource};
+use crate::datasource::subset::collect_subset;
+use crate::datasource::{DataSource, LoadParams, SampleParams, Tables};
 use crate::sampler::SamplerOutput;
 use anyhow::Result;
+use async_std::task;
 use serde_json::Value;
 use synth_core::schema::Namespace;
-use synth_core::{Content, Name};
+use synth_core::{Content, Name, Value as SynthValue};
 
 #[derive(Clone, Debug)]
 pub struct MySqlExportStrategy {
     pub uri: String,
+    pub load: LoadParams,
 }
 
 impl ExportStrategy for MySqlExportStrategy {
     fn export(&self, params: ExportParams) -> Result<SamplerOutput> {
-        let datasource = MySqlDataSource::new(&self.uri)?;
+        let connect_params = MySqlConnectParams {
+            uri: self.uri.clone(),
+            load: self.load,
+        };
+
+        let datasource = MySqlDataSource::new(&connect_params)?;
 
         create_and_insert_values(params, &datasource)
     }
+
+    fn insert(&self, params: InsertParams) -> Result<()> {
+        let connect_params = MySqlConnectParams {
+            uri: self.uri.clone(),
+            load: self.load,
+        };
+
+        let datasource = MySqlDataSource::new(&connect_params)?;
+
+        params.insert_into(&datasource)
+    }
 }
 
 #[derive(Clone, Debug)]
 pub struct MySqlImportStrategy {
     pub uri: String,
+    pub sample: SampleParams,
+    pub load: LoadParams,
 }
 
 impl ImportStrategy for MySqlImportStrategy {
-    fn import(&self) -> Result<Namespace> {
-        let datasource = MySqlDataSource::new(&self.uri)?;
+    fn import(&self) -> Result<(Namespace, Vec<Finding>)> {
+        let connect_params = MySqlConnectParams {
+            uri: self.uri.clone(),
+            load: self.load,
+        };
+
+        let datasource = MySqlDataSource::new(&connect_params)?;
 
-        build_namespace_import(&datasource)
+        build_namespace_import(&datasource, &self.sample)
     }
 
-    fn import_collection(&self, name: &Name) -> Result<Content> {
-        self.import()?
-            .collections
-            .remove(name)
+    fn import_collection(&self, name: &Name) -> Result<(Content, Vec<Finding>)> {
+        take_collection(self.import()?, name)
             .ok_or_else(|| anyhow!("Could not find table '{}' in Postgres database.", name))
     }
 
+    fn subset(&self, root: &str, condition: &str) -> Result<Vec<(String, Vec<SynthValue>)>> {
+        let connect_params = MySqlConnectParams {
+            uri: self.uri.clone(),
+            load: self.load,
+        };
+
+        let datasource = MySqlDataSource::new(&connect_params)?;
+
+        task::block_on(collect_subset(&datasource, root, condition))
+    }
+
+    fn mask(&self, masks: &Namespace, seed: u64, secret: Option<&str>) -> Result<Tables> {
+        let connect_params = MySqlConnectParams {
+            uri: self.uri.clone(),
+            load: self.load,
+        };
+
+        let datasource = MySqlDataSource::new(&connect_params)?;
+
+        task::block_on(mask_source(datasource, masks, seed, secret))
+    }
+
     fn as_value(&self) -> Result<Value> {
         bail!("MySql import doesn't support conversion into value")
     }
@@ -0,0 +1,375 @@
+use lazy_static::lazy_static;
+use regex::Regex;
+use serde_json::Value;
+use std::fmt::{self, Display, Formatter};
+use std::net::IpAddr;
+use synth_core::schema::inference::visit_sampled_fields;
+use synth_core::schema::{FakerContent, StringContent};
+use synth_core::Content;
+
+lazy_static! {
+    static ref EMAIL: Regex = Regex::new(r"^[^@\s]+@[^@\s]+\.[a-zA-Z]{2,}$").unwrap();
+    static ref PHONE: Regex =
+        Regex::new(r"^(\+\d{1,3}[ .-]?)?(\(\d{1,4}\)[ .-]?)?\d{2,4}([ .-]\d{2,4}){1,4}$").unwrap();
+    static ref DATE: Regex = Regex::new(r"^\d{4}-\d{2}-\d{2}$").unwrap();
+    static ref CREDIT_CARD: Regex = Regex::new(r"^\d{4}([ -]?\d{3,4}){2,4}$").unwrap();
+    static ref NAME_WORD: Regex =
+        Regex::new(r"^\p{Lu}\p{Ll}*(\p{Lu}\p{Ll}+)?(['’-]\p{Lu}?\p{Ll}+)*\.?$").unwrap();
+}
+
+/// The share of the sampled values of a field which have to look like personal data for the
+/// field to be flagged.
+const THRESHOLD: f64 = 0.8;
+
+/// The kinds of personal data which are detected in sampled strings.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum PiiKind {
+    Email,
+    Phone,
+    CreditCard,
+    Ipv4,
+    Ipv6,
+    /// Both IPv4 and IPv6 addresses
+    Ip,
+    FullName,
+    FirstName,
+    LastName,
+}
+
+impl PiiKind {
+    /// The faker generator which replaces fields of this kind.
+    fn generator(self) -> &'static str {
+        match self {
+            Self::Email => "safe_email",
+            Self::Phone => "phone_number",
+            Self::CreditCard => "credit_card",
+            Self::Ipv4 => "ipv4",
+            Self::Ipv6 => "ipv6",
+            Self::Ip => "ip",
+            Self::FullName => "name",
+            Self::FirstName => "first_name",
+            Self::LastName => "last_name",
+        }
+    }
+}
+
+impl Display for PiiKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let kind = match self {
+            Self::Email => "email addresses",
+            Self::Phone => "phone numbers",
+            Self::CreditCard => "credit card numbers",
+            Self::Ipv4 | Self::Ipv6 | Self::Ip => "IP addresses",
+            Self::FullName | Self::FirstName | Self::LastName => "names",
+        };
+        write!(f, "{}", kind)
+    }
+}
+
+/// A field whose content was replaced, as its sampled values look like personal data.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Finding {
+    pub(crate) collection: String,
+    /// The path to the field, starting with the name of its collection
+    pub(crate) field: String,
+    pub(crate) kind: PiiKind,
+    pub(crate) matched: usize,
+    pub(crate) sampled: usize,
+}
+
+impl Finding {
+    /// The name of the field of the collection which contains the replaced field.
+    pub(crate) fn top_level_field(&self) -> &str {
+        let path = self.field[self.collection.len()..].trim_start_matches('.');
+        path.split('.').next().unwrap_or_default()
+    }
+}
+
+impl Display for Finding {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}: {} of {} sampled values look like {}. Replaced with the faker generator {}",
+            self.field,
+            self.matched,
+            self.sampled,
+            self.kind,
+            self.kind.generator()
+        )
+    }
+}
+
+/// Scans the sampled values of every string field of an imported collection for personal data,
+/// such as email addresses, phone numbers, names, credit card numbers and IP addresses. Fields
+/// which mostly contain personal data are replaced by a faker generator of the same kind, such
+/// that none of the sampled values end up in the namespace.
+///
+/// `samples` is the array of sampled values of the collection.
+pub(crate) fn scan_collection(name: &str, content: &mut Content, samples: &Value) -> Vec<Finding> {
+    let mut findings = Vec::new();
+    visit_sampled_fields(
+        name,
+        content,
+        samples,
+        &mut |path, field, content, values| {
+            if let Content::String(StringContent::Pattern(_) | StringContent::Categorical(_)) =
+                content
+            {
+                let strings: Vec<&str> = values.iter().filter_map(|value| value.as_str()).collect();
+                if let Some(finding) = detect(name, path, field, &strings) {
+                    *content = Content::String(StringContent::Faker(FakerContent {
+                        generator: finding.kind.generator().to_string(),
+                        locales: Vec::new(),
+                        args: Default::default(),
+                    }));
+                    findings.push(finding);
+                }
+            }
+        },
+    );
+    findings
+}
+
+/// Logs the fields replaced by a scan.
+pub(crate) fn report_findings(findings: &[Finding]) {
+    for finding in findings.iter() {
+        warn!("{}", finding);
+    }
+}
+
+/// Prints a summary of the fields replaced during an import, along with the kind of personal
+/// data they looked like and the faker generator which replaced them.
+pub(crate) fn print_findings_summary(findings: &[Finding]) {
+    if findings.is_empty() {
+        return;
+    }
+
+    let rows: Vec<[String; 3]> = findings
+        .iter()
+        .map(|finding| {
+            [
+                finding.field.clone(),
+                finding.kind.to_string(),
+                finding.kind.generator().to_string(),
+            ]
+        })
+        .collect();
+    let header = ["FIELD", "DETECTED", "REPLACED WITH"].map(String::from);
+    let widths: Vec<usize> = (0..2)
+        .map(|i| {
+            rows.iter()
+                .chain(std::iter::once(&header))
+                .map(|row| row[i].chars().count())
+                .max()
+                .unwrap_or(0)
+        })
+        .collect();
+
+    println!(
+        "{} field(s) looked like personal data and were replaced with fakers:",
+        findings.len()
+    );
+    for row in std::iter::once(&header).chain(rows.iter()) {
+        println!(
+            "  {:<w0$}  {:<w1$}  {}",
+            row[0],
+            row[1],
+            row[2],
+            w0 = widths[0],
+            w1 = widths[1]
+        );
+    }
+}
+
+/// The kind of personal data most of the given values of a field look like, if any.
+fn detect(collection: &str, path: &str, field: &str, values: &[&str]) -> Option<Finding> {
+    let values: Vec<&str> = values
+        .iter()
+        .map(|value| value.trim())
+        .filter(|value| !value.is_empty())
+        .collect();
+    if values.is_empty() {
+        return None;
+    }
+
+    let name_kind = name_kind(field);
+    let mut counts: Vec<(PiiKind, usize)> = Vec::new();
+    for value in values.iter() {
+        if let Some(kind) = classify(value, name_kind) {
+            match counts.iter_mut().find(|(counted, _)| *counted == kind) {
+                Some((_, count)) => *count += 1,
+                None => counts.push((kind, 1)),
+            }
+        }
+    }
+
+    // Columns mixing both IP versions are still IP addresses
+    let ips: usize = counts
+        .iter()
+        .filter(|(kind, _)| matches!(kind, PiiKind::Ipv4 | PiiKind::Ipv6))
+        .map(|(_, count)| count)
+        .sum();
+
+    let (kind, matched) = counts.into_iter().max_by_key(|(_, count)| *count)?;
+    let (kind, matched) = match kind {
+        PiiKind::Ipv4 | PiiKind::Ipv6 if matched < ips => (PiiKind::Ip, ips),
+        _ => (kind, matched),
+    };
+
+    if (matched as f64) < THRESHOLD * values.len() as f64 {
+        return None;
+    }
+
+    Some(Finding {
+        collection: collection.to_string(),
+        field: path.to_string(),
+        kind,
+        matched,
+        sampled: values.len(),
+    })
+}
+
+fn classify(value: &str, name_kind: Option<PiiKind>) -> Option<PiiKind> {
+    if EMAIL.is_match(value) {
+        return Some(PiiKind::Email);
+    }
+
+    match value.parse::<IpAddr>() {
+        Ok(IpAddr::V4(_)) => return Some(PiiKind::Ipv4),
+        Ok(IpAddr::V6(_)) => return Some(PiiKind::Ipv6),
+        Err(_) => {}
+    }
+
+    if CREDIT_CARD.is_match(value) && luhn(value) {
+        return Some(PiiKind::CreditCard);
+    }
+
+    let digits = value.chars().filter(char::is_ascii_digit).count();
+    if PHONE.is_match(value) && !DATE.is_match(value) && (7..=15).contains(&digits) {
+        return Some(PiiKind::Phone);
+    }
+
+    match name_kind {
+        // Single capitalised words are too common to tell full names apart from other values
+        Some(PiiKind::FullName) if is_name(value, 2) => Some(PiiKind::FullName),
+        Some(kind @ (PiiKind::FirstName | PiiKind::LastName)) if is_name(value, 1) => Some(kind),
+        _ => None,
+    }
+}
+
+/// The kind of name a field contains, judging by its name. Names are only detected in such
+/// fields, as any capitalised word would otherwise be taken for a name.
+fn name_kind(field: &str) -> Option<PiiKind> {
+    let field: String = field
+        .chars()
+        .filter(|c| c.is_alphanumeric())
+        .flat_map(char::to_lowercase)
+        .collect();
+
+    match field.as_str() {
+        "firstname" | "givenname" | "forename" => Some(PiiKind::FirstName),
+        "lastname" | "surname" | "familyname" => Some(PiiKind::LastName),
+        "name" | "fullname" | "customername" | "contactname" | "personname" | "displayname" => {
+            Some(PiiKind::FullName)
+        }
+        _ => None,
+    }
+}
+
+/// Whether the value is made of at least `min_words` and at most 4 capitalised words.
+fn is_name(value: &str, min_words: usize) -> bool {
+    let words: Vec<&str> = value.split_whitespace().collect();
+    (min_words..=4).contains(&words.len()) && words.iter().all(|word| NAME_WORD.is_match(word))
+}
+
+/// Whether the digits of the value pass the Luhn checksum of credit card numbers.
+fn luhn(value: &str) -> bool {
+    let sum: u32 = value
+        .chars()
+        .filter_map(|c| c.to_digit(10))
+        .rev()
+        .enumerate()
+        .map(|(i, digit)| match (i % 2, digit * 2) {
+            (0, _) => digit,
+            (_, doubled) if doubled > 9 => doubled - 9,
+            (_, doubled) => doubled,
+        })
+        .sum();
+    sum % 10 == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn scan_collection() {
+        let mut content: Content = serde_json::from_value(json!({
+            "type": "array",
+            "length": 1,
+            "content": {
+                "type": "object",
+                "name": { "type": "string", "pattern": "[a-z]*" },
+                "product": { "type": "string", "pattern": "[a-z]*" },
+                "email": {
+                    "type": "one_of",
+                    "variants": [{ "type": "string", "pattern": "[a-z]*" }, { "type": "null" }]
+                },
+                "contact": {
+                    "type": "object",
+                    "phone": { "type": "string", "pattern": "[a-z]*" },
+                    "ip": { "type": "string", "pattern": "[a-z]*" }
+                },
+                "card": { "type": "string", "pattern": "[a-z]*" },
+                "created": { "type": "string", "pattern": "[a-z]*" }
+            }
+        }))
+        .unwrap();
+
+        let samples = json!([
+            {
+                "name": "Alice Smith",
+                "product": "Widget",
+                "email": "alice@example.com",
+                "contact": { "phone": "+1 555-123-4567", "ip": "10.0.0.1" },
+                "card": "4111 1111 1111 1111",
+                "created": "2021-10-25"
+            },
+            {
+                "name": "Bob O'Neil",
+                "product": "Gadget",
+                "email": null,
+                "contact": { "phone": "(020) 7946 0958", "ip": "::1" },
+                "card": "5500-0000-0000-0004",
+                "created": "2021-10-26"
+            },
+        ]);
+
+        let findings = super::scan_collection("users", &mut content, &samples);
+        let flagged: Vec<(&str, PiiKind, usize)> = findings
+            .iter()
+            .map(|finding| (finding.field.as_str(), finding.kind, finding.sampled))
+            .collect();
+        assert_eq!(
+            flagged,
+            vec![
+                ("users.card", PiiKind::CreditCard, 2),
+                ("users.contact.ip", PiiKind::Ip, 2),
+                ("users.contact.phone", PiiKind::Phone, 2),
+                ("users.email", PiiKind::Email, 1),
+                ("users.name", PiiKind::FullName, 2),
+            ]
+        );
+
+        let content = serde_json::to_value(&content).unwrap();
+        assert_eq!(content["content"]["name"]["faker"]["generator"], "name");
+        assert_eq!(
+            content["content"]["email"]["variants"][0]["faker"]["generator"],
+            "safe_email"
+        );
+        assert_eq!(content["content"]["product"]["pattern"], "[a-z]*");
+        assert_eq!(content["content"]["created"]["pattern"], "[a-z]*");
+        assert_eq!(findings[1].top_level_field(), "contact");
+    }
+}
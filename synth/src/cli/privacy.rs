@@ -0,0 +1,514 @@
+use crate::datasource::Privacy;
+use chrono::{Duration, NaiveDate, NaiveDateTime, NaiveTime, TimeZone, Timelike};
+use rand::distributions::{Distribution, WeightedIndex};
+use rand::Rng;
+use serde_json::Value;
+use std::convert::TryFrom;
+use synth_core::schema::inference::visit_sampled_content;
+use synth_core::schema::number_content::{F32, F64, I32, I64, U32, U64};
+use synth_core::schema::{
+    ArrayContent, BoolContent, Categorical, CategoricalType, ChronoValue, ChronoValueFormatter,
+    DateTimeContent, NumberContent, OneOfContent, RangeStep, StringContent, Weight,
+};
+use synth_core::Content;
+
+/// The quantiles which replace the smallest and largest sampled values of numeric ranges.
+const LOW_QUANTILE: f64 = 0.05;
+const HIGH_QUANTILE: f64 = 0.95;
+
+/// The public upper bound of the lengths of arrays, which are clamped to it.
+const MAX_ARRAY_LENGTH: f64 = 1000.0;
+
+/// The public bounds of dates and times, which are clamped to them: 1900-01-01 and 2100-01-01,
+/// in seconds since the Unix epoch. Times are bounded by the length of a day.
+const DATE_TIME_DOMAIN: (f64, f64) = (-2_208_988_800.0, 4_102_444_800.0);
+const TIME_DOMAIN: (f64, f64) = (0.0, 86_400.0);
+
+/// Adds calibrated noise to the statistics inferred from the sampled values of an imported
+/// collection, such that they don't reveal individual rows:
+///
+/// - The weights of categories get Laplace noise of scale `1 / epsilon`, and the categories whose
+///   noisy weight is below the threshold are left out. Categorical content left without any
+///   category falls back to the default content of its type.
+/// - The bounds of numeric ranges are replaced by their 5th and 95th percentiles, picked by the
+///   exponential mechanism over the domain of their type, narrowed by the clamp of `privacy`,
+///   with half of the budget each. Distributions fitted to the values are replaced by such
+///   ranges, as their parameters aren't noisy.
+/// - The bounds of dates and times are replaced by their noisy 5th and 95th percentiles between
+///   1900 and 2100, like the bounds of numeric ranges.
+/// - The lengths of arrays are noised like numbers of at most `MAX_ARRAY_LENGTH`, and the weights
+///   of the null and non null variants of nullable fields get Laplace noise like categories.
+///
+/// The budget is spent on every statistic separately, so the privacy loss of the collection adds
+/// up over its statistics.
+///
+/// `samples` is the array of sampled values of the collection.
+pub(crate) fn add_noise<R: Rng>(
+    name: &str,
+    collection: &mut Content,
+    samples: &Value,
+    privacy: &Privacy,
+    rng: &mut R,
+) {
+    visit_sampled_content(
+        name,
+        collection,
+        samples,
+        &mut |path, _, content, values| {
+            match content {
+                // The length of the collection itself is not inferred from the samples
+                Content::Array(ArrayContent { length, .. }) if path != name => {
+                    if let Content::Number(number) = length.as_mut() {
+                        let lengths: Vec<f64> = values
+                            .iter()
+                            .filter_map(|value| value.as_array())
+                            .map(|array| array.len() as f64)
+                            .collect();
+                        let domain = Some((0.0, MAX_ARRAY_LENGTH));
+                        add_noise_to_number(path, number, &lengths, domain, privacy, rng);
+                        // Arrays of unbounded length would never be generated
+                        if let NumberContent::U64(U64::Range(RangeStep { high: None, .. })) = number
+                        {
+                            *number = NumberContent::U64(U64::Range(RangeStep::new(1, 2, 1)));
+                        }
+                    }
+                }
+                Content::OneOf(one_of) if one_of.is_nullable() => {
+                    add_noise_to_weights(one_of, privacy, rng)
+                }
+                Content::String(StringContent::Categorical(categorical)) => {
+                    *content = match noisy_categorical(categorical, privacy, true, rng) {
+                        Some(categorical) => {
+                            Content::String(StringContent::Categorical(categorical))
+                        }
+                        None => {
+                            warn!("{}: all categories are too rare to be kept", path);
+                            Content::String(StringContent::default())
+                        }
+                    }
+                }
+                // Both categories of booleans are known, so they are never left out
+                Content::Bool(BoolContent::Categorical(categorical)) => {
+                    *content = match noisy_categorical(categorical, privacy, false, rng) {
+                        Some(categorical) => Content::Bool(BoolContent::Categorical(categorical)),
+                        None => Content::Bool(BoolContent::default()),
+                    }
+                }
+                Content::Number(number) => {
+                    let samples: Vec<f64> =
+                        values.iter().filter_map(|value| value.as_f64()).collect();
+                    add_noise_to_number(path, number, &samples, privacy.clamp, privacy, rng);
+                }
+                Content::DateTime(date_time) => {
+                    add_noise_to_date_time(path, date_time, values, privacy, rng)
+                }
+                _ => {}
+            }
+        },
+    );
+}
+
+macro_rules! noisy_number_categorical {
+    ($path:ident, $number:ident, $privacy:ident, $rng:ident, $($as:ident),*) => {
+        match $number {
+            $(NumberContent::$as($as::Categorical(categorical)) => {
+                *$number = match noisy_categorical(categorical, $privacy, true, $rng) {
+                    Some(categorical) => NumberContent::$as($as::Categorical(categorical)),
+                    None => {
+                        warn!("{}: all categories are too rare to be kept", $path);
+                        NumberContent::$as($as::Range(RangeStep::default()))
+                    }
+                }
+            })*
+            _ => {}
+        }
+    };
+}
+
+macro_rules! distribution_to_range {
+    ($number:ident, $($as:ident),*) => {
+        match $number {
+            $(NumberContent::$as($as::Distribution(distribution)) => {
+                *$number = NumberContent::$as($as::Range(RangeStep {
+                    low: distribution.low,
+                    high: distribution.high,
+                    step: distribution.step,
+                    ..Default::default()
+                }))
+            })*
+            _ => {}
+        }
+    };
+}
+
+macro_rules! number_range_domain {
+    ($number:ident, $(($as:ident, $ty:ty)),*) => {
+        match $number {
+            $(NumberContent::$as($as::Range(_)) => Some((<$ty>::MIN as f64, <$ty>::MAX as f64)),)*
+            _ => None,
+        }
+    };
+}
+
+macro_rules! noisy_number_range {
+    ($number:ident, $low:expr, $high:expr, $(($as:ident, $ty:ty)),*) => {
+        match $number {
+            $(NumberContent::$as($as::Range(range)) => {
+                range.low = Some($low as $ty);
+                range.high = Some($high as $ty);
+            })*
+            _ => {}
+        }
+    };
+}
+
+/// Noises the categories or the bounds of the range of the number. The bounds are picked over
+/// the domain of its type, narrowed by `clamp` if given.
+fn add_noise_to_number<R: Rng>(
+    path: &str,
+    number: &mut NumberContent,
+    samples: &[f64],
+    clamp: Option<(f64, f64)>,
+    privacy: &Privacy,
+    rng: &mut R,
+) {
+    distribution_to_range!(number, U64, I64, U32, I32, F64, F32);
+    noisy_number_categorical!(path, number, privacy, rng, U64, I64, U32, I32);
+
+    let domain = match number_range_domain!(
+        number,
+        (U64, u64),
+        (I64, i64),
+        (U32, u32),
+        (I32, i32),
+        (F64, f64),
+        (F32, f32)
+    ) {
+        Some(domain) => domain,
+        None => return,
+    };
+    let domain = match clamp {
+        Some((low, high)) if low.max(domain.0) < high.min(domain.1) => {
+            (low.max(domain.0), high.min(domain.1))
+        }
+        Some(_) => {
+            warn!(
+                "{}: the clamp is outside of the values of its type, which bound its range instead",
+                path
+            );
+            domain
+        }
+        None => domain,
+    };
+
+    let epsilon = privacy.epsilon / 2.0;
+    let low = noisy_quantile(samples, LOW_QUANTILE, domain, epsilon, rng);
+    let high = noisy_quantile(samples, HIGH_QUANTILE, domain, epsilon, rng);
+    let (low, high) = (low.min(high), low.max(high));
+
+    noisy_number_range!(number, low, high, (F64, f64), (F32, f32));
+    noisy_number_range!(
+        number,
+        low.floor(),
+        high.ceil(),
+        (U64, u64),
+        (I64, i64),
+        (U32, u32),
+        (I32, i32)
+    );
+}
+
+/// Replaces the bounds of the date time content by noisy quantiles of the sampled values, or
+/// removes them if no value could be parsed.
+fn add_noise_to_date_time<R: Rng>(
+    path: &str,
+    date_time: &mut DateTimeContent,
+    values: &[&Value],
+    privacy: &Privacy,
+    rng: &mut R,
+) {
+    let formatter = ChronoValueFormatter::new_with(&date_time.format, Some(date_time.type_));
+    let parsed: Vec<ChronoValue> = values
+        .iter()
+        .filter_map(|value| formatter.parse(value.as_str()?).ok())
+        .collect();
+    let samples: Vec<f64> = parsed.iter().map(to_seconds).collect();
+
+    let like = match parsed.first() {
+        Some(like) => like,
+        None => {
+            warn!(
+                "{}: none of the sampled values could be parsed. Its bounds are left out",
+                path
+            );
+            date_time.begin = None;
+            date_time.end = None;
+            return;
+        }
+    };
+    let domain = match like {
+        ChronoValue::NaiveTime(_) => TIME_DOMAIN,
+        _ => DATE_TIME_DOMAIN,
+    };
+
+    let epsilon = privacy.epsilon / 2.0;
+    let low = noisy_quantile(&samples, LOW_QUANTILE, domain, epsilon, rng);
+    let high = noisy_quantile(&samples, HIGH_QUANTILE, domain, epsilon, rng);
+    let (low, high) = (low.min(high), low.max(high));
+
+    date_time.begin = from_seconds(low, like);
+    date_time.end = from_seconds(high, like);
+}
+
+/// The number of seconds between the Unix epoch, or midnight for times, and the value.
+fn to_seconds(value: &ChronoValue) -> f64 {
+    match value {
+        ChronoValue::NaiveDate(date) => {
+            (*date - NaiveDate::from_ymd(1970, 1, 1)).num_seconds() as f64
+        }
+        ChronoValue::NaiveTime(time) => time.num_seconds_from_midnight() as f64,
+        ChronoValue::NaiveDateTime(date_time) => date_time.timestamp() as f64,
+        ChronoValue::DateTime(date_time) => date_time.timestamp() as f64,
+    }
+}
+
+/// The value `seconds` after the Unix epoch, or midnight for times, of the type of `like`.
+fn from_seconds(seconds: f64, like: &ChronoValue) -> Option<ChronoValue> {
+    let seconds = seconds.floor() as i64;
+    match like {
+        ChronoValue::NaiveDate(_) => NaiveDate::from_ymd(1970, 1, 1)
+            .checked_add_signed(Duration::days(seconds.div_euclid(86400)))
+            .map(ChronoValue::NaiveDate),
+        ChronoValue::NaiveTime(_) => {
+            NaiveTime::from_num_seconds_from_midnight_opt(seconds.rem_euclid(86400) as u32, 0)
+                .map(ChronoValue::NaiveTime)
+        }
+        ChronoValue::NaiveDateTime(_) => {
+            NaiveDateTime::from_timestamp_opt(seconds, 0).map(ChronoValue::NaiveDateTime)
+        }
+        ChronoValue::DateTime(date_time) => date_time
+            .offset()
+            .timestamp_opt(seconds, 0)
+            .single()
+            .map(ChronoValue::DateTime),
+    }
+}
+
+/// Adds Laplace noise to the weights of the variants, which are their numbers of occurrences.
+/// The variants are weighted evenly if no weight is left positive.
+fn add_noise_to_weights<R: Rng>(one_of: &mut OneOfContent, privacy: &Privacy, rng: &mut R) {
+    let noisy: Vec<f64> = one_of
+        .variants
+        .iter()
+        .map(|variant| {
+            (variant.weight() + laplace(1.0 / privacy.epsilon, rng))
+                .round()
+                .max(0.0)
+        })
+        .collect();
+    let is_empty = noisy.iter().all(|weight| *weight == 0.0);
+    for (variant, weight) in one_of.variants.iter_mut().zip(noisy) {
+        let weight = match is_empty {
+            true => Weight::default(),
+            false => Weight::try_from(weight).unwrap_or_default(),
+        };
+        variant.set_weight(weight);
+    }
+}
+
+/// The categorical with noisy weights, if any category is left. Categories below the threshold
+/// of `privacy` are left out if `suppress` is set.
+fn noisy_categorical<T: CategoricalType, R: Rng>(
+    categorical: &Categorical<T>,
+    privacy: &Privacy,
+    suppress: bool,
+    rng: &mut R,
+) -> Option<Categorical<T>> {
+    let threshold = if suppress {
+        privacy.threshold.max(1)
+    } else {
+        1
+    };
+    let noisy: Categorical<T> = categorical
+        .iter()
+        .map(|(t, occurrences)| {
+            let noisy = (occurrences as f64 + laplace(1.0 / privacy.epsilon, rng)).round();
+            let noisy = if noisy >= threshold as f64 {
+                noisy as u64
+            } else {
+                0
+            };
+            (t.clone(), noisy)
+        })
+        .collect();
+
+    let is_empty = noisy.iter().next().is_none();
+    (!is_empty).then(|| noisy)
+}
+
+/// A sample of the Laplace distribution centered on 0.
+fn laplace<R: Rng>(scale: f64, rng: &mut R) -> f64 {
+    let u: f64 = rng.gen_range(-0.5..0.5);
+    -scale * u.signum() * (1.0 - 2.0 * u.abs()).ln()
+}
+
+/// The `q` quantile of the samples, picked by the exponential mechanism over `domain` as in
+/// Smith's mechanism: the samples are clamped to the domain, whose bounds are added to them, and
+/// the gaps between consecutive sorted values are weighted by their width and how close their rank
+/// is to the quantile. A value is drawn uniformly from the picked gap. As the bounds of the domain
+/// are public, the quantile can be anywhere in the domain rather than between the sampled values.
+/// The domain must not be empty.
+fn noisy_quantile<R: Rng>(
+    samples: &[f64],
+    q: f64,
+    (low, high): (f64, f64),
+    epsilon: f64,
+    rng: &mut R,
+) -> f64 {
+    let mut sorted: Vec<f64> = samples
+        .iter()
+        .filter(|x| !x.is_nan())
+        .map(|x| x.clamp(low, high))
+        .collect();
+    sorted.sort_by(|a, b| a.partial_cmp(b).unwrap());
+    let target = q * sorted.len() as f64;
+    sorted.insert(0, low);
+    sorted.push(high);
+
+    // The gap of rank i has i samples below it. Widths are halved so that the widest domains
+    // don't overflow
+    let gaps: Vec<(f64, f64, f64)> = sorted
+        .windows(2)
+        .enumerate()
+        .filter(|(_, gap)| gap[1] > gap[0])
+        .map(|(rank, gap)| {
+            let utility = -(rank as f64 - target).abs();
+            let score = (gap[1] / 2.0 - gap[0] / 2.0).ln() + epsilon * utility / 2.0;
+            (gap[0], gap[1], score)
+        })
+        .collect();
+
+    // Scores are shifted by their maximum so that the weights don't underflow
+    let max = gaps
+        .iter()
+        .map(|(_, _, score)| *score)
+        .fold(f64::NEG_INFINITY, f64::max);
+    let weights = gaps.iter().map(|(_, _, score)| (score - max).exp());
+    let index = WeightedIndex::new(weights)
+        .expect("the domain is not empty")
+        .sample(rng);
+    let (low, high, _) = gaps[index];
+
+    let u: f64 = rng.gen();
+    (low * (1.0 - u) + high * u).clamp(low, high)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+    use serde_json::json;
+    use synth_core::schema::inference::infer_frequencies;
+    use synth_core::schema::{MergeStrategy, Namespace, OptionalMergeStrategy};
+
+    #[test]
+    fn add_noise() {
+        let rows: Vec<serde_json::Value> = (0..1000)
+            .map(|i| {
+                let country = match i {
+                    0 => "monaco",
+                    _ if i % 2 == 0 => "fr",
+                    _ => "de",
+                };
+                let nickname = if i % 10 == 0 {
+                    Value::Null
+                } else {
+                    json!("bob")
+                };
+                // A single member joined long before the others
+                let joined = match i {
+                    1 => "1950-01-01".to_string(),
+                    _ => format!("2021-{:02}-{:02}", i % 12 + 1, i % 28 + 1),
+                };
+                json!({
+                    "age": 18 + i % 60,
+                    "country": country,
+                    "nickname": nickname,
+                    "joined": joined,
+                })
+            })
+            .collect();
+        let samples = json!(rows);
+        // Strings are only inferred as categoricals from enumerated types of databases
+        let mut content = serde_json::to_value(Namespace::collection(&rows[0])).unwrap();
+        content["content"]["country"] = json!({ "type": "string", "categorical": { "fr": 1 } });
+        content["content"]["joined"] =
+            json!({ "type": "date_time", "format": "%Y-%m-%d", "subtype": "naive_date" });
+        let mut content: Content = serde_json::from_value(content).unwrap();
+        OptionalMergeStrategy
+            .try_merge(&mut content, &samples)
+            .unwrap();
+        infer_frequencies("users", &mut content, &samples);
+
+        let privacy = Privacy {
+            epsilon: 2.0,
+            threshold: 5,
+            clamp: Some((0.0, 150.0)),
+        };
+        super::add_noise(
+            "users",
+            &mut content,
+            &samples,
+            &privacy,
+            &mut StdRng::seed_from_u64(0),
+        );
+
+        let content = serde_json::to_value(&content).unwrap();
+        let age = &content["content"]["age"]["range"];
+        let (low, high) = (age["low"].as_u64().unwrap(), age["high"].as_u64().unwrap());
+        assert!(low > 18 && low < 30, "{}", low);
+        assert!(high < 77 && high > 65, "{}", high);
+
+        let countries = content["content"]["country"]["categorical"]
+            .as_object()
+            .unwrap();
+        assert!(!countries.contains_key("monaco"));
+        assert!((countries["fr"].as_u64().unwrap() as i64 - 500).abs() < 20);
+        assert!((countries["de"].as_u64().unwrap() as i64 - 500).abs() < 20);
+
+        let nulls = content["content"]["nickname"]["variants"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .find(|variant| variant["type"] == "null")
+            .unwrap()["weight"]
+            .as_f64()
+            .unwrap();
+        assert!((nulls - 100.0).abs() < 20.0, "{}", nulls);
+
+        let joined = &content["content"]["joined"];
+        assert!(!joined.to_string().contains("1950"), "{}", joined);
+        assert!(joined["begin"].as_str().unwrap().starts_with("2021-"));
+        assert!(
+            joined["end"].as_str().unwrap().starts_with("2021-"),
+            "{}",
+            joined
+        );
+    }
+
+    #[test]
+    fn noisy_quantile() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let quantiles: Vec<f64> = (0..100)
+            .map(|_| super::noisy_quantile(&[10.0, 20.0], 0.5, (0.0, 100.0), 1.0, &mut rng))
+            .collect();
+
+        assert!(quantiles.iter().all(|x| (0.0..=100.0).contains(x)));
+        // The sampled extremes don't bound the quantile
+        assert!(quantiles.iter().any(|x| !(10.0..=20.0).contains(x)));
+
+        let widest = super::noisy_quantile(&[], 0.5, (f64::MIN, f64::MAX), 1.0, &mut rng);
+        assert!(widest.is_finite());
+    }
+}
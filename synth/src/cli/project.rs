@@ -0,0 +1,69 @@
+use crate::datasource::LoadParams;
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::path::Path;
+
+/// The configuration of a project, read from the `synth.json` file of the directory from which
+/// synth is run. Command line options take precedence over it.
+#[derive(Debug, Default, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct ProjectConfig {
+    #[serde(default)]
+    pub(crate) load: LoadParams,
+}
+
+impl ProjectConfig {
+    pub(crate) const FILE_NAME: &'static str = "synth.json";
+
+    /// Reads the configuration of the project at `dir`, which is empty if there is no
+    /// configuration file.
+    pub(crate) fn read(dir: &Path) -> Result<Self> {
+        let path = dir.join(Self::FILE_NAME);
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+
+        let contents = std::fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        let config: Self = serde_json::from_str(&contents)
+            .with_context(|| format!("Failed to parse {}", path.display()))?;
+        config
+            .load
+            .validate()
+            .with_context(|| format!("Invalid configuration in {}", path.display()))?;
+
+        Ok(config)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn read() {
+        let tempdir = tempdir().unwrap();
+        let dir = tempdir.path();
+        let path = dir.join(ProjectConfig::FILE_NAME);
+
+        assert_eq!(ProjectConfig::read(dir).unwrap(), ProjectConfig::default());
+
+        std::fs::write(
+            &path,
+            r#"{ "load": { "pool_size": 10, "statement_timeout": 30000 } }"#,
+        )
+        .unwrap();
+        let load = ProjectConfig::read(dir).unwrap().load;
+        assert_eq!(load.pool_size, Some(10));
+        assert_eq!(load.statement_timeout, Some(30000));
+        assert_eq!(load.batch_size(), LoadParams::DEFAULT_BATCH_SIZE);
+        assert_eq!(load.concurrency(), 10);
+
+        std::fs::write(&path, r#"{ "load": { "pool_size": 0 } }"#).unwrap();
+        assert!(ProjectConfig::read(dir).is_err());
+
+        std::fs::write(&path, r#"{ "load": { "pool": 1 } }"#).unwrap();
+        assert!(ProjectConfig::read(dir).is_err());
+    }
+}
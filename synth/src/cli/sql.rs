@@ -0,0 +1,281 @@
+use crate::cli::export::{ExportParams, ExportStrategy};
+use crate::datasource::ddl::{tables_from_namespace, SqlDialect};
+use crate::datasource::mysql_datasource::to_mysql_literal;
+use crate::datasource::postgres_datasource::{encode_copy_text, to_postgres_literal};
+use crate::datasource::ExportMode;
+use crate::sampler::{Sampler, SamplerOutput};
+use anyhow::{Context, Result};
+use std::convert::TryFrom;
+use std::path::PathBuf;
+use synth_core::graph::prelude::{ChronoValue, ChronoValueAndFormat, Number};
+use synth_core::Value;
+
+/// Writes the generated data into a SQL script instead of a database, for when the script has to
+/// be reviewed before it is applied.
+#[derive(Clone, Debug)]
+pub struct SqlScriptExportStrategy {
+    pub path: PathBuf,
+    pub dialect: SqlDialect,
+    /// Whether to write `COPY` blocks, as understood by `psql`, instead of `INSERT` statements
+    pub copy: bool,
+    /// The number of rows inserted by a single `INSERT` statement
+    pub batch_size: usize,
+}
+
+impl ExportStrategy for SqlScriptExportStrategy {
+    fn export(&self, params: ExportParams) -> Result<SamplerOutput> {
+        if matches!(
+            params.mode,
+            ExportMode::Replace | ExportMode::Upsert | ExportMode::Drop
+        ) {
+            bail!(
+                "The '{}' export mode is not supported for SQL scripts",
+                params.mode
+            );
+        }
+
+        if self.copy && self.dialect != SqlDialect::Postgres {
+            bail!("COPY blocks can only be written with the postgres dialect");
+        }
+
+        let sampler = Sampler::try_from(&params.namespace)?;
+        let output =
+            sampler.sample_seeded(params.collection_name.clone(), params.target, params.seed)?;
+
+        let collection_name = params.collection_name.map(|name| name.to_string());
+        let collections: Vec<(&str, &[Value])> = match output {
+            SamplerOutput::Collection(ref collection) => {
+                vec![(collection_name.as_deref().unwrap(), collection)]
+            }
+            SamplerOutput::Namespace(ref namespace) => namespace
+                .iter()
+                .map(|(name, collection)| (name.as_str(), collection.as_slice()))
+                .collect(),
+        };
+
+        // Tables are ordered such that referenced tables come first
+        let tables: Vec<_> = tables_from_namespace(&params.namespace)?
+            .into_iter()
+            .filter(|table| collections.iter().any(|(name, _)| *name == table.name))
+            .collect();
+
+        let mut script = String::new();
+
+        if self.dialect == SqlDialect::Postgres {
+            script.push_str("SET standard_conforming_strings = on;\n\n");
+        }
+
+        if params.create_tables {
+            for table in tables.iter() {
+                script.push_str(&table.create_statement(self.dialect));
+                script.push_str("\n\n");
+            }
+        }
+
+        if params.transactional {
+            script.push_str("BEGIN;\n\n");
+        }
+
+        if params.mode == ExportMode::Truncate {
+            for table in tables.iter().rev() {
+                script.push_str(&format!("DELETE FROM {};\n", table.name));
+            }
+            script.push('\n');
+        }
+
+        for table in tables.iter() {
+            let (name, collection) = collections
+                .iter()
+                .find(|(name, _)| *name == table.name)
+                .expect("tables were filtered by collection");
+
+            if self.copy {
+                self.write_copy(&mut script, name, collection)?;
+            } else {
+                self.write_inserts(&mut script, name, collection)
+                    .with_context(|| format!("Failed to write collection {}", name))?;
+            }
+        }
+
+        if params.transactional {
+            script.push_str("COMMIT;\n");
+        }
+
+        std::fs::write(&self.path, script)
+            .with_context(|| format!("Failed to write to {}", self.path.display()))?;
+
+        Ok(output)
+    }
+}
+
+impl SqlScriptExportStrategy {
+    fn write_inserts(&self, script: &mut String, name: &str, collection: &[Value]) -> Result<()> {
+        let column_names = match collection.get(0) {
+            Some(first_row) => column_names(first_row),
+            None => return Ok(()),
+        };
+
+        for rows in collection.chunks(self.batch_size) {
+            script.push_str(&format!("INSERT INTO {} ({}) VALUES\n", name, column_names));
+
+            for (i, row) in rows.iter().enumerate() {
+                let literals = row
+                    .as_object()
+                    .expect("This is always an object (sampler contract)")
+                    .values()
+                    .map(|value| self.literal(value))
+                    .collect::<Result<Vec<String>>>()?;
+
+                script.push('(');
+                script.push_str(&literals.join(", "));
+                script.push(')');
+                script.push_str(if i == rows.len() - 1 { ";\n" } else { ",\n" });
+            }
+            script.push('\n');
+        }
+
+        Ok(())
+    }
+
+    fn write_copy(&self, script: &mut String, name: &str, collection: &[Value]) -> Result<()> {
+        let column_names = match collection.get(0) {
+            Some(first_row) => column_names(first_row),
+            None => return Ok(()),
+        };
+
+        script.push_str(&format!("COPY {} ({}) FROM stdin;\n", name, column_names));
+        script.push_str(
+            &encode_copy_text(collection)
+                .with_context(|| format!("Failed to write collection {}", name))?,
+        );
+        script.push_str("\\.\n\n");
+
+        Ok(())
+    }
+
+    fn literal(&self, value: &Value) -> Result<String> {
+        match self.dialect {
+            SqlDialect::Postgres => to_postgres_literal(value),
+            SqlDialect::MySql => to_mysql_literal(value),
+            SqlDialect::Sqlite => to_sqlite_literal(value),
+        }
+    }
+}
+
+fn column_names(row: &Value) -> String {
+    row.as_object()
+        .expect("This is always an object (sampler contract)")
+        .keys()
+        .cloned()
+        .collect::<Vec<String>>()
+        .join(", ")
+}
+
+/// SQLite has no dedicated boolean, date time or JSON types. Those are stored as integers and
+/// text respectively.
+fn to_sqlite_literal(value: &Value) -> Result<String> {
+    let quote = |text: &str| format!("'{}'", text.replace('\'', "''"));
+
+    let literal = match value {
+        Value::Null(_) => "NULL".to_string(),
+        Value::Bool(b) => if *b { "1" } else { "0" }.to_string(),
+        Value::Number(Number::F32(f)) if !f.is_finite() => {
+            bail!("SQLite does not support the floating point value {}", f)
+        }
+        Value::Number(Number::F64(f)) if !f.is_finite() => {
+            bail!("SQLite does not support the floating point value {}", f)
+        }
+        Value::Number(n) => n.to_string(),
+        Value::String(s) => quote(s),
+        Value::DateTime(ChronoValueAndFormat { value, .. }) => quote(&match value {
+            ChronoValue::NaiveDate(nd) => nd.format("%Y-%m-%d").to_string(),
+            ChronoValue::NaiveTime(nt) => nt.format("%H:%M:%S%.f").to_string(),
+            ChronoValue::NaiveDateTime(ndt) => ndt.format("%Y-%m-%d %H:%M:%S%.f").to_string(),
+            ChronoValue::DateTime(dt) => dt.format("%Y-%m-%d %H:%M:%S%.f%:z").to_string(),
+        }),
+        Value::Object(_) | Value::Array(_) => {
+            quote(&synth_core::graph::json::synth_val_to_json(value.clone()).to_string())
+        }
+    };
+
+    Ok(literal)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::datasource::LoadParams;
+    use serde_json::json;
+    use synth_core::Namespace;
+    use tempfile::tempdir;
+
+    fn export(dialect: SqlDialect, copy: bool, transactional: bool) -> String {
+        let namespace: Namespace = serde_json::from_value(json!({
+            "users": {
+                "type": "array",
+                "length": 2,
+                "content": {
+                    "type": "object",
+                    "id": { "type": "number", "subtype": "u64", "id": {} },
+                    "name": {
+                        "type": "string",
+                        "categorical": { "O'Brien": 1 }
+                    }
+                }
+            },
+            "posts": {
+                "type": "array",
+                "length": 1,
+                "content": {
+                    "type": "object",
+                    "author": "@users.content.id"
+                }
+            }
+        }))
+        .unwrap();
+
+        let tempdir = tempdir().unwrap();
+        let path = tempdir.path().join("export.sql");
+        let strategy = SqlScriptExportStrategy {
+            path: path.clone(),
+            dialect,
+            copy,
+            batch_size: LoadParams::DEFAULT_BATCH_SIZE,
+        };
+        strategy
+            .export(ExportParams {
+                namespace,
+                collection_name: None,
+                target: 2,
+                seed: 0,
+                ns_path: PathBuf::new(),
+                transactional,
+                mode: ExportMode::Append,
+                create_tables: false,
+            })
+            .unwrap();
+
+        std::fs::read_to_string(&path).unwrap()
+    }
+
+    #[test]
+    fn test_export_inserts() {
+        assert_eq!(
+            export(SqlDialect::MySql, false, true),
+            "BEGIN;\n\n\
+             INSERT INTO users (id, name) VALUES\n(1, 'O\\'Brien'),\n(2, 'O\\'Brien');\n\n\
+             INSERT INTO posts (author) VALUES\n(1);\n\n\
+             COMMIT;\n"
+        );
+    }
+
+    #[test]
+    fn test_export_copy() {
+        assert_eq!(
+            export(SqlDialect::Postgres, true, false),
+            "SET standard_conforming_strings = on;\n\n\
+             COPY users (id, name) FROM stdin;\n1\tO'Brien\n2\tO'Brien\n\\.\n\n\
+             COPY posts (author) FROM stdin;\n1\n\\.\n\n"
+        );
+    }
+}
@@ -1,5 +1,7 @@
 use crate::cli::export::{ExportParams, ExportStrategy};
-use crate::cli::import::ImportStrategy;
+use crate::cli::import::{take_collection, ImportStrategy};
+use crate::cli::pii::Finding;
+use crate::datasource::{ExportMode, Privacy};
 use crate::sampler::{Sampler, SamplerOutput};
 use anyhow::Result;
 use serde_json::Value;
@@ -11,16 +13,27 @@ use std::path::PathBuf;
 #[derive(Clone, Debug)]
 pub struct FileImportStrategy {
     pub from_file: PathBuf,
+    pub privacy: Option<Privacy>,
 }
 
 #[derive(Clone, Debug)]
-pub struct StdinImportStrategy;
+pub struct StdinImportStrategy {
+    pub privacy: Option<Privacy>,
+}
 
 #[derive(Clone, Debug)]
 pub struct StdoutExportStrategy;
 
 impl ExportStrategy for StdoutExportStrategy {
     fn export(&self, params: ExportParams) -> Result<SamplerOutput> {
+        if params.mode != ExportMode::Append {
+            bail!("The '{}' export mode requires a database sink", params.mode);
+        }
+
+        if params.create_tables {
+            bail!("Creating tables requires a database sink");
+        }
+
         let generator = Sampler::try_from(&params.namespace)?;
         let output = generator.sample_seeded(params.collection_name, params.target, params.seed)?;
         println!("{}", output.clone().into_json());
@@ -30,10 +43,8 @@ impl ExportStrategy for StdoutExportStrategy {
 }
 
 impl ImportStrategy for FileImportStrategy {
-    fn import_collection(&self, name: &Name) -> Result<Content> {
-        self.import()?
-            .collections
-            .remove(name)
+    fn import_collection(&self, name: &Name) -> Result<(Content, Vec<Finding>)> {
+        take_collection(self.import()?, name)
             .ok_or_else(|| anyhow!("Could not find collection '{}' in file.", name))
     }
 
@@ -42,10 +53,18 @@ impl ImportStrategy for FileImportStrategy {
             self.from_file.clone(),
         )?)?)
     }
+
+    fn privacy(&self) -> Option<&Privacy> {
+        self.privacy.as_ref()
+    }
 }
 
 impl ImportStrategy for StdinImportStrategy {
     fn as_value(&self) -> Result<Value> {
         Ok(serde_json::from_reader(std::io::stdin())?)
     }
+
+    fn privacy(&self) -> Option<&Privacy> {
+        self.privacy.as_ref()
+    }
 }
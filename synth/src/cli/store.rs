@@ -90,6 +90,16 @@ impl Store {
         Ok(ns)
     }
 
+    /// Get a collection of the namespace at the given directory path
+    pub fn get_collection_path(&self, ns_path: &Path, collection: &Name) -> Result<Content> {
+        let path = self.collection_path(ns_path, collection);
+        let collection_file_content = std::fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        UNDERLYING
+            .parse(&collection_file_content)
+            .with_context(|| anyhow!("at file {}", path.display()))
+    }
+
     pub fn save_collection_path(
         &self,
         ns_path: &Path,
@@ -101,6 +111,7 @@ impl Store {
         let mut file = std::fs::OpenOptions::new()
             .write(true)
             .create(true)
+            .truncate(true)
             .open(self.collection_path(ns_path, &collection))?;
         serde_json::to_writer_pretty(&mut file, &content)?;
         Ok(())
@@ -24,7 +24,7 @@ use synth_core::{
     Compile, Compiler, Content, Graph, Name, Namespace,
 };
 
-use super::{Args, TelemetryCommand};
+use super::{Args, SchemaCommand, TelemetryCommand};
 
 const API_KEY: &str = "L-AQtrFVtZGL_PjK2FbFLBR3oXNtfv8OrCD8ObyeBQo";
 const EVENT_NAME: &str = "synth-command";
@@ -306,8 +306,11 @@ where
 
     let command_name = match &args {
         Args::Init { .. } => "init",
-        Args::Generate { .. } => "generate",
-        Args::Import { .. } => "import",
+        Args::Generate(_) => "generate",
+        Args::Import(_) => "import",
+        Args::Subset(_) => "subset",
+        Args::Mask(_) => "mask",
+        Args::Schema(SchemaCommand::Ddl { .. }) => "schema::ddl",
         Args::Telemetry(TelemetryCommand::Enable) => "telemetry::enable",
         Args::Telemetry(TelemetryCommand::Disable) => "telemetry::disable",
         Args::Telemetry(TelemetryCommand::Status) => "telemetry::status",
@@ -484,6 +487,7 @@ pub mod tests {
         ExportParams, ExportStrategy, Namespace, SamplerOutput, TelemetryClient, TelemetryContext,
         TelemetryExportStrategy,
     };
+    use crate::datasource::ExportMode;
     use crate::sampler::Sampler;
     use anyhow::Result;
     use std::cell::RefCell;
@@ -567,6 +571,9 @@ pub mod tests {
                 target: 1,
                 seed: 500,
                 ns_path: PathBuf::from("/dummy/path"),
+                transactional: false,
+                mode: ExportMode::Append,
+                create_tables: false,
             })
             .unwrap();
 
@@ -656,6 +663,9 @@ pub mod tests {
                 target: 1,
                 seed: 500,
                 ns_path: PathBuf::from("/dummy/path"),
+                transactional: false,
+                mode: ExportMode::Append,
+                create_tables: false,
             })
             .unwrap();
 
@@ -733,6 +743,9 @@ pub mod tests {
                 target: 1,
                 seed: 500,
                 ns_path: PathBuf::from("/dummy/path"),
+                transactional: false,
+                mode: ExportMode::Append,
+                create_tables: false,
             })
             .unwrap();
 
@@ -810,6 +823,9 @@ pub mod tests {
                 target: 1,
                 seed: 500,
                 ns_path: PathBuf::from("/dummy/namespace"),
+                transactional: false,
+                mode: ExportMode::Append,
+                create_tables: false,
             })
             .unwrap();
 
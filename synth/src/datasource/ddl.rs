@@ -0,0 +1,531 @@
+//! Derives `CREATE TABLE` statements from the collections of a namespace, so that data can be
+//! generated into databases which do not have the target tables yet.
+
+use crate::datasource::relational_datasource::{reverse_dependency_order, ForeignKey};
+use anyhow::{Context, Result};
+use serde::Serialize;
+use std::str::FromStr;
+use synth_core::schema::number_content::{I32, I64, U32, U64};
+use synth_core::schema::{
+    ArrayContent, ChronoValueType, DateTimeContent, HiddenContent, NumberContent, ObjectContent,
+    OneOfContent, PseudonymiseContent, SameAsContent, StringContent, UniqueContent,
+};
+use synth_core::{Content, Namespace};
+
+/// The SQL dialects for which table definitions can be derived.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SqlDialect {
+    Postgres,
+    MySql,
+    Sqlite,
+}
+
+impl SqlDialect {
+    pub const VARIANTS: [&'static str; 3] = ["postgres", "mysql", "sqlite"];
+}
+
+impl std::fmt::Display for SqlDialect {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            Self::Postgres => "postgres",
+            Self::MySql => "mysql",
+            Self::Sqlite => "sqlite",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl FromStr for SqlDialect {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "postgres" | "postgresql" => Ok(Self::Postgres),
+            "mysql" | "mariadb" => Ok(Self::MySql),
+            "sqlite" => Ok(Self::Sqlite),
+            other => Err(anyhow!(
+                "Unknown SQL dialect '{}'. Was expecting one of {}",
+                other,
+                Self::VARIANTS.join(", ")
+            )),
+        }
+    }
+}
+
+/// The type of a column, independent of any dialect.
+#[derive(Clone, Debug, PartialEq)]
+enum ColumnType {
+    Bool,
+    I32,
+    U32,
+    I64,
+    U64,
+    F32,
+    F64,
+    Text,
+    Uuid,
+    Json,
+    Date,
+    Time,
+    Timestamp,
+    TimestampTz,
+    Array(Box<ColumnType>),
+}
+
+impl ColumnType {
+    /// `key` is set for columns which are part of a primary key or unique constraint, as MySQL
+    /// can't index `TEXT` columns without a prefix length.
+    fn to_sql(&self, dialect: SqlDialect, key: bool) -> String {
+        let sql = match dialect {
+            SqlDialect::Postgres => match self {
+                Self::Bool => "boolean",
+                Self::I32 => "integer",
+                Self::U32 | Self::I64 | Self::U64 => "bigint",
+                Self::F32 => "real",
+                Self::F64 => "double precision",
+                Self::Text => "text",
+                Self::Uuid => "uuid",
+                Self::Json => "jsonb",
+                Self::Date => "date",
+                Self::Time => "time",
+                Self::Timestamp => "timestamp",
+                Self::TimestampTz => "timestamptz",
+                Self::Array(element) => return format!("{}[]", element.to_sql(dialect, false)),
+            },
+            SqlDialect::MySql => match self {
+                Self::Bool => "boolean",
+                Self::I32 => "int",
+                Self::U32 => "int unsigned",
+                Self::I64 => "bigint",
+                Self::U64 => "bigint unsigned",
+                Self::F32 => "float",
+                Self::F64 => "double",
+                Self::Text if key => "varchar(255)",
+                Self::Text => "text",
+                Self::Uuid => "char(36)",
+                Self::Json | Self::Array(_) => "json",
+                Self::Date => "date",
+                Self::Time => "time",
+                Self::Timestamp | Self::TimestampTz => "datetime",
+            },
+            SqlDialect::Sqlite => match self {
+                Self::Bool | Self::I32 | Self::U32 | Self::I64 | Self::U64 => "INTEGER",
+                Self::F32 | Self::F64 => "REAL",
+                _ => "TEXT",
+            },
+        };
+        sql.to_string()
+    }
+}
+
+#[derive(Debug)]
+struct Column {
+    name: String,
+    column_type: ColumnType,
+    nullable: bool,
+    unique: bool,
+    id: bool,
+}
+
+/// A table derived from a collection of a namespace.
+#[derive(Debug)]
+pub(crate) struct Table {
+    pub(crate) name: String,
+    columns: Vec<Column>,
+    primary_key: Vec<String>,
+    foreign_keys: Vec<ForeignKey>,
+}
+
+impl Table {
+    fn from_collection(namespace: &Namespace, name: &str, collection: &Content) -> Result<Self> {
+        let fields = match collection {
+            Content::Array(ArrayContent { content, .. }) => match content.as_ref() {
+                Content::Object(ObjectContent { fields, .. }) => fields,
+                _ => bail!(
+                    "Collection {} does not contain objects and cannot be mapped to a table",
+                    name
+                ),
+            },
+            _ => bail!(
+                "Collection {} is not an array and cannot be mapped to a table",
+                name
+            ),
+        };
+
+        let mut columns = Vec::with_capacity(fields.len());
+        let mut foreign_keys = Vec::new();
+
+        for (field_name, content) in fields {
+            // Hidden fields are never part of the generated rows
+            if content.is_hidden() {
+                continue;
+            }
+
+            let (content, nullable) = match content {
+                Content::Null(_) => (content, true),
+                _ => match content.as_nullable() {
+                    Some(inner) => (inner, true),
+                    None => (content, false),
+                },
+            };
+
+            let (content, unique) = match content {
+                Content::Unique(UniqueContent { content, .. }) => (content.as_ref(), true),
+                _ => (content, false),
+            };
+
+            // Only references to top level fields of another collection, i.e.
+            // `@collection.content.field`, can be foreign keys
+            if let Content::SameAs(SameAsContent { ref_ }) = content {
+                let referenced_fields: Vec<&str> = ref_.iter_fields().collect();
+                if let ["content", to_column] = referenced_fields.as_slice() {
+                    foreign_keys.push(ForeignKey {
+                        from_table: name.to_string(),
+                        from_column: field_name.clone(),
+                        to_table: ref_.collection().to_string(),
+                        to_column: to_column.to_string(),
+                    });
+                }
+            }
+
+            let column_type = column_type(namespace, content, 0)
+                .with_context(|| format!("At field {}.{}", name, field_name))?;
+
+            columns.push(Column {
+                name: field_name.clone(),
+                column_type,
+                nullable,
+                unique,
+                id: is_id(content),
+            });
+        }
+
+        let mut primary_key: Vec<String> = columns
+            .iter()
+            .filter(|column| column.id && !column.nullable)
+            .map(|column| column.name.clone())
+            .collect();
+
+        if primary_key.is_empty() {
+            if let Some(column) = columns
+                .iter()
+                .find(|column| column.unique && !column.nullable)
+            {
+                primary_key.push(column.name.clone());
+            }
+        }
+
+        Ok(Self {
+            name: name.to_string(),
+            columns,
+            primary_key,
+            foreign_keys,
+        })
+    }
+
+    /// Whether the given column alone is guaranteed to be unique, i.e. whether it can be the
+    /// target of a foreign key.
+    fn is_key(&self, column_name: &str) -> bool {
+        self.primary_key == [column_name]
+            || self
+                .columns
+                .iter()
+                .any(|column| column.name == column_name && column.unique)
+    }
+
+    pub(crate) fn create_statement(&self, dialect: SqlDialect) -> String {
+        let mut definitions: Vec<String> = self
+            .columns
+            .iter()
+            .map(|column| {
+                let key = column.unique
+                    || self.primary_key.contains(&column.name)
+                    || self
+                        .foreign_keys
+                        .iter()
+                        .any(|fk| fk.from_column == column.name);
+
+                let mut definition = format!(
+                    "{} {}",
+                    column.name,
+                    column.column_type.to_sql(dialect, key)
+                );
+                if !column.nullable {
+                    definition.push_str(" NOT NULL");
+                }
+                if column.unique && self.primary_key != [column.name.as_str()] {
+                    definition.push_str(" UNIQUE");
+                }
+                definition
+            })
+            .collect();
+
+        if !self.primary_key.is_empty() {
+            definitions.push(format!("PRIMARY KEY ({})", self.primary_key.join(", ")));
+        }
+
+        for fk in self.foreign_keys.iter() {
+            definitions.push(format!(
+                "FOREIGN KEY ({}) REFERENCES {} ({})",
+                fk.from_column, fk.to_table, fk.to_column
+            ));
+        }
+
+        format!(
+            "CREATE TABLE IF NOT EXISTS {} (\n    {}\n);",
+            self.name,
+            definitions.join(",\n    ")
+        )
+    }
+}
+
+/// Derives a table for every collection of the namespace. Tables are ordered such that every
+/// table comes after the tables it references.
+pub(crate) fn tables_from_namespace(namespace: &Namespace) -> Result<Vec<Table>> {
+    let mut tables = namespace
+        .iter()
+        .map(|(name, collection)| Table::from_collection(namespace, name.as_ref(), collection))
+        .collect::<Result<Vec<Table>>>()?;
+
+    // Foreign keys can only reference columns which are known to be unique
+    let keys: Vec<(String, String)> = tables
+        .iter()
+        .flat_map(|table| {
+            table
+                .columns
+                .iter()
+                .filter(move |column| table.is_key(&column.name))
+                .map(move |column| (table.name.clone(), column.name.clone()))
+        })
+        .collect();
+
+    for table in tables.iter_mut() {
+        table.foreign_keys.retain(|fk| {
+            let is_key = keys.contains(&(fk.to_table.clone(), fk.to_column.clone()));
+            if !is_key {
+                warn!(
+                    "Not creating a foreign key from {}.{} to {}.{} as the referenced field is neither an id nor unique",
+                    fk.from_table, fk.from_column, fk.to_table, fk.to_column
+                );
+            }
+            is_key
+        });
+    }
+
+    // Reversing both the input and the output of `reverse_dependency_order` puts referenced
+    // tables first while keeping unrelated tables in their original order
+    let table_names: Vec<String> = tables
+        .iter()
+        .rev()
+        .map(|table| table.name.clone())
+        .collect();
+    let foreign_keys: Vec<ForeignKey> = tables
+        .iter()
+        .flat_map(|table| table.foreign_keys.iter().cloned())
+        .collect();
+    let order: Vec<String> = reverse_dependency_order(&table_names, &foreign_keys)
+        .into_iter()
+        .rev()
+        .cloned()
+        .collect();
+
+    tables.sort_by_key(|table| order.iter().position(|name| name == &table.name));
+
+    Ok(tables)
+}
+
+/// The `CREATE TABLE` statements for all collections of the namespace, in dependency order.
+pub(crate) fn create_table_statements(
+    namespace: &Namespace,
+    dialect: SqlDialect,
+) -> Result<Vec<String>> {
+    Ok(tables_from_namespace(namespace)?
+        .iter()
+        .map(|table| table.create_statement(dialect))
+        .collect())
+}
+
+fn is_id(content: &Content) -> bool {
+    matches!(
+        content,
+        Content::Number(
+            NumberContent::U32(U32::Id(_))
+                | NumberContent::U64(U64::Id(_))
+                | NumberContent::I32(I32::Id(_))
+                | NumberContent::I64(I64::Id(_))
+        )
+    )
+}
+
+/// Reference chains longer than this are assumed to be cycles.
+const MAX_REFERENCE_DEPTH: usize = 32;
+
+fn column_type(namespace: &Namespace, content: &Content, depth: usize) -> Result<ColumnType> {
+    let column_type = match content {
+        Content::Null(_) => ColumnType::Text,
+        Content::Bool(_) => ColumnType::Bool,
+        Content::Number(number) => match number {
+            NumberContent::U32(_) => ColumnType::U32,
+            NumberContent::U64(_) => ColumnType::U64,
+            NumberContent::I32(_) => ColumnType::I32,
+            NumberContent::I64(_) => ColumnType::I64,
+            NumberContent::F32(_) => ColumnType::F32,
+            NumberContent::F64(_) => ColumnType::F64,
+        },
+        Content::String(StringContent::Uuid(_)) => ColumnType::Uuid,
+        Content::String(StringContent::Serialized(_)) => ColumnType::Json,
+        Content::String(_) | Content::Series(_) => ColumnType::Text,
+        Content::DateTime(DateTimeContent { type_, .. }) => match type_ {
+            ChronoValueType::NaiveDate => ColumnType::Date,
+            ChronoValueType::NaiveTime => ColumnType::Time,
+            ChronoValueType::NaiveDateTime => ColumnType::Timestamp,
+            ChronoValueType::DateTime => ColumnType::TimestampTz,
+        },
+        Content::Array(ArrayContent { content, .. }) => {
+            ColumnType::Array(Box::new(column_type(namespace, content, depth)?))
+        }
+        Content::Object(_) => ColumnType::Json,
+        Content::Unique(UniqueContent { content, .. })
+        | Content::Hidden(HiddenContent { content })
+        | Content::Pseudonymise(PseudonymiseContent { content, .. }) => {
+            column_type(namespace, content, depth)?
+        }
+        Content::SameAs(SameAsContent { ref_ }) => {
+            if depth >= MAX_REFERENCE_DEPTH {
+                bail!("Could not resolve the type of the reference to {}", ref_);
+            }
+            column_type(namespace, namespace.get_s_node(ref_)?, depth + 1)?
+        }
+        Content::OneOf(OneOfContent { variants }) => {
+            let mut types = variants
+                .iter()
+                .filter(|variant| !variant.content.is_null())
+                .map(|variant| column_type(namespace, &variant.content, depth))
+                .collect::<Result<Vec<ColumnType>>>()?;
+            types.dedup();
+            match types.as_slice() {
+                [column_type] => column_type.clone(),
+                [] => ColumnType::Text,
+                _ => {
+                    warn!(
+                        "Found a one_of with variants of different types {:?}. Falling back to a text column.",
+                        types
+                    );
+                    ColumnType::Text
+                }
+            }
+        }
+    };
+
+    Ok(column_type)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn namespace() -> Namespace {
+        serde_json::from_value(json!({
+            "hospitals": {
+                "type": "array",
+                "length": 1,
+                "content": {
+                    "type": "object",
+                    "id": {
+                        "type": "number",
+                        "subtype": "u64",
+                        "id": {}
+                    },
+                    "name": {
+                        "type": "string",
+                        "faker": { "generator": "company_name" },
+                        "unique": true
+                    }
+                }
+            },
+            "doctors": {
+                "type": "array",
+                "length": 1,
+                "content": {
+                    "type": "object",
+                    "id": {
+                        "type": "number",
+                        "subtype": "i32",
+                        "id": {}
+                    },
+                    "hospital_id": "@hospitals.content.id",
+                    "hired_on": {
+                        "type": "date_time",
+                        "format": "%Y-%m-%d",
+                        "subtype": "naive_date",
+                        "optional": true
+                    },
+                    "salary": {
+                        "type": "number",
+                        "subtype": "f64",
+                        "range": { "low": 0, "high": 1 }
+                    }
+                }
+            }
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_create_table_statements_postgres() {
+        let statements = create_table_statements(&namespace(), SqlDialect::Postgres).unwrap();
+
+        assert_eq!(
+            statements,
+            vec![
+                "CREATE TABLE IF NOT EXISTS hospitals (\n    id bigint NOT NULL,\n    name text NOT NULL UNIQUE,\n    PRIMARY KEY (id)\n);",
+                "CREATE TABLE IF NOT EXISTS doctors (\n    hired_on date,\n    hospital_id bigint NOT NULL,\n    id integer NOT NULL,\n    salary double precision NOT NULL,\n    PRIMARY KEY (id),\n    FOREIGN KEY (hospital_id) REFERENCES hospitals (id)\n);",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_create_table_statements_mysql() {
+        let statements = create_table_statements(&namespace(), SqlDialect::MySql).unwrap();
+
+        assert_eq!(
+            statements[0],
+            "CREATE TABLE IF NOT EXISTS hospitals (\n    id bigint unsigned NOT NULL,\n    name varchar(255) NOT NULL UNIQUE,\n    PRIMARY KEY (id)\n);"
+        );
+    }
+
+    #[test]
+    fn test_foreign_key_to_non_key_is_dropped() {
+        let namespace: Namespace = serde_json::from_value(json!({
+            "users": {
+                "type": "array",
+                "length": 1,
+                "content": {
+                    "type": "object",
+                    "email": { "type": "string", "pattern": "[a-z]+" }
+                }
+            },
+            "posts": {
+                "type": "array",
+                "length": 1,
+                "content": {
+                    "type": "object",
+                    "author": "@users.content.email"
+                }
+            }
+        }))
+        .unwrap();
+
+        let statements = create_table_statements(&namespace, SqlDialect::Sqlite).unwrap();
+
+        assert_eq!(
+            statements,
+            vec![
+                "CREATE TABLE IF NOT EXISTS posts (\n    author TEXT NOT NULL\n);",
+                "CREATE TABLE IF NOT EXISTS users (\n    email TEXT NOT NULL\n);",
+            ]
+        );
+    }
+}
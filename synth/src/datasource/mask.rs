@@ -0,0 +1,471 @@
+use crate::datasource::relational_datasource::{
+    reverse_dependency_order, ForeignKey, RelationalDataSource,
+};
+use crate::datasource::subset::order_self_references;
+use crate::datasource::Tables;
+use crate::sampler::{Sampler, SamplerOutput};
+use anyhow::{Context, Result};
+use async_std::task;
+use std::collections::{BTreeSet, HashMap, VecDeque};
+use std::convert::TryFrom;
+use synth_core::schema::content::number_content::U64;
+use synth_core::schema::{ArrayContent, NumberContent, ObjectContent, PseudonymiseContent};
+use synth_core::{Content, Name, Namespace, Value};
+
+/// The masked values of referenced columns by their original value, by table and column.
+pub(crate) type Replacements = HashMap<(String, String), HashMap<Value, Value>>;
+
+/// The tables of a database masked one at a time, as they are read. See [mask_source].
+struct MaskedTables<T> {
+    datasource: T,
+    masks: Namespace,
+    foreign_keys: Vec<ForeignKey>,
+    seed: u64,
+    secret: Option<String>,
+    /// The tables left to mask, in the order in which they are inserted
+    remaining: VecDeque<String>,
+    replacements: Replacements,
+}
+
+/// Reads every table of a database and masks the columns which are part of the `masks`
+/// namespace. See [mask_table].
+///
+/// The tables are read and masked one at a time, as they are inserted, in the order in which they
+/// can be inserted, referenced tables first. Only the masked values of referenced columns are
+/// kept until the tables referencing them are masked. Masked columns can't be referenced by
+/// tables inserted before them, as happens when the foreign keys of tables form a cycle.
+pub(crate) async fn mask_source<T: RelationalDataSource + Sync + 'static>(
+    datasource: T,
+    masks: &Namespace,
+    seed: u64,
+    secret: Option<&str>,
+) -> Result<Tables> {
+    let table_names = datasource.get_table_names().await?;
+    let foreign_keys = datasource.get_foreign_keys().await?;
+
+    let mut masks = masks.clone();
+    let mut masked = BTreeSet::new();
+    for (name, content) in masks.collections.iter_mut() {
+        if !table_names
+            .iter()
+            .any(|table_name| table_name == name.as_ref())
+        {
+            bail!("The masked table {} does not exist", name);
+        }
+
+        let column_infos = datasource.get_columns_infos(name.as_ref()).await?;
+        let (_, object) = mask_fields(name, content)?;
+        for column in object.fields.keys() {
+            if !column_infos
+                .iter()
+                .any(|column_info| &column_info.column_name == column)
+            {
+                bail!("The masked column {}.{} does not exist", name, column);
+            }
+            masked.insert((name.to_string(), column.clone()));
+        }
+    }
+
+    let mut order: Vec<String> = reverse_dependency_order(&table_names, &foreign_keys)
+        .into_iter()
+        .cloned()
+        .collect();
+    order.reverse();
+    check_mask_order(&order, masked, &foreign_keys)?;
+
+    Ok(Tables {
+        names: order.clone(),
+        rows: Box::new(MaskedTables {
+            datasource,
+            masks,
+            foreign_keys,
+            seed,
+            secret: secret.map(ToString::to_string),
+            remaining: order.into(),
+            replacements: HashMap::new(),
+        }),
+    })
+}
+
+impl<T: RelationalDataSource + Sync> Iterator for MaskedTables<T> {
+    type Item = Result<(String, Vec<Value>)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let table_name = self.remaining.pop_front()?;
+        let rows = task::block_on(self.mask_next(&table_name))
+            .with_context(|| format!("Failed to mask the table {}", table_name));
+        Some(rows.map(|rows| (table_name, rows)))
+    }
+}
+
+impl<T: RelationalDataSource + Sync> MaskedTables<T> {
+    async fn mask_next(&mut self, table_name: &str) -> Result<Vec<Value>> {
+        let mut rows = self.datasource.get_rows(table_name, "TRUE", vec![]).await?;
+        info!("Read {} rows from {}...", rows.len(), table_name);
+
+        mask_table(
+            table_name,
+            &mut rows,
+            &self.masks,
+            &self.foreign_keys,
+            self.seed,
+            self.secret.as_deref(),
+            &mut self.replacements,
+        )?;
+
+        // Masked values are dropped once every table referencing them is masked
+        let (remaining, foreign_keys) = (&self.remaining, &self.foreign_keys);
+        self.replacements.retain(|(table_name, column), _| {
+            foreign_keys.iter().any(|fk| {
+                &fk.to_table == table_name
+                    && &fk.to_column == column
+                    && remaining.contains(&fk.from_table)
+            })
+        });
+
+        Ok(order_self_references(rows, table_name, &self.foreign_keys))
+    }
+}
+
+/// Fails if a column whose values are replaced is referenced by a table inserted before it, as
+/// the referencing column could no longer be rewritten.
+fn check_mask_order(
+    order: &[String],
+    mut replaced: BTreeSet<(String, String)>,
+    foreign_keys: &[ForeignKey],
+) -> Result<()> {
+    // Columns referencing replaced columns are rewritten, so they are replaced as well
+    loop {
+        let rewritten: Vec<(String, String)> = foreign_keys
+            .iter()
+            .filter(|fk| replaced.contains(&(fk.to_table.clone(), fk.to_column.clone())))
+            .map(|fk| (fk.from_table.clone(), fk.from_column.clone()))
+            .filter(|column| !replaced.contains(column))
+            .collect();
+        if rewritten.is_empty() {
+            break;
+        }
+        replaced.extend(rewritten);
+    }
+
+    let position = |table_name: &str| order.iter().position(|name| name == table_name);
+    for fk in foreign_keys
+        .iter()
+        .filter(|fk| replaced.contains(&(fk.to_table.clone(), fk.to_column.clone())))
+    {
+        if let (Some(from), Some(to)) = (position(&fk.from_table), position(&fk.to_table)) {
+            if from < to {
+                bail!(
+                    "{}.{} references the masked column {}.{}, but their tables reference each other. Masking tables whose foreign keys form a cycle is not supported",
+                    fk.from_table,
+                    fk.from_column,
+                    fk.to_table,
+                    fk.to_column
+                );
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Replaces the columns of the rows of a table which are fields of its collection in `masks`
+/// with values generated from that collection. Other columns are kept as they are.
+///
+/// Fields which are `pseudonymise` content replace every value of their column by its
+/// pseudonym, keyed with their own secret or else with `secret`. Null values are kept.
+///
+/// Columns referencing masked columns are rewritten with the same values as the rows they
+/// reference, such that foreign keys stay consistent. This takes precedence over their own mask.
+/// The referenced tables must have been masked before, and their masked values are looked up in
+/// `replacements`, to which the masked values of the referenced columns of the table are added.
+pub(crate) fn mask_table(
+    table_name: &str,
+    rows: &mut [Value],
+    masks: &Namespace,
+    foreign_keys: &[ForeignKey],
+    seed: u64,
+    secret: Option<&str>,
+    replacements: &mut Replacements,
+) -> Result<()> {
+    let is_referenced = |column: &str| {
+        foreign_keys
+            .iter()
+            .any(|fk| fk.to_table == table_name && fk.to_column == column)
+    };
+
+    let mask = masks
+        .iter()
+        .find(|(name, _)| name.as_ref() == table_name)
+        .map(|(name, content)| (name.clone(), content.clone()));
+
+    let mut pseudonymised: Vec<(String, PseudonymiseContent)> = Vec::new();
+    if let Some((name, mut content)) = mask {
+        // Generate exactly one masked value for every row. Pseudonymised columns depend on the
+        // values of the row, so they are not sampled.
+        let (length, object) = mask_fields(&name, &mut content)?;
+        *length = Box::new(Content::Number(NumberContent::U64(U64::Constant(
+            rows.len() as u64,
+        ))));
+
+        let columns: Vec<String> = object
+            .fields
+            .iter()
+            .filter(|(_, content)| matches!(content, Content::Pseudonymise(_)))
+            .map(|(column, _)| column.clone())
+            .collect();
+        for column in columns {
+            if let Some(Content::Pseudonymise(content)) = object.fields.remove(&column) {
+                pseudonymised.push((column, content));
+            }
+        }
+
+        // Tables without rows have nothing to mask, and would never complete a sample
+        if !rows.is_empty() && !object.fields.is_empty() {
+            let namespace: Namespace = std::iter::once((name, content)).collect();
+            let masked_rows = match Sampler::try_from(&namespace)?.sample_seeded(None, 1, seed)? {
+                SamplerOutput::Namespace(generated) => generated
+                    .into_iter()
+                    .next()
+                    .map(|(_, masked_rows)| masked_rows)
+                    .unwrap_or_default(),
+                SamplerOutput::Collection(_) => unreachable!("the whole namespace is sampled"),
+            };
+            if masked_rows.len() != rows.len() {
+                bail!(
+                    "Generated {} masked rows for the {} rows of table {}",
+                    masked_rows.len(),
+                    rows.len(),
+                    table_name
+                );
+            }
+
+            for (row, masked_row) in rows.iter_mut().zip(masked_rows) {
+                let row = row
+                    .as_object_mut()
+                    .expect("This is always an object (sampler contract)");
+                let masked_row = match masked_row {
+                    Value::Object(masked_row) => masked_row,
+                    _ => unreachable!("masks are arrays of objects"),
+                };
+
+                for (column, masked) in masked_row {
+                    let original = match row.get_mut(&column) {
+                        Some(original) => std::mem::replace(original, masked.clone()),
+                        None => {
+                            bail!("The masked column {}.{} does not exist", table_name, column)
+                        }
+                    };
+
+                    if is_referenced(&column) {
+                        replacements
+                            .entry((table_name.to_string(), column))
+                            .or_default()
+                            .insert(original, masked);
+                    }
+                }
+            }
+        }
+    }
+
+    for (column, content) in pseudonymised {
+        let secret = content.secret.as_deref().or(secret).ok_or_else(|| {
+            anyhow!(
+                "No secret to pseudonymise {}.{} with. Set the secret of its content, the SYNTH_MASK_SECRET environment variable or pass --secret-file",
+                table_name,
+                column
+            )
+        })?;
+
+        let mut pseudonyms: HashMap<Value, Value> = HashMap::new();
+        for row in rows.iter_mut() {
+            let value = row
+                .as_object_mut()
+                .expect("This is always an object (sampler contract)")
+                .get_mut(&column)
+                .ok_or_else(|| {
+                    anyhow!("The masked column {}.{} does not exist", table_name, column)
+                })?;
+            if value.is_null() {
+                continue;
+            }
+
+            let pseudonym = match pseudonyms.get(value) {
+                Some(pseudonym) => pseudonym.clone(),
+                None => {
+                    let pseudonym = content.pseudonymise(secret, value).with_context(|| {
+                        format!("Failed to pseudonymise {}.{}", table_name, column)
+                    })?;
+                    pseudonyms.insert(value.clone(), pseudonym.clone());
+                    pseudonym
+                }
+            };
+            *value = pseudonym;
+        }
+
+        if is_referenced(&column) {
+            replacements
+                .entry((table_name.to_string(), column))
+                .or_default()
+                .extend(pseudonyms);
+        }
+    }
+
+    // Rewrite the columns referencing masked columns, including the columns of this table
+    for fk in foreign_keys.iter().filter(|fk| fk.from_table == table_name) {
+        let replacement = match replacements.get(&(fk.to_table.clone(), fk.to_column.clone())) {
+            Some(replacement) => replacement,
+            None => continue,
+        };
+
+        let mut rewritten = HashMap::new();
+        for row in rows.iter_mut() {
+            let value = row
+                .as_object_mut()
+                .expect("This is always an object (sampler contract)")
+                .get_mut(&fk.from_column);
+
+            if let Some(value) = value {
+                if let Some(masked) = replacement.get(value) {
+                    let original = std::mem::replace(value, masked.clone());
+                    rewritten.insert(original, masked.clone());
+                }
+            }
+        }
+
+        if is_referenced(&fk.from_column) && !rewritten.is_empty() {
+            replacements
+                .entry((table_name.to_string(), fk.from_column.clone()))
+                .or_default()
+                .extend(rewritten);
+        }
+    }
+
+    Ok(())
+}
+
+/// The length and the fields of the masks of a table.
+fn mask_fields<'a>(
+    name: &Name,
+    content: &'a mut Content,
+) -> Result<(&'a mut Box<Content>, &'a mut ObjectContent)> {
+    match content {
+        Content::Array(ArrayContent { length, content }) => match content.as_mut() {
+            Content::Object(object) => Ok((length, object)),
+            _ => bail!("The masks of table {} should be an array of objects", name),
+        },
+        _ => bail!("The masks of table {} should be an array of objects", name),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::datasource::subset::tests::rows;
+    use serde_json::json;
+
+    fn fk(from_table: &str, from_column: &str, to_table: &str, to_column: &str) -> ForeignKey {
+        ForeignKey {
+            from_table: from_table.to_string(),
+            from_column: from_column.to_string(),
+            to_table: to_table.to_string(),
+            to_column: to_column.to_string(),
+        }
+    }
+
+    #[test]
+    fn mask_table() {
+        let mut tables = vec![
+            (
+                "users",
+                rows(json!([
+                    { "email": "alice@example.com", "name": "Alice", "age": 30 },
+                    { "email": "bob@example.com", "name": "Bob", "age": 40 },
+                    { "email": "carol@example.com", "name": "Alice", "age": null },
+                ])),
+            ),
+            (
+                "orders",
+                rows(json!([
+                    { "id": 1, "email": "bob@example.com" },
+                    { "id": 2, "email": "alice@example.com" },
+                    { "id": 3, "email": "bob@example.com" },
+                ])),
+            ),
+            (
+                "refunds",
+                rows(json!([{ "order_id": 3, "email": "bob@example.com" }])),
+            ),
+        ];
+
+        let masks: Namespace = serde_json::from_value(json!({
+            "users": {
+                "type": "array",
+                "length": { "type": "number", "constant": 1 },
+                "content": {
+                    "type": "object",
+                    "email": { "type": "string", "pattern": "[a-z]{10}@masked\\.com" },
+                    "name": {
+                        "type": "pseudonymise",
+                        "content": { "type": "string", "pattern": "[A-Z][a-z]{5}" }
+                    }
+                }
+            }
+        }))
+        .unwrap();
+
+        let foreign_keys = vec![
+            fk("orders", "email", "users", "email"),
+            fk("refunds", "email", "orders", "email"),
+        ];
+
+        let mut replacements = Replacements::new();
+        let mask = |(table_name, rows): &mut (&str, Vec<Value>),
+                    secret,
+                    replacements: &mut Replacements| {
+            super::mask_table(
+                table_name,
+                rows,
+                &masks,
+                &foreign_keys,
+                0,
+                secret,
+                replacements,
+            )
+        };
+
+        assert!(mask(&mut tables[0].clone(), None, &mut Replacements::new()).is_err());
+        for table in tables.iter_mut() {
+            mask(table, Some("secret"), &mut replacements).unwrap();
+        }
+
+        let field = |table: usize, row: usize, column: &str| {
+            tables[table].1[row].as_object().unwrap()[column].clone()
+        };
+
+        let alice = field(0, 0, "email");
+        let bob = field(0, 1, "email");
+        assert!(alice.as_string().unwrap().ends_with("@masked.com"));
+        assert_ne!(alice, bob);
+        assert_ne!(field(0, 0, "name"), rows(json!(["Alice"]))[0]);
+        assert_eq!(field(0, 0, "name"), field(0, 2, "name"));
+        assert_ne!(field(0, 0, "name"), field(0, 1, "name"));
+        assert_eq!(field(0, 1, "age"), rows(json!([40]))[0]);
+
+        assert_eq!(field(1, 0, "email"), bob);
+        assert_eq!(field(1, 1, "email"), alice);
+        assert_eq!(field(1, 2, "email"), bob);
+        assert_eq!(field(2, 0, "email"), bob);
+
+        // Tables referencing each other can't be masked one at a time
+        let order = vec!["orders".to_string(), "users".to_string()];
+        let masked: BTreeSet<(String, String)> =
+            std::iter::once(("users".to_string(), "email".to_string())).collect();
+        let cycle = vec![
+            fk("orders", "email", "users", "email"),
+            fk("users", "last_order", "orders", "id"),
+        ];
+        assert!(check_mask_order(&order, masked.clone(), &cycle).is_err());
+        assert!(check_mask_order(&order, masked, &cycle[1..]).is_ok());
+    }
+}
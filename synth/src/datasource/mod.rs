@@ -1,10 +1,295 @@
-use anyhow::Result;
+use anyhow::{Context, Result};
 use async_trait::async_trait;
-use synth_core::Value;
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+use synth_core::{Namespace, Value};
 
+pub(crate) mod ddl;
+pub(crate) mod mask;
 pub(crate) mod mysql_datasource;
 pub(crate) mod postgres_datasource;
 pub(crate) mod relational_datasource;
+pub(crate) mod subset;
+pub(crate) mod type_check;
+
+/// How generated data is written into collections which may already contain data.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportMode {
+    /// Insert alongside existing data. Conflicts with existing rows are errors.
+    Append,
+    /// Delete all existing data from the target collections before inserting.
+    Truncate,
+    /// Existing rows with a conflicting primary key are replaced by the generated ones.
+    Replace,
+    /// Existing rows with a conflicting primary key are updated with the generated values.
+    Upsert,
+    /// Drop the target collections, along with their indexes and validators, before inserting.
+    /// Only supported for MongoDB.
+    Drop,
+}
+
+impl ExportMode {
+    pub const VARIANTS: [&'static str; 5] = ["append", "truncate", "replace", "upsert", "drop"];
+
+    /// The modes supported by relational databases, which can't drop tables.
+    pub const RELATIONAL_VARIANTS: [&'static str; 4] = ["append", "truncate", "replace", "upsert"];
+}
+
+impl std::fmt::Display for ExportMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            Self::Append => "append",
+            Self::Truncate => "truncate",
+            Self::Replace => "replace",
+            Self::Upsert => "upsert",
+            Self::Drop => "drop",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl Default for ExportMode {
+    fn default() -> Self {
+        Self::Append
+    }
+}
+
+impl FromStr for ExportMode {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "append" => Ok(Self::Append),
+            "truncate" => Ok(Self::Truncate),
+            "replace" => Ok(Self::Replace),
+            "upsert" => Ok(Self::Upsert),
+            "drop" => Ok(Self::Drop),
+            other => Err(anyhow!(
+                "Unknown export mode '{}'. Was expecting one of {}",
+                other,
+                Self::VARIANTS.join(", ")
+            )),
+        }
+    }
+}
+
+/// How the rows from which a schema is inferred are picked during import.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SampleStrategy {
+    /// Rows are picked uniformly at random, which requires a full scan of the table.
+    Random,
+    /// The first rows returned by the database are picked.
+    First,
+    /// Rows are picked from a random subset of the table without scanning all of it. The number
+    /// of rows is only an estimate.
+    TableSample,
+}
+
+impl SampleStrategy {
+    pub const VARIANTS: [&'static str; 3] = ["random", "first", "tablesample"];
+}
+
+impl std::fmt::Display for SampleStrategy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            Self::Random => "random",
+            Self::First => "first",
+            Self::TableSample => "tablesample",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl Default for SampleStrategy {
+    fn default() -> Self {
+        Self::Random
+    }
+}
+
+impl FromStr for SampleStrategy {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "random" => Ok(Self::Random),
+            "first" => Ok(Self::First),
+            "tablesample" => Ok(Self::TableSample),
+            other => Err(anyhow!(
+                "Unknown sample strategy '{}'. Was expecting one of {}",
+                other,
+                Self::VARIANTS.join(", ")
+            )),
+        }
+    }
+}
+
+/// Which rows, and how many of them, to sample from every collection during import.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SampleParams {
+    pub size: usize,
+    pub strategy: SampleStrategy,
+    pub seed: u64,
+    /// The noise added to the statistics inferred from the samples, if any
+    pub privacy: Option<Privacy>,
+}
+
+impl Default for SampleParams {
+    fn default() -> Self {
+        Self {
+            size: 10,
+            strategy: SampleStrategy::default(),
+            seed: 0,
+            privacy: None,
+        }
+    }
+}
+
+/// How much noise is added to the statistics inferred from sampled rows, such that they don't
+/// reveal individual rows. Parsed from `epsilon=<epsilon>[,threshold=<threshold>][,clamp=<low>..<high>]`.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+pub struct Privacy {
+    /// The privacy budget spent on each noised statistic separately, such that the privacy loss
+    /// of a collection adds up over its statistics. Lower values add more noise.
+    pub epsilon: f64,
+    /// Categories whose noisy number of occurrences is below this are left out
+    pub threshold: u64,
+    /// Public bounds of numeric values, which narrow the domain of their type. Sampled numbers
+    /// are clamped to them.
+    pub clamp: Option<(f64, f64)>,
+}
+
+impl Privacy {
+    pub const DEFAULT_THRESHOLD: u64 = 5;
+}
+
+impl FromStr for Privacy {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let mut epsilon = None;
+        let mut threshold = Self::DEFAULT_THRESHOLD;
+        let mut clamp = None;
+
+        for setting in s.split(',').map(str::trim) {
+            match setting.split_once('=') {
+                Some(("epsilon", value)) => {
+                    let value: f64 = value
+                        .parse()
+                        .with_context(|| format!("Invalid epsilon '{}'", value))?;
+                    if !value.is_finite() || value <= 0.0 {
+                        bail!("The epsilon should be a positive number, not {}", value);
+                    }
+                    epsilon = Some(value);
+                }
+                Some(("threshold", value)) => {
+                    threshold = value
+                        .parse()
+                        .with_context(|| format!("Invalid threshold '{}'", value))?;
+                }
+                Some(("clamp", value)) => {
+                    let bounds = value.split_once("..").and_then(|(low, high)| {
+                        Some((low.trim().parse::<f64>().ok()?, high.trim().parse::<f64>().ok()?))
+                    });
+                    match bounds {
+                        Some((low, high)) if low.is_finite() && high.is_finite() && low < high => {
+                            clamp = Some((low, high))
+                        }
+                        _ => bail!(
+                            "Invalid clamp '{}'. Was expecting <low>..<high> with low below high",
+                            value
+                        ),
+                    }
+                }
+                _ => bail!(
+                    "Unknown privacy setting '{}'. Was expecting epsilon=<epsilon>, threshold=<threshold> or clamp=<low>..<high>",
+                    setting
+                ),
+            }
+        }
+
+        match epsilon {
+            Some(epsilon) => Ok(Self {
+                epsilon,
+                threshold,
+                clamp,
+            }),
+            None => bail!("The privacy settings should include epsilon=<epsilon>"),
+        }
+    }
+}
+
+/// The rows of tables, read one table at a time in the order in which the tables are inserted.
+pub struct Tables {
+    /// The names of the tables, in the order in which they are inserted
+    pub names: Vec<String>,
+    pub rows: Box<dyn Iterator<Item = Result<(String, Vec<Value>)>>>,
+}
+
+impl From<Vec<(String, Vec<Value>)>> for Tables {
+    fn from(tables: Vec<(String, Vec<Value>)>) -> Self {
+        Self {
+            names: tables.iter().map(|(name, _)| name.clone()).collect(),
+            rows: Box::new(tables.into_iter().map(Ok)),
+        }
+    }
+}
+
+/// Settings tuning the load put on databases. Unset settings fall back to their defaults.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct LoadParams {
+    /// The maximum number of connections to the database
+    pub pool_size: Option<u32>,
+    /// The number of rows or documents inserted by a single statement
+    pub batch_size: Option<usize>,
+    /// The time after which statements are cancelled, in milliseconds
+    pub statement_timeout: Option<u64>,
+    /// The maximum number of statements running at the same time
+    pub concurrency: Option<usize>,
+}
+
+impl LoadParams {
+    pub const DEFAULT_POOL_SIZE: u32 = 3;
+    pub const DEFAULT_BATCH_SIZE: usize = 1000;
+
+    /// Fills the settings which are unset with the settings of `other`.
+    pub fn or(self, other: Self) -> Self {
+        Self {
+            pool_size: self.pool_size.or(other.pool_size),
+            batch_size: self.batch_size.or(other.batch_size),
+            statement_timeout: self.statement_timeout.or(other.statement_timeout),
+            concurrency: self.concurrency.or(other.concurrency),
+        }
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        if self.pool_size == Some(0) {
+            bail!("The pool size must be positive");
+        }
+        if self.batch_size == Some(0) {
+            bail!("The batch size must be positive");
+        }
+        if self.concurrency == Some(0) {
+            bail!("The concurrency must be positive");
+        }
+        Ok(())
+    }
+
+    pub fn pool_size(&self) -> u32 {
+        self.pool_size.unwrap_or(Self::DEFAULT_POOL_SIZE)
+    }
+
+    pub fn batch_size(&self) -> usize {
+        self.batch_size.unwrap_or(Self::DEFAULT_BATCH_SIZE)
+    }
+
+    /// Defaults to the pool size, as more statements would only wait for a connection.
+    pub fn concurrency(&self) -> usize {
+        self.concurrency.unwrap_or(self.pool_size() as usize)
+    }
+}
 
 /// This trait encompasses all data source types, whether it's SQL or No-SQL. APIs should be defined
 /// async when possible, delegating to the caller on how to handle it. Data source specific
@@ -17,5 +302,37 @@ pub trait DataSource {
     where
         Self: Sized;
 
-    async fn insert_data(&self, collection_name: &str, collection: &[Value]) -> Result<()>;
+    async fn insert_data(
+        &self,
+        collection_name: &str,
+        collection: &[Value],
+        mode: ExportMode,
+    ) -> Result<()>;
+
+    /// Checks the generated collections against the existing collections they are written into,
+    /// before anything is inserted.
+    async fn check_collections(
+        &self,
+        namespace: &Namespace,
+        collections: &[(&str, &[Value])],
+    ) -> Result<()>;
+
+    /// Deletes all existing data from the given collections, in an order which respects the
+    /// references between them.
+    async fn clear_collections(&self, collection_names: &[String]) -> Result<()>;
+
+    /// Creates the given collections of the namespace if they do not exist yet.
+    async fn create_collections(
+        &self,
+        namespace: &Namespace,
+        collection_names: &[String],
+    ) -> Result<()>;
+
+    /// Starts a transaction which all subsequent inserts will run in, until either
+    /// [commit_transaction] or [rollback_transaction] is called.
+    async fn begin_transaction(&self) -> Result<()>;
+
+    async fn commit_transaction(&self) -> Result<()>;
+
+    async fn rollback_transaction(&self) -> Result<()>;
 }
@@ -1,58 +1,171 @@
+use crate::datasource::ddl::SqlDialect;
 use crate::datasource::relational_datasource::{
     ColumnInfo, ForeignKey, PrimaryKey, RelationalDataSource, ValueWrapper,
 };
-use crate::datasource::DataSource;
+use crate::datasource::type_check::ColumnKind;
+use crate::datasource::{DataSource, ExportMode, LoadParams, SampleParams, SampleStrategy};
 use anyhow::{Context, Result};
+use async_std::sync::Mutex;
 use async_std::task;
 use async_trait::async_trait;
 use rust_decimal::prelude::ToPrimitive;
 use rust_decimal::Decimal;
 use sqlx::mysql::{MySqlColumn, MySqlPoolOptions, MySqlQueryResult, MySqlRow};
-use sqlx::{Column, MySql, Pool, Row, TypeInfo};
+use sqlx::{Column, Executor, MySql, Pool, Row, Transaction, TypeInfo};
 use std::collections::BTreeMap;
 use std::convert::TryFrom;
 use std::prelude::rust_2015::Result::Ok;
+use synth_core::graph::json::json_val_to_synth;
+use synth_core::graph::prelude::{ChronoValue, ChronoValueAndFormat};
 use synth_core::schema::number_content::{F64, I64, U64};
 use synth_core::schema::{
-    ChronoValueType, DateTimeContent, NumberContent, RangeStep, RegexContent, StringContent,
+    BoolContent, Categorical, ChronoValueType, DateTimeContent, NumberContent, ObjectContent,
+    RangeStep, RegexContent, StringContent,
 };
-use synth_core::{Content, Value};
+use synth_core::{Content, Namespace, Value};
 use synth_gen::prelude::*;
 
-/// TODO
-/// Known issues:
-/// - MySql aliases bool and boolean data types as tinyint. We currently define all tinyint as i8.
-///   Ideally, the user can define a way to force certain fields as bool rather than i8.
+pub struct MySqlConnectParams {
+    pub(crate) uri: String,
+    pub(crate) load: LoadParams,
+}
 
 pub struct MySqlDataSource {
     pool: Pool<MySql>,
+    transaction: Mutex<Option<Transaction<'static, MySql>>>,
+    load: LoadParams,
 }
 
 #[async_trait]
 impl DataSource for MySqlDataSource {
-    type ConnectParams = String;
+    type ConnectParams = MySqlConnectParams;
 
     fn new(connect_params: &Self::ConnectParams) -> Result<Self> {
         task::block_on(async {
+            // MariaDB has its own variable for the timeout, in seconds. MySQL only applies its
+            // timeout to `SELECT` statements.
+            let session_setup = connect_params.load.statement_timeout.map(|timeout| {
+                if connect_params.uri.starts_with("mariadb://") {
+                    format!(
+                        "SET SESSION max_statement_time = {}",
+                        timeout as f64 / 1000.
+                    )
+                } else {
+                    format!("SET SESSION max_execution_time = {}", timeout)
+                }
+            });
+
             let pool = MySqlPoolOptions::new()
-                .max_connections(3) //TODO expose this as a user config?
-                .connect(connect_params.as_str())
+                .max_connections(connect_params.load.pool_size())
+                .after_connect(move |conn| {
+                    let session_setup = session_setup.clone();
+                    Box::pin(async move {
+                        if let Some(session_setup) = session_setup {
+                            conn.execute(session_setup.as_str()).await?;
+                        }
+                        Ok(())
+                    })
+                })
+                .connect(connect_params.uri.as_str())
                 .await?;
 
-            Ok::<Self, anyhow::Error>(MySqlDataSource { pool })
+            Ok::<Self, anyhow::Error>(MySqlDataSource {
+                pool,
+                transaction: Mutex::new(None),
+                load: connect_params.load,
+            })
         })
     }
 
-    async fn insert_data(&self, collection_name: &str, collection: &[Value]) -> Result<()> {
-        self.insert_relational_data(collection_name, collection)
+    async fn insert_data(
+        &self,
+        collection_name: &str,
+        collection: &[Value],
+        mode: ExportMode,
+    ) -> Result<()> {
+        // Unlike Postgres sequences, AUTO_INCREMENT counters are advanced automatically when
+        // inserting explicit values larger than the counter, so they don't need to be reset.
+        self.insert_relational_data(collection_name, collection, mode)
             .await
     }
+
+    async fn check_collections(
+        &self,
+        namespace: &Namespace,
+        collections: &[(&str, &[Value])],
+    ) -> Result<()> {
+        self.check_relational_data(namespace, collections).await
+    }
+
+    async fn clear_collections(&self, collection_names: &[String]) -> Result<()> {
+        self.clear_relational_data(collection_names).await
+    }
+
+    async fn create_collections(
+        &self,
+        namespace: &Namespace,
+        collection_names: &[String],
+    ) -> Result<()> {
+        self.create_relational_tables(namespace, collection_names)
+            .await
+    }
+
+    async fn begin_transaction(&self) -> Result<()> {
+        let mut transaction = self.transaction.lock().await;
+        if transaction.is_some() {
+            bail!("A transaction is already in progress");
+        }
+        *transaction = Some(self.pool.begin().await?);
+        Ok(())
+    }
+
+    async fn commit_transaction(&self) -> Result<()> {
+        match self.transaction.lock().await.take() {
+            Some(transaction) => Ok(transaction.commit().await?),
+            None => bail!("There is no transaction in progress to commit"),
+        }
+    }
+
+    async fn rollback_transaction(&self) -> Result<()> {
+        match self.transaction.lock().await.take() {
+            Some(transaction) => Ok(transaction.rollback().await?),
+            None => bail!("There is no transaction in progress to roll back"),
+        }
+    }
+}
+
+impl MySqlDataSource {
+    /// The fraction of a table to sample to get about `size` rows, based on the row count
+    /// estimated by MySql.
+    async fn sample_fraction(&self, table_name: &str, size: usize) -> Result<f64> {
+        let query = r"SELECT table_rows
+            FROM information_schema.tables
+            WHERE table_name = ? AND table_schema = DATABASE()";
+
+        let estimated_rows: Option<u64> = sqlx::query(query)
+            .bind(table_name)
+            .fetch_one(&self.pool)
+            .await?
+            .try_get(0)?;
+
+        // Oversample to make up for the estimate being off
+        match estimated_rows {
+            Some(rows) if rows > 0 => Ok((2.0 * size as f64 / rows as f64).min(1.0)),
+            _ => Ok(1.0),
+        }
+    }
 }
 
 #[async_trait]
 impl RelationalDataSource for MySqlDataSource {
     type QueryResult = MySqlQueryResult;
 
+    const DIALECT: SqlDialect = SqlDialect::MySql;
+
+    fn load_params(&self) -> &LoadParams {
+        &self.load
+    }
+
     async fn execute_query(
         &self,
         query: String,
@@ -64,6 +177,14 @@ impl RelationalDataSource for MySqlDataSource {
             query = query.bind(param);
         }
 
+        // Only hold on to the lock when running inside a transaction, otherwise batches can run
+        // concurrently on the pool
+        let mut transaction = self.transaction.lock().await;
+        if let Some(transaction) = transaction.as_mut() {
+            return Ok(query.execute(transaction).await?);
+        }
+        drop(transaction);
+
         let result = query.execute(&self.pool).await?;
 
         Ok(result)
@@ -83,9 +204,37 @@ impl RelationalDataSource for MySqlDataSource {
         Ok(table_names)
     }
 
+    async fn get_rows(
+        &self,
+        table_name: &str,
+        condition: &str,
+        query_params: Vec<&Value>,
+    ) -> Result<Vec<Value>> {
+        let query = format!("SELECT * FROM {} WHERE {}", table_name, condition);
+        let mut query = sqlx::query(&query);
+
+        for param in query_params {
+            query = query.bind(param);
+        }
+
+        query
+            .fetch_all(&self.pool)
+            .await?
+            .into_iter()
+            .map(ValueWrapper::try_from)
+            .map(|v| match v {
+                Ok(wrapper) => Ok(wrapper.0),
+                Err(e) => bail!(
+                    "Failed to convert to value wrapper from query results: {:?}",
+                    e
+                ),
+            })
+            .collect()
+    }
+
     async fn get_columns_infos(&self, table_name: &str) -> Result<Vec<ColumnInfo>> {
         let query = r"SELECT column_name, ordinal_position, is_nullable, data_type,
-            character_maximum_length
+            character_maximum_length, numeric_precision, numeric_scale, column_type
             FROM information_schema.columns
             WHERE table_name = ? AND table_schema = DATABASE()";
 
@@ -125,13 +274,32 @@ impl RelationalDataSource for MySqlDataSource {
             .collect()
     }
 
-    async fn set_seed(&self) -> Result<()> {
+    async fn set_seed(&self, _seed: u64) -> Result<()> {
         // MySql doesn't set seed in a separate query
         Ok(())
     }
 
-    async fn get_deterministic_samples(&self, table_name: &str) -> Result<Vec<Value>> {
-        let query = format!("SELECT * FROM {} ORDER BY rand(0.5) LIMIT 10", table_name);
+    async fn get_deterministic_samples(
+        &self,
+        table_name: &str,
+        sample: &SampleParams,
+    ) -> Result<Vec<Value>> {
+        let query = match sample.strategy {
+            SampleStrategy::Random => format!(
+                "SELECT * FROM {} ORDER BY rand({}) LIMIT {}",
+                table_name, sample.seed, sample.size
+            ),
+            SampleStrategy::First => format!("SELECT * FROM {} LIMIT {}", table_name, sample.size),
+            // MySql has no TABLESAMPLE, so keep rows with a probability based on the estimated
+            // number of rows instead of sorting the whole table
+            SampleStrategy::TableSample => {
+                let fraction = self.sample_fraction(table_name, sample.size).await?;
+                format!(
+                    "SELECT * FROM {} WHERE rand({}) < {} LIMIT {}",
+                    table_name, sample.seed, fraction, sample.size
+                )
+            }
+        };
 
         sqlx::query(&query)
             .fetch_all(&self.pool)
@@ -148,9 +316,67 @@ impl RelationalDataSource for MySqlDataSource {
             .collect()
     }
 
-    fn decode_to_content(&self, data_type: &str, char_max_len: Option<i32>) -> Result<Content> {
+    fn column_kind(column_info: &ColumnInfo) -> ColumnKind {
+        let column_type = column_info
+            .column_type
+            .as_deref()
+            .unwrap_or_default()
+            .to_lowercase();
+        let unsigned = column_type.contains("unsigned");
+        let integer = |bits| {
+            if unsigned {
+                ColumnKind::unsigned(bits)
+            } else {
+                ColumnKind::signed(bits)
+            }
+        };
+
+        match column_info.data_type.to_lowercase().as_str() {
+            // MySql aliases bool and boolean as tinyint(1)
+            "tinyint" if column_type == "tinyint(1)" => ColumnKind::Bool,
+            "tinyint" => integer(8),
+            "smallint" => integer(16),
+            "mediumint" => integer(24),
+            "int" | "integer" => integer(32),
+            "bigint" => integer(64),
+            "float" | "double" | "numeric" | "decimal" => ColumnKind::Float,
+            "char" | "varchar" | "binary" | "varbinary" => ColumnKind::Text(
+                column_info
+                    .character_maximum_length
+                    .map(|char_max_len| char_max_len as usize),
+            ),
+            "tinytext" | "text" | "mediumtext" | "longtext" | "enum" | "set" => {
+                ColumnKind::Text(None)
+            }
+            "date" | "datetime" | "timestamp" | "time" => ColumnKind::DateTime,
+            _ => ColumnKind::Any,
+        }
+    }
+
+    fn decode_to_content(&self, column_info: &ColumnInfo) -> Result<Content> {
+        let data_type = &column_info.data_type;
+        let char_max_len = column_info.character_maximum_length;
+
+        let column_type = column_info.column_type.as_deref().unwrap_or_default();
+
         let content = match data_type.to_lowercase().as_str() {
-            "char" | "varchar" | "text" | "binary" | "varbinary" | "enum" | "set" => {
+            // MySql aliases bool and boolean as tinyint(1)
+            "tinyint" if column_type.eq_ignore_ascii_case("tinyint(1)") => {
+                Content::Bool(BoolContent::default())
+            }
+            "enum" => {
+                let mut categorical = Categorical::default();
+                for value in parse_column_type_values(column_type)? {
+                    categorical.push(value);
+                }
+                Content::String(StringContent::Categorical(categorical))
+            }
+            "set" => Content::String(StringContent::Pattern(
+                RegexContent::pattern(set_pattern(&parse_column_type_values(column_type)?))
+                    .context("set values are escaped")?,
+            )),
+            "json" => Content::Object(ObjectContent::default()),
+            "char" | "varchar" | "text" | "binary" | "varbinary" => {
                 let pattern =
                     "[a-zA-Z0-9]{0, {}}".replace("{}", &format!("{}", char_max_len.unwrap_or(1)));
                 Content::String(StringContent::Pattern(
@@ -194,7 +420,12 @@ impl RelationalDataSource for MySqlDataSource {
         Ok(content)
     }
 
-    fn extend_parameterised_query(query: &mut String, _curr_index: usize, extend: usize) {
+    fn extend_parameterised_query(
+        query: &mut String,
+        _curr_index: usize,
+        column_infos: &[Option<&ColumnInfo>],
+    ) {
+        let extend = column_infos.len();
         query.push('(');
         for i in 0..extend {
             query.push('?');
@@ -204,6 +435,41 @@ impl RelationalDataSource for MySqlDataSource {
         }
         query.push(')');
     }
+
+    fn conflict_clause(
+        mode: ExportMode,
+        primary_keys: &[String],
+        column_names: &[String],
+        column_infos: &[ColumnInfo],
+    ) -> String {
+        let assignments: Vec<String> = match mode {
+            // Replacing a row resets the columns we don't generate back to their defaults
+            ExportMode::Replace => column_infos
+                .iter()
+                .map(|column_info| &column_info.column_name)
+                .filter(|column_name| !primary_keys.contains(column_name))
+                .map(|column_name| {
+                    if column_names.contains(column_name) {
+                        format!("{0} = VALUES({0})", column_name)
+                    } else {
+                        format!("{} = DEFAULT", column_name)
+                    }
+                })
+                .collect(),
+            _ => column_names
+                .iter()
+                .filter(|column_name| !primary_keys.contains(column_name))
+                .map(|column_name| format!("{0} = VALUES({0})", column_name))
+                .collect(),
+        };
+
+        if assignments.is_empty() {
+            // Assigning the key to itself turns the insert into a no-op on conflict
+            format!(" ON DUPLICATE KEY UPDATE {0} = {0}", primary_keys[0])
+        } else {
+            format!(" ON DUPLICATE KEY UPDATE {}", assignments.join(", "))
+        }
+    }
 }
 
 impl TryFrom<MySqlRow> for ColumnInfo {
@@ -215,22 +481,26 @@ impl TryFrom<MySqlRow> for ColumnInfo {
             ordinal_position: row.try_get::<u32, usize>(1)? as i32,
             is_nullable: row.try_get::<String, usize>(2)? == *"YES",
             data_type: row.try_get::<String, usize>(3)?,
-            character_maximum_length: extract_column_char_max_len(4, row)?,
+            character_maximum_length: extract_column_length(4, &row)?,
+            numeric_precision: extract_column_length(5, &row)?,
+            numeric_scale: extract_column_length(6, &row)?,
+            column_type: Some(row.try_get::<String, usize>(7)?),
+            type_schema: None,
         })
     }
 }
 
-/// Extracts a column's max character length. MySql's datatype for max char length is INT, but for
-/// Mariadb it's BIGINT UNSIGNED, so we must try both rust data types when reading the row. We
-/// truncate i64 to i32 in order to fit our internal models and practically, we probably won't be
-/// generating synthetic data for sizes beyond i32.
-fn extract_column_char_max_len(index: usize, row: MySqlRow) -> Result<Option<i32>> {
-    let character_maximum_length = match row.try_get(index) {
+/// Extracts a column's max character length, numeric precision or numeric scale. MySql's datatype
+/// for these is INT, but for Mariadb it's BIGINT UNSIGNED, so we must try both rust data types
+/// when reading the row. We truncate i64 to i32 in order to fit our internal models and
+/// practically, we probably won't be generating synthetic data for sizes beyond i32.
+fn extract_column_length(index: usize, row: &MySqlRow) -> Result<Option<i32>> {
+    let length = match row.try_get(index) {
         Ok(c) => c,
         Err(_) => row.try_get::<Option<u64>, usize>(index)?.map(|c| c as i32),
     };
 
-    Ok(character_maximum_length)
+    Ok(length)
 }
 
 impl TryFrom<MySqlRow> for PrimaryKey {
@@ -274,6 +544,8 @@ impl TryFrom<MySqlRow> for ValueWrapper {
 
 fn try_match_value(row: &MySqlRow, column: &MySqlColumn) -> Result<Value> {
     let value = match column.type_info().name().to_lowercase().as_str() {
+        "boolean" => Value::Bool(row.try_get::<bool, &str>(column.name())?),
+        "json" => json_val_to_synth(row.try_get::<serde_json::Value, &str>(column.name())?),
         "char" | "varchar" | "text" | "binary" | "varbinary" | "enum" | "set" => {
             Value::String(row.try_get::<String, &str>(column.name())?)
         }
@@ -318,3 +590,184 @@ fn try_match_value(row: &MySqlRow, column: &MySqlColumn) -> Result<Value> {
 
     Ok(value)
 }
+
+/// Parses the values of an `enum(...)` or `set(...)` column type, in which values are quoted with
+/// single quotes and quotes within values are doubled.
+fn parse_column_type_values(column_type: &str) -> Result<Vec<String>> {
+    let list = column_type
+        .find('(')
+        .zip(column_type.rfind(')'))
+        .map(|(start, end)| &column_type[start + 1..end])
+        .ok_or_else(|| anyhow!("Could not find the values of column type {}", column_type))?;
+
+    let mut values = Vec::new();
+    let mut chars = list.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\'' => {
+                let mut value = String::new();
+                loop {
+                    match chars.next() {
+                        Some('\'') if chars.peek() == Some(&'\'') => {
+                            chars.next();
+                            value.push('\'');
+                        }
+                        Some('\'') => break,
+                        Some(c) => value.push(c),
+                        None => bail!("Unterminated value in column type {}", column_type),
+                    }
+                }
+                values.push(value);
+            }
+            ',' | ' ' => {}
+            c => bail!("Unexpected '{}' in column type {}", c, column_type),
+        }
+    }
+
+    if values.is_empty() {
+        bail!("Column type {} does not have any values", column_type)
+    }
+
+    Ok(values)
+}
+
+/// A pattern matching any subset of the values of a `set(...)` column, joined by commas in the
+/// order of the values, which is how MySql returns them.
+fn set_pattern(values: &[String]) -> String {
+    let escaped: Vec<String> = values.iter().map(|value| regex::escape(value)).collect();
+
+    let subsets: Vec<String> = (0..escaped.len())
+        .map(|first| {
+            let rest: String = escaped[first + 1..]
+                .iter()
+                .map(|value| format!("(,{})?", value))
+                .collect();
+            format!("{}{}", escaped[first], rest)
+        })
+        .collect();
+
+    format!("({})?", subsets.join("|"))
+}
+
+/// Converts a value to a MySQL literal which can be used in a SQL statement. Date times with a
+/// timezone are converted to UTC, like they are when bound as query parameters.
+pub(crate) fn to_mysql_literal(value: &Value) -> Result<String> {
+    let literal = match value {
+        Value::Null(_) => "NULL".to_string(),
+        Value::Bool(b) => if *b { "TRUE" } else { "FALSE" }.to_string(),
+        Value::Number(Number::F32(f)) if !f.is_finite() => {
+            bail!("MySQL does not support the floating point value {}", f)
+        }
+        Value::Number(Number::F64(f)) if !f.is_finite() => {
+            bail!("MySQL does not support the floating point value {}", f)
+        }
+        Value::Number(n) => n.to_string(),
+        Value::String(s) => quote_mysql_text(s),
+        Value::DateTime(ChronoValueAndFormat { value, .. }) => quote_mysql_text(&match value {
+            ChronoValue::NaiveDate(nd) => nd.format("%Y-%m-%d").to_string(),
+            ChronoValue::NaiveTime(nt) => nt.format("%H:%M:%S%.f").to_string(),
+            ChronoValue::NaiveDateTime(ndt) => ndt.format("%Y-%m-%d %H:%M:%S%.f").to_string(),
+            ChronoValue::DateTime(dt) => dt.naive_utc().format("%Y-%m-%d %H:%M:%S%.f").to_string(),
+        }),
+        Value::Object(_) | Value::Array(_) => {
+            quote_mysql_text(&synth_core::graph::json::synth_val_to_json(value.clone()).to_string())
+        }
+    };
+
+    Ok(literal)
+}
+
+fn quote_mysql_text(text: &str) -> String {
+    let mut out = String::with_capacity(text.len() + 2);
+    out.push('\'');
+    for c in text.chars() {
+        match c {
+            '\\' => out.push_str("\\\\"),
+            '\'' => out.push_str("\\'"),
+            '\0' => out.push_str("\\0"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\x1a' => out.push_str("\\Z"),
+            c => out.push(c),
+        }
+    }
+    out.push('\'');
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_to_mysql_literal() {
+        assert_eq!(to_mysql_literal(&Value::Null(())).unwrap(), "NULL");
+        assert_eq!(to_mysql_literal(&Value::Bool(true)).unwrap(), "TRUE");
+        assert_eq!(
+            to_mysql_literal(&Value::Number(42i64.into())).unwrap(),
+            "42"
+        );
+        assert_eq!(
+            to_mysql_literal(&Value::String("it's a \\ back\nslash".to_string())).unwrap(),
+            "'it\\'s a \\\\ back\\nslash'"
+        );
+        assert!(to_mysql_literal(&Value::Number(f64::NAN.into())).is_err());
+    }
+
+    #[test]
+    fn test_parse_column_type_values() {
+        assert_eq!(
+            parse_column_type_values("enum('a','it''s','b,c')").unwrap(),
+            vec!["a", "it's", "b,c"]
+        );
+        assert!(parse_column_type_values("enum('a)").is_err());
+        assert!(parse_column_type_values("tinyint(1)").is_err());
+    }
+
+    #[test]
+    fn test_set_pattern() {
+        let values = vec!["a".to_string(), "b.c".to_string(), "d".to_string()];
+        let pattern = regex::Regex::new(&format!("^{}$", set_pattern(&values))).unwrap();
+
+        for subset in ["", "a", "b.c", "a,d", "a,b.c,d", "b.c,d"] {
+            assert!(pattern.is_match(subset), "{}", subset);
+        }
+        for not_a_subset in ["d,a", "a,", ",a", "bxc", "a,a"] {
+            assert!(!pattern.is_match(not_a_subset), "{}", not_a_subset);
+        }
+    }
+
+    #[test]
+    fn test_conflict_clause() {
+        let column_infos: Vec<ColumnInfo> = ["id", "name", "updated_at"]
+            .iter()
+            .enumerate()
+            .map(|(i, column_name)| ColumnInfo {
+                column_name: column_name.to_string(),
+                ordinal_position: i as i32 + 1,
+                is_nullable: false,
+                data_type: "int".to_string(),
+                character_maximum_length: None,
+                numeric_precision: None,
+                numeric_scale: None,
+                column_type: None,
+                type_schema: None,
+            })
+            .collect();
+        let primary_keys = vec!["id".to_string()];
+        let column_names = vec!["id".to_string(), "name".to_string()];
+        let conflict_clause = |mode| {
+            MySqlDataSource::conflict_clause(mode, &primary_keys, &column_names, &column_infos)
+        };
+
+        assert_eq!(
+            conflict_clause(ExportMode::Replace),
+            " ON DUPLICATE KEY UPDATE name = VALUES(name), updated_at = DEFAULT"
+        );
+        assert_eq!(
+            conflict_clause(ExportMode::Upsert),
+            " ON DUPLICATE KEY UPDATE name = VALUES(name)"
+        );
+    }
+}
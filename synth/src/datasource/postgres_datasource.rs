@@ -1,33 +1,47 @@
+use crate::datasource::ddl::SqlDialect;
 use crate::datasource::relational_datasource::{
-    ColumnInfo, ForeignKey, PrimaryKey, RelationalDataSource, ValueWrapper,
+    insert_batch_size, ColumnInfo, ForeignKey, PrimaryKey, RelationalDataSource, ValueWrapper,
 };
-use crate::datasource::DataSource;
+use crate::datasource::type_check::ColumnKind;
+use crate::datasource::{DataSource, ExportMode, LoadParams, SampleParams, SampleStrategy};
 use anyhow::{Context, Result};
-use async_std::sync::Arc;
+use async_std::sync::{Arc, Mutex};
 use async_std::task;
 use async_trait::async_trait;
+use chrono::{DateTime, FixedOffset, NaiveDateTime, NaiveTime, Utc};
 use rust_decimal::prelude::ToPrimitive;
 use rust_decimal::Decimal;
-use sqlx::postgres::{PgColumn, PgPoolOptions, PgQueryResult, PgRow};
-use sqlx::{Column, Executor, Pool, Postgres, Row, TypeInfo};
+use sqlx::postgres::types::{PgInterval, PgTimeTz};
+use sqlx::postgres::{PgColumn, PgConnection, PgPoolOptions, PgQueryResult, PgRow};
+use sqlx::{Column, Decode, Executor, Pool, Postgres, Row, Transaction, Type, TypeInfo};
 use std::collections::BTreeMap;
-use std::convert::TryFrom;
-use synth_core::schema::number_content::{F32, F64, I32, I64};
+use std::convert::{TryFrom, TryInto};
+use std::net::IpAddr;
+use synth_core::graph::json::json_val_to_synth;
+use synth_core::graph::prelude::{ChronoValue, ChronoValueAndFormat, Number};
+use synth_core::graph::string::FakerArgs;
+use synth_core::schema::number_content::{F32, F64, I32, I64, U64};
 use synth_core::schema::{
-    BoolContent, ChronoValueType, DateTimeContent, NumberContent, RangeStep, RegexContent,
-    StringContent, Uuid,
+    ArrayContent, BoolContent, Categorical, ChronoValueType, DateTimeContent, FakerContent,
+    NumberContent, ObjectContent, RangeStep, RegexContent, StringContent, Uuid,
 };
-use synth_core::{Content, Value};
+use synth_core::{Content, Namespace, Value};
+
+/// Size of the chunks of `COPY` data sent to the database at a time.
+const COPY_CHUNK_SIZE: usize = 1 << 20;
 
 pub struct PostgresConnectParams {
     pub(crate) uri: String,
     pub(crate) schema: Option<String>,
+    pub(crate) load: LoadParams,
 }
 
 pub struct PostgresDataSource {
     pool: Pool<Postgres>,
     single_thread_pool: Pool<Postgres>,
     schema: String, // consider adding a type schema
+    transaction: Mutex<Option<Transaction<'static, Postgres>>>,
+    load: LoadParams,
 }
 
 #[async_trait]
@@ -41,14 +55,18 @@ impl DataSource for PostgresDataSource {
                 .clone()
                 .unwrap_or_else(|| "public".to_string());
 
-            let mut arc = Arc::new(schema.clone());
+            let mut session_setup = format!("SET search_path = '{}';", schema);
+            if let Some(statement_timeout) = connect_params.load.statement_timeout {
+                session_setup.push_str(&format!("SET statement_timeout = {};", statement_timeout));
+            }
+
+            let mut arc = Arc::new(session_setup.clone());
             let pool = PgPoolOptions::new()
-                .max_connections(3) //TODO expose this as a user config?
+                .max_connections(connect_params.load.pool_size())
                 .after_connect(move |conn| {
-                    let schema = arc.clone();
+                    let session_setup = arc.clone();
                     Box::pin(async move {
-                        conn.execute(&*format!("SET search_path = '{}';", schema))
-                            .await?;
+                        conn.execute(session_setup.as_str()).await?;
                         Ok(())
                     })
                 })
@@ -56,14 +74,13 @@ impl DataSource for PostgresDataSource {
                 .await?;
 
             // Needed for queries that require explicit synchronous order, i.e. setseed + random
-            arc = Arc::new(schema.clone());
+            arc = Arc::new(session_setup);
             let single_thread_pool = PgPoolOptions::new()
                 .max_connections(1)
                 .after_connect(move |conn| {
-                    let schema = arc.clone();
+                    let session_setup = arc.clone();
                     Box::pin(async move {
-                        conn.execute(&*format!("SET search_path = '{}';", schema))
-                            .await?;
+                        conn.execute(session_setup.as_str()).await?;
                         Ok(())
                     })
                 })
@@ -77,17 +94,225 @@ impl DataSource for PostgresDataSource {
                 pool,
                 single_thread_pool,
                 schema,
+                transaction: Mutex::new(None),
+                load: connect_params.load,
             })
         })
     }
 
-    async fn insert_data(&self, collection_name: &str, collection: &[Value]) -> Result<()> {
-        self.insert_relational_data(collection_name, collection)
+    async fn insert_data(
+        &self,
+        collection_name: &str,
+        collection: &[Value],
+        mode: ExportMode,
+    ) -> Result<()> {
+        // COPY cannot resolve conflicts with existing rows
+        let can_copy = matches!(mode, ExportMode::Append | ExportMode::Truncate);
+
+        // Collections which don't fit in a single INSERT statement are loaded with
+        // `COPY ... FROM STDIN` instead, which has no parameter limit
+        let columns = collection
+            .first()
+            .and_then(Value::as_object)
+            .map_or(0, |row| row.len());
+        let fits_one_insert = collection.len() <= insert_batch_size(&self.load, columns);
+
+        let copy_data = if can_copy && !fits_one_insert {
+            encode_copy_text(collection)
+                .map_err(|e| {
+                    warn!(
+                        "Cannot use COPY for collection {}, falling back to INSERT: {}",
+                        collection_name, e
+                    )
+                })
+                .ok()
+        } else {
+            None
+        };
+
+        match copy_data {
+            Some(data) => self.copy_data(collection_name, collection, &data).await?,
+            None => {
+                self.insert_relational_data(collection_name, collection, mode)
+                    .await?
+            }
+        }
+
+        if let Some(first_row) = collection.get(0) {
+            let column_names: Vec<&String> = first_row
+                .as_object()
+                .expect("This is always an object (sampler contract)")
+                .keys()
+                .collect();
+            self.reset_sequences(collection_name, &column_names)
+                .await
+                .with_context(|| format!("Failed to reset the sequences of {}", collection_name))?;
+        }
+
+        Ok(())
+    }
+
+    async fn check_collections(
+        &self,
+        namespace: &Namespace,
+        collections: &[(&str, &[Value])],
+    ) -> Result<()> {
+        self.check_relational_data(namespace, collections).await
+    }
+
+    async fn clear_collections(&self, collection_names: &[String]) -> Result<()> {
+        self.clear_relational_data(collection_names).await
+    }
+
+    async fn create_collections(
+        &self,
+        namespace: &Namespace,
+        collection_names: &[String],
+    ) -> Result<()> {
+        self.create_relational_tables(namespace, collection_names)
             .await
     }
+
+    async fn begin_transaction(&self) -> Result<()> {
+        let mut transaction = self.transaction.lock().await;
+        if transaction.is_some() {
+            bail!("A transaction is already in progress");
+        }
+        *transaction = Some(self.pool.begin().await?);
+        Ok(())
+    }
+
+    async fn commit_transaction(&self) -> Result<()> {
+        match self.transaction.lock().await.take() {
+            Some(transaction) => Ok(transaction.commit().await?),
+            None => bail!("There is no transaction in progress to commit"),
+        }
+    }
+
+    async fn rollback_transaction(&self) -> Result<()> {
+        match self.transaction.lock().await.take() {
+            Some(transaction) => Ok(transaction.rollback().await?),
+            None => bail!("There is no transaction in progress to roll back"),
+        }
+    }
 }
 
 impl PostgresDataSource {
+    /// Bulk loads a collection with `COPY ... FROM STDIN` using the text format. `data` is the
+    /// collection as encoded by [encode_copy_text].
+    async fn copy_data(
+        &self,
+        collection_name: &str,
+        collection: &[Value],
+        data: &str,
+    ) -> Result<()> {
+        let column_names = collection[0]
+            .as_object()
+            .expect("This is always an object (sampler contract)")
+            .keys()
+            .cloned()
+            .collect::<Vec<String>>()
+            .join(",");
+        let statement = format!("COPY {} ({}) FROM STDIN", collection_name, column_names);
+
+        let mut transaction = self.transaction.lock().await;
+        let mut pool_connection;
+        let connection: &mut PgConnection = match transaction.as_mut() {
+            Some(transaction) => transaction,
+            None => {
+                pool_connection = self.pool.acquire().await?;
+                &mut pool_connection
+            }
+        };
+
+        let mut copy_in = connection.copy_in_raw(&statement).await?;
+
+        for chunk in data.as_bytes().chunks(COPY_CHUNK_SIZE) {
+            if let Err(e) = copy_in.send(chunk).await {
+                copy_in.abort(e.to_string()).await?;
+                return Err(e.into());
+            }
+        }
+
+        let rows = copy_in.finish().await?;
+
+        info!("Copied {} rows...", rows);
+        Ok(())
+    }
+
+    /// Advances the sequences owned by the given columns (i.e. `serial` and identity columns) to
+    /// the largest value in the table. Otherwise the next insert relying on the column default
+    /// would collide with the rows we generated.
+    async fn reset_sequences(&self, table_name: &str, column_names: &[&String]) -> Result<()> {
+        for column_name in column_names {
+            let sequence: Option<String> = sqlx::query("SELECT pg_get_serial_sequence($1, $2)")
+                .bind(table_name)
+                .bind(column_name.as_str())
+                .fetch_one(&self.single_thread_pool)
+                .await?
+                .try_get(0)?;
+
+            if let Some(sequence) = sequence {
+                // Run through `execute_query` so that the rows inserted in an ongoing transaction
+                // are taken into account
+                let query = format!(
+                    "SELECT setval(cast(cast($1 as text) as regclass), COALESCE(MAX({0}), 1), MAX({0}) IS NOT NULL) FROM {1}",
+                    column_name, table_name
+                );
+                self.execute_query(query, vec![&Value::String(sequence.clone())])
+                    .await?;
+                debug!(
+                    "Reset sequence {} of {}.{}",
+                    sequence, table_name, column_name
+                );
+            }
+        }
+
+        Ok(())
+    }
+
+    /// The percentage of a table to sample to get about `size` rows, based on the row count
+    /// estimated by the last `ANALYZE` of the table.
+    async fn sample_percentage(&self, table_name: &str, size: usize) -> Result<f64> {
+        let estimated_rows: f32 =
+            sqlx::query("SELECT reltuples FROM pg_class WHERE oid = cast($1 as regclass)")
+                .bind(table_name)
+                .fetch_one(&self.single_thread_pool)
+                .await?
+                .try_get(0)?;
+
+        // Tables which have never been analyzed have no estimate. As `SYSTEM` samples whole
+        // pages, oversample to make up for pages with fewer rows than average.
+        if estimated_rows <= 0.0 {
+            Ok(100.0)
+        } else {
+            Ok((200.0 * size as f64 / estimated_rows as f64).min(100.0))
+        }
+    }
+
+    /// Returns the labels of an enum type of the schema in their declared order, or nothing if the
+    /// type is not an enum.
+    async fn get_enum_labels(&self, type_schema: &str, type_name: &str) -> Result<Vec<String>> {
+        let query = r"SELECT e.enumlabel
+        FROM pg_enum e
+        JOIN pg_type t ON t.oid = e.enumtypid
+        JOIN pg_namespace n ON n.oid = t.typnamespace
+        WHERE t.typname = $1
+        AND n.nspname = $2
+        ORDER BY e.enumsortorder";
+
+        let labels = sqlx::query(query)
+            .bind(type_name)
+            .bind(type_schema)
+            .fetch_all(&self.single_thread_pool)
+            .await?
+            .into_iter()
+            .map(|row| row.try_get(0))
+            .collect::<Result<Vec<String>, _>>()?;
+
+        Ok(labels)
+    }
+
     async fn check_schema_exists(pool: &Pool<Postgres>, schema: &str) -> Result<()> {
         let query = r"SELECT schema_name
         FROM information_schema.schemata
@@ -117,6 +342,12 @@ impl PostgresDataSource {
 impl RelationalDataSource for PostgresDataSource {
     type QueryResult = PgQueryResult;
 
+    const DIALECT: SqlDialect = SqlDialect::Postgres;
+
+    fn load_params(&self) -> &LoadParams {
+        &self.load
+    }
+
     async fn execute_query(
         &self,
         query: String,
@@ -128,6 +359,14 @@ impl RelationalDataSource for PostgresDataSource {
             query = query.bind(param);
         }
 
+        // Only hold on to the lock when running inside a transaction, otherwise batches can run
+        // concurrently on the pool
+        let mut transaction = self.transaction.lock().await;
+        if let Some(transaction) = transaction.as_mut() {
+            return Ok(query.execute(transaction).await?);
+        }
+        drop(transaction);
+
         let result = query.execute(&self.pool).await?;
 
         Ok(result)
@@ -151,9 +390,37 @@ impl RelationalDataSource for PostgresDataSource {
         tables
     }
 
+    async fn get_rows(
+        &self,
+        table_name: &str,
+        condition: &str,
+        query_params: Vec<&Value>,
+    ) -> Result<Vec<Value>> {
+        let query = format!("SELECT * FROM {} WHERE {}", table_name, condition);
+        let mut query = sqlx::query(&query);
+
+        for param in query_params {
+            query = query.bind(param);
+        }
+
+        query
+            .fetch_all(&self.pool)
+            .await?
+            .into_iter()
+            .map(ValueWrapper::try_from)
+            .map(|v| match v {
+                Ok(wrapper) => Ok(wrapper.0),
+                Err(e) => bail!(
+                    "Failed to convert to value wrapper from query results: {:?}",
+                    e
+                ),
+            })
+            .collect()
+    }
+
     async fn get_columns_infos(&self, table_name: &str) -> Result<Vec<ColumnInfo>> {
         let query = r"SELECT column_name, ordinal_position, is_nullable, udt_name,
-        character_maximum_length
+        character_maximum_length, numeric_precision, numeric_scale, udt_schema
         FROM information_schema.columns
         WHERE table_name = $1
         AND table_schema = $2
@@ -207,8 +474,10 @@ impl RelationalDataSource for PostgresDataSource {
 
     /// Must use the singled threaded pool when setting this in conjunction with random, called by
     /// [get_deterministic_samples]. Otherwise, expect endless facepalms (-_Q)
-    async fn set_seed(&self) -> Result<()> {
-        sqlx::query("SELECT setseed(0.5)")
+    async fn set_seed(&self, seed: u64) -> Result<()> {
+        // `setseed` only accepts seeds between -1 and 1
+        sqlx::query("SELECT setseed($1)")
+            .bind(seed as f64 / u64::MAX as f64)
             .execute(&self.single_thread_pool)
             .await?;
         Ok(())
@@ -216,10 +485,27 @@ impl RelationalDataSource for PostgresDataSource {
 
     /// Must use the singled threaded pool when setting this in conjunction with setseed, called by
     /// [set_seed]. Otherwise, expect big regrets :(
-    async fn get_deterministic_samples(&self, table_name: &str) -> Result<Vec<Value>> {
-        let query: &str = &format!("SELECT * FROM {} ORDER BY random() LIMIT 10", table_name);
+    async fn get_deterministic_samples(
+        &self,
+        table_name: &str,
+        sample: &SampleParams,
+    ) -> Result<Vec<Value>> {
+        let query = match sample.strategy {
+            SampleStrategy::Random => format!(
+                "SELECT * FROM {} ORDER BY random() LIMIT {}",
+                table_name, sample.size
+            ),
+            SampleStrategy::First => format!("SELECT * FROM {} LIMIT {}", table_name, sample.size),
+            SampleStrategy::TableSample => {
+                let percentage = self.sample_percentage(table_name, sample.size).await?;
+                format!(
+                    "SELECT * FROM {} TABLESAMPLE SYSTEM ({}) REPEATABLE ({}) LIMIT {}",
+                    table_name, percentage, sample.seed, sample.size
+                )
+            }
+        };
 
-        sqlx::query(query)
+        sqlx::query(&query)
             .fetch_all(&self.single_thread_pool)
             .await?
             .into_iter()
@@ -234,62 +520,88 @@ impl RelationalDataSource for PostgresDataSource {
             .collect()
     }
 
-    // until https://github.com/rust-lang/rust-clippy/pull/7865 gets into nightly
-    #[allow(clippy::match_str_case_mismatch)]
-    fn decode_to_content(&self, data_type: &str, char_max_len: Option<i32>) -> Result<Content> {
-        let content = match data_type.to_lowercase().as_str() {
-            "bool" => Content::Bool(BoolContent::default()),
-            "oid" => {
-                bail!("OID data type not supported")
-            }
-            "char" | "varchar" | "text" | "citext" | "bpchar" | "name" | "unknown" => {
-                let pattern =
-                    "[a-zA-Z0-9]{0, {}}".replace("{}", &format!("{}", char_max_len.unwrap_or(1)));
-                Content::String(StringContent::Pattern(
-                    RegexContent::pattern(pattern).context("pattern will always compile")?,
-                ))
-            }
-            "int2" => Content::Number(NumberContent::I64(I64::Range(RangeStep::default()))),
-            "int4" => Content::Number(NumberContent::I32(I32::Range(RangeStep::default()))),
-            "int8" => Content::Number(NumberContent::I64(I64::Range(RangeStep::default()))),
-            "float4" => Content::Number(NumberContent::F32(F32::Range(RangeStep::default()))),
-            "float8" => Content::Number(NumberContent::F64(F64::Range(RangeStep::default()))),
-            "numeric" => Content::Number(NumberContent::F64(F64::Range(RangeStep::default()))),
-            "timestamptz" => Content::DateTime(DateTimeContent {
-                format: "".to_string(), // todo
-                type_: ChronoValueType::DateTime,
-                begin: None,
-                end: None,
-            }),
-            "timestamp" => Content::DateTime(DateTimeContent {
-                format: "".to_string(), // todo
-                type_: ChronoValueType::NaiveDateTime,
-                begin: None,
-                end: None,
-            }),
-            "date" => Content::DateTime(DateTimeContent {
-                format: "%Y-%m-%d".to_string(),
-                type_: ChronoValueType::NaiveDate,
-                begin: None,
-                end: None,
-            }),
-            "uuid" => Content::String(StringContent::Uuid(Uuid)),
-            _ => bail!("We haven't implemented a converter for {}", data_type),
-        };
+    fn decode_to_content(&self, column_info: &ColumnInfo) -> Result<Content> {
+        let type_schema = column_info.type_schema.as_ref().unwrap_or(&self.schema);
+        decode_type_to_content(
+            &column_info.data_type,
+            column_info.character_maximum_length,
+            column_info.numeric_precision.zip(column_info.numeric_scale),
+            &|type_name| task::block_on(self.get_enum_labels(type_schema, type_name)),
+        )
+    }
 
-        Ok(content)
+    fn column_kind(column_info: &ColumnInfo) -> ColumnKind {
+        data_type_kind(&column_info.data_type, column_info.character_maximum_length)
     }
 
-    fn extend_parameterised_query(query: &mut String, curr_index: usize, extend: usize) {
+    fn extend_parameterised_query(
+        query: &mut String,
+        curr_index: usize,
+        column_infos: &[Option<&ColumnInfo>],
+    ) {
         query.push('(');
-        for i in 0..extend {
-            query.push_str(&format!("${}", curr_index + i + 1));
-            if i != extend - 1 {
+        for (i, column_info) in column_infos.iter().enumerate() {
+            // Parameters are sent as text and cast to the type of the column, which lets Postgres
+            // parse types such as json, arrays, enums and network addresses for us
+            match column_info {
+                Some(column_info) => query.push_str(&format!(
+                    "CAST(CAST(${} AS text) AS \"{}\")",
+                    curr_index + i + 1,
+                    column_info.data_type.replace('"', "\"\"")
+                )),
+                None => query.push_str(&format!("${}", curr_index + i + 1)),
+            }
+            if i != column_infos.len() - 1 {
                 query.push(',');
             }
         }
         query.push(')');
     }
+
+    fn encode_parameter(value: &Value, column_info: Option<&ColumnInfo>) -> Result<Value> {
+        match (value, column_info) {
+            (Value::Null(_), _) | (_, None) => Ok(value.clone()),
+            (value, Some(_)) => Ok(Value::String(to_postgres_text(value)?)),
+        }
+    }
+
+    fn conflict_clause(
+        mode: ExportMode,
+        primary_keys: &[String],
+        column_names: &[String],
+        column_infos: &[ColumnInfo],
+    ) -> String {
+        let assignments: Vec<String> = match mode {
+            // Replacing a row resets the columns we don't generate back to their defaults
+            ExportMode::Replace => column_infos
+                .iter()
+                .map(|column_info| &column_info.column_name)
+                .filter(|column_name| !primary_keys.contains(column_name))
+                .map(|column_name| {
+                    if column_names.contains(column_name) {
+                        format!("{0} = EXCLUDED.{0}", column_name)
+                    } else {
+                        format!("{} = DEFAULT", column_name)
+                    }
+                })
+                .collect(),
+            _ => column_names
+                .iter()
+                .filter(|column_name| !primary_keys.contains(column_name))
+                .map(|column_name| format!("{0} = EXCLUDED.{0}", column_name))
+                .collect(),
+        };
+
+        if assignments.is_empty() {
+            format!(" ON CONFLICT ({}) DO NOTHING", primary_keys.join(","))
+        } else {
+            format!(
+                " ON CONFLICT ({}) DO UPDATE SET {}",
+                primary_keys.join(","),
+                assignments.join(", ")
+            )
+        }
+    }
 }
 
 impl TryFrom<PgRow> for ColumnInfo {
@@ -302,6 +614,10 @@ impl TryFrom<PgRow> for ColumnInfo {
             is_nullable: row.try_get::<String, usize>(2)? == *"YES",
             data_type: row.try_get(3)?,
             character_maximum_length: row.try_get(4)?,
+            numeric_precision: row.try_get(5)?,
+            numeric_scale: row.try_get(6)?,
+            column_type: None,
+            type_schema: row.try_get(7)?,
         })
     }
 }
@@ -345,6 +661,120 @@ impl TryFrom<PgRow> for ValueWrapper {
     }
 }
 
+/// Maps a Postgres type, as named by `udt_name`, to content generating values of that type. Array
+/// types are named after their element type, prefixed with an underscore. Types which aren't built
+/// in are looked up as enums with `enum_labels`.
+// until https://github.com/rust-lang/rust-clippy/pull/7865 gets into nightly
+#[allow(clippy::match_str_case_mismatch)]
+fn decode_type_to_content(
+    data_type: &str,
+    char_max_len: Option<i32>,
+    numeric_precision_scale: Option<(i32, i32)>,
+    enum_labels: &dyn Fn(&str) -> Result<Vec<String>>,
+) -> Result<Content> {
+    let pattern = |pattern: &str| -> Result<Content> {
+        Ok(Content::String(StringContent::Pattern(
+            RegexContent::pattern(pattern.to_string()).context("pattern will always compile")?,
+        )))
+    };
+    let date_time = |format: &str, type_: ChronoValueType| {
+        Content::DateTime(DateTimeContent {
+            format: format.to_string(),
+            type_,
+            begin: None,
+            end: None,
+        })
+    };
+
+    let content = match data_type.to_lowercase().as_str() {
+        "bool" => Content::Bool(BoolContent::default()),
+        "oid" => {
+            bail!("OID data type not supported")
+        }
+        "char" | "varchar" | "text" | "citext" | "bpchar" | "name" | "unknown" => {
+            pattern(&"[a-zA-Z0-9]{0, {}}".replace("{}", &format!("{}", char_max_len.unwrap_or(1))))?
+        }
+        "int2" => Content::Number(NumberContent::I64(I64::Range(RangeStep::default()))),
+        "int4" => Content::Number(NumberContent::I32(I32::Range(RangeStep::default()))),
+        "int8" => Content::Number(NumberContent::I64(I64::Range(RangeStep::default()))),
+        "float4" => Content::Number(NumberContent::F32(F32::Range(RangeStep::default()))),
+        "float8" => Content::Number(NumberContent::F64(F64::Range(RangeStep::default()))),
+        "numeric" => match numeric_precision_scale {
+            Some((precision, scale)) => {
+                // Stay below the smallest value which no longer fits in `precision - scale`
+                // integral digits
+                let step = 10f64.powi(-scale);
+                let max = 10f64.powi(precision - scale) - step;
+                Content::Number(NumberContent::F64(F64::Range(RangeStep::new(
+                    -max, max, step,
+                ))))
+            }
+            None => Content::Number(NumberContent::F64(F64::Range(RangeStep::default()))),
+        },
+        "json" | "jsonb" => Content::Object(ObjectContent::default()),
+        "interval" => pattern("[1-9][0-9]{0,2} (days|hours|minutes|seconds)")?,
+        "inet" => Content::String(StringContent::Faker(FakerContent {
+            generator: "ipv4".to_string(),
+            locales: Vec::new(),
+            args: FakerArgs::default(),
+        })),
+        "cidr" => pattern("(10|172|192)\\.[1-9]?[0-9]\\.[1-9]?[0-9]\\.0/24")?,
+        "bytea" => pattern("\\\\x([0-9a-f]{2}){0,16}")?,
+        "time" => date_time("%H:%M:%S", ChronoValueType::NaiveTime),
+        "timetz" => pattern("([01][0-9]|2[0-3]):[0-5][0-9]:[0-5][0-9][+-](0[0-9]|1[0-2]):00")?,
+        "timestamptz" => date_time("%Y-%m-%dT%H:%M:%S%z", ChronoValueType::DateTime),
+        "timestamp" => date_time("%Y-%m-%dT%H:%M:%S", ChronoValueType::NaiveDateTime),
+        "date" => date_time("%Y-%m-%d", ChronoValueType::NaiveDate),
+        "uuid" => Content::String(StringContent::Uuid(Uuid)),
+        array if array.starts_with('_') => Content::Array(ArrayContent {
+            length: Box::new(Content::Number(NumberContent::U64(U64::Range(
+                RangeStep::new(0, 5, 1),
+            )))),
+            content: Box::new(decode_type_to_content(
+                &data_type[1..],
+                char_max_len,
+                numeric_precision_scale,
+                enum_labels,
+            )?),
+        }),
+        _ => {
+            let labels = enum_labels(data_type)?;
+            if labels.is_empty() {
+                bail!("We haven't implemented a converter for {}", data_type)
+            }
+
+            let mut categorical = Categorical::default();
+            for label in labels {
+                categorical.push(label);
+            }
+            Content::String(StringContent::Categorical(categorical))
+        }
+    };
+
+    Ok(content)
+}
+
+/// The values a column of the given type accepts. Array types are named after their element type
+/// prefixed with an underscore.
+fn data_type_kind(data_type: &str, char_max_len: Option<i32>) -> ColumnKind {
+    match data_type {
+        "bool" => ColumnKind::Bool,
+        "int2" => ColumnKind::signed(16),
+        "int4" => ColumnKind::signed(32),
+        "int8" => ColumnKind::signed(64),
+        "float4" | "float8" | "numeric" => ColumnKind::Float,
+        "char" | "varchar" | "bpchar" => {
+            ColumnKind::Text(char_max_len.map(|char_max_len| char_max_len as usize))
+        }
+        "text" | "citext" | "name" | "uuid" => ColumnKind::Text(None),
+        "date" | "time" | "timestamp" | "timestamptz" => ColumnKind::DateTime,
+        _ if data_type.starts_with('_') => {
+            ColumnKind::Array(Box::new(data_type_kind(&data_type[1..], None)))
+        }
+        _ => ColumnKind::Any,
+    }
+}
+
 // until https://github.com/rust-lang/rust-clippy/pull/7865 gets into nightly
 #[allow(clippy::match_str_case_mismatch)]
 fn try_match_value(row: &PgRow, column: &PgColumn) -> Result<Value> {
@@ -370,19 +800,425 @@ fn try_match_value(row: &PgRow, column: &PgColumn) -> Result<Value> {
 
             bail!("Failed to convert Postgresql numeric data type to 64 bit float")
         }
-        "timestampz" => Value::String(row.try_get::<String, &str>(column.name())?),
-        "timestamp" => Value::String(row.try_get::<String, &str>(column.name())?),
+        "json" | "jsonb" => {
+            json_val_to_synth(row.try_get::<serde_json::Value, &str>(column.name())?)
+        }
+        "interval" => {
+            let interval = row.try_get::<PgInterval, &str>(column.name())?;
+            Value::String(format!(
+                "{} months {} days {} microseconds",
+                interval.months, interval.days, interval.microseconds
+            ))
+        }
+        "inet" | "cidr" => Value::String(decode_inet(raw_bytes(row, column)?)?),
+        "bytea" => Value::String(encode_bytea(&row.try_get::<Vec<u8>, &str>(column.name())?)),
+        "uuid" => Value::String(decode_uuid(raw_bytes(row, column)?)?),
+        "time" => Value::String(
+            row.try_get::<NaiveTime, &str>(column.name())?
+                .format("%H:%M:%S")
+                .to_string(),
+        ),
+        "timetz" => {
+            let time_tz = row.try_get::<PgTimeTz<NaiveTime, FixedOffset>, &str>(column.name())?;
+            Value::String(format!(
+                "{}{}",
+                time_tz.time.format("%H:%M:%S"),
+                time_tz.offset
+            ))
+        }
+        "timestamptz" => Value::String(
+            row.try_get::<DateTime<Utc>, &str>(column.name())?
+                .format("%Y-%m-%dT%H:%M:%S%z")
+                .to_string(),
+        ),
+        "timestamp" => Value::String(
+            row.try_get::<NaiveDateTime, &str>(column.name())?
+                .format("%Y-%m-%dT%H:%M:%S")
+                .to_string(),
+        ),
         "date" => Value::String(format!(
             "{}",
             row.try_get::<chrono::NaiveDate, &str>(column.name())?
         )),
-        _ => {
-            bail!(
-                "Could not convert value. Converter not implemented for {}",
-                column.type_info().name()
-            );
-        }
+        array if array.ends_with("[]") => try_match_array(row, column, &array[..array.len() - 2])?,
+        // Arrays of enums are named after the enum, prefixed with an underscore
+        array if array.starts_with('_') => array_value(row, column, Value::String)?,
+        // Enums are the only other types we generate content for
+        _ => Value::String(row.try_get_unchecked::<String, &str>(column.name())?),
     };
 
     Ok(value)
 }
+
+// until https://github.com/rust-lang/rust-clippy/pull/7865 gets into nightly
+#[allow(clippy::match_str_case_mismatch)]
+fn try_match_array(row: &PgRow, column: &PgColumn, element_type: &str) -> Result<Value> {
+    match element_type {
+        "bool" => array_value(row, column, Value::Bool),
+        "char" | "varchar" | "text" | "citext" | "bpchar" | "name" => {
+            array_value(row, column, Value::String)
+        }
+        "int2" => array_value(row, column, |i: i16| Value::Number(i.into())),
+        "int4" => array_value(row, column, |i: i32| Value::Number(i.into())),
+        "int8" => array_value(row, column, |i: i64| Value::Number(i.into())),
+        "float4" => array_value(row, column, |f: f32| Value::Number(f.into())),
+        "float8" => array_value(row, column, |f: f64| Value::Number(f.into())),
+        "numeric" => array_value(row, column, |d: Decimal| {
+            d.to_f64()
+                .map(|f| Value::Number(f.into()))
+                .unwrap_or(Value::Null(()))
+        }),
+        "date" => array_value(row, column, |d: chrono::NaiveDate| {
+            Value::String(d.to_string())
+        }),
+        _ => bail!(
+            "Could not convert value. Converter not implemented for {}",
+            column.type_info().name()
+        ),
+    }
+}
+
+/// Decodes a one dimensional array. Null elements are left out, as the content of array elements
+/// is never nullable.
+fn array_value<T>(row: &PgRow, column: &PgColumn, to_value: impl Fn(T) -> Value) -> Result<Value>
+where
+    T: for<'r> Decode<'r, Postgres> + Type<Postgres>,
+{
+    let elements = row.try_get_unchecked::<Vec<Option<T>>, &str>(column.name())?;
+
+    Ok(Value::Array(
+        elements
+            .into_iter()
+            .flatten()
+            .map(to_value)
+            .filter(|value| !value.is_null())
+            .collect(),
+    ))
+}
+
+/// Returns the value of a column in the binary format, for types sqlx can't decode.
+fn raw_bytes<'r>(row: &'r PgRow, column: &PgColumn) -> Result<&'r [u8]> {
+    Ok(row.try_get_unchecked::<&[u8], &str>(column.name())?)
+}
+
+/// Decodes the binary format of `inet` and `cidr` values: the address family, the number of
+/// network bits, whether it is a `cidr`, the length of the address and the address itself.
+fn decode_inet(bytes: &[u8]) -> Result<String> {
+    let (bits, is_cidr, address) = match bytes {
+        [2, bits, is_cidr, 4, address @ ..] if address.len() == 4 => {
+            let octets: [u8; 4] = address.try_into()?;
+            (*bits, *is_cidr != 0, IpAddr::from(octets))
+        }
+        [3, bits, is_cidr, 16, address @ ..] if address.len() == 16 => {
+            let octets: [u8; 16] = address.try_into()?;
+            (*bits, *is_cidr != 0, IpAddr::from(octets))
+        }
+        _ => bail!("Invalid inet value {:?}", bytes),
+    };
+
+    let max_bits = if address.is_ipv4() { 32 } else { 128 };
+    if is_cidr || bits != max_bits {
+        Ok(format!("{}/{}", address, bits))
+    } else {
+        Ok(address.to_string())
+    }
+}
+
+fn decode_uuid(bytes: &[u8]) -> Result<String> {
+    if bytes.len() != 16 {
+        bail!("Invalid uuid value {:?}", bytes)
+    }
+
+    let hex = encode_hex(bytes);
+    Ok(format!(
+        "{}-{}-{}-{}-{}",
+        &hex[0..8],
+        &hex[8..12],
+        &hex[12..16],
+        &hex[16..20],
+        &hex[20..32]
+    ))
+}
+
+/// Encodes binary data in the hex format of `bytea` values.
+fn encode_bytea(bytes: &[u8]) -> String {
+    format!("\\x{}", encode_hex(bytes))
+}
+
+fn encode_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+/// Encodes a collection in the text format of `COPY ... FROM STDIN`: one line per row, with
+/// columns separated by tabs and `\N` for nulls.
+pub(crate) fn encode_copy_text(collection: &[Value]) -> Result<String> {
+    let mut out = String::new();
+
+    for row in collection {
+        let row_obj = row
+            .as_object()
+            .expect("This is always an object (sampler contract)");
+
+        for (i, value) in row_obj.values().enumerate() {
+            if i != 0 {
+                out.push('\t');
+            }
+            match value {
+                Value::Null(_) => out.push_str("\\N"),
+                value => escape_copy_text(&mut out, &to_postgres_text(value)?),
+            }
+        }
+        out.push('\n');
+    }
+
+    Ok(out)
+}
+
+fn escape_copy_text(out: &mut String, text: &str) {
+    for c in text.chars() {
+        match c {
+            '\\' => out.push_str("\\\\"),
+            '\t' => out.push_str("\\t"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            c => out.push(c),
+        }
+    }
+}
+
+/// Converts a non-null value to its Postgres text representation, as accepted by the input
+/// function of the column type.
+pub(crate) fn to_postgres_text(value: &Value) -> Result<String> {
+    let text = match value {
+        Value::Null(_) => bail!("null does not have a text representation"),
+        Value::Bool(b) => if *b { "t" } else { "f" }.to_string(),
+        Value::Number(Number::F32(f)) => float_to_postgres_text(**f as f64),
+        Value::Number(Number::F64(f)) => float_to_postgres_text(**f),
+        Value::Number(n) => n.to_string(),
+        Value::String(s) => {
+            if s.contains('\0') {
+                bail!("Postgres text values cannot contain null bytes")
+            }
+            s.clone()
+        }
+        Value::DateTime(ChronoValueAndFormat { value, .. }) => match value {
+            ChronoValue::NaiveDate(nd) => nd.format("%Y-%m-%d").to_string(),
+            ChronoValue::NaiveTime(nt) => nt.format("%H:%M:%S%.f").to_string(),
+            ChronoValue::NaiveDateTime(ndt) => ndt.format("%Y-%m-%d %H:%M:%S%.f").to_string(),
+            ChronoValue::DateTime(dt) => dt.format("%Y-%m-%d %H:%M:%S%.f%:z").to_string(),
+        },
+        Value::Object(_) => synth_core::graph::json::synth_val_to_json(value.clone()).to_string(),
+        Value::Array(arr) => {
+            let elements = arr
+                .iter()
+                .map(|elem| match elem {
+                    Value::Null(_) => Ok("NULL".to_string()),
+                    Value::Array(_) => to_postgres_text(elem),
+                    elem => Ok(format!(
+                        "\"{}\"",
+                        to_postgres_text(elem)?
+                            .replace('\\', "\\\\")
+                            .replace('"', "\\\"")
+                    )),
+                })
+                .collect::<Result<Vec<_>>>()?;
+            format!("{{{}}}", elements.join(","))
+        }
+    };
+
+    Ok(text)
+}
+
+/// Converts a value to a Postgres literal which can be used in a SQL statement. Apart from
+/// nulls, booleans and finite numbers, values are written as string literals which Postgres
+/// coerces to the column type. Assumes `standard_conforming_strings` is on.
+pub(crate) fn to_postgres_literal(value: &Value) -> Result<String> {
+    let literal = match value {
+        Value::Null(_) => "NULL".to_string(),
+        Value::Bool(b) => if *b { "TRUE" } else { "FALSE" }.to_string(),
+        Value::Number(Number::F32(f)) if f.is_finite() => f.to_string(),
+        Value::Number(Number::F64(f)) if f.is_finite() => f.to_string(),
+        Value::Number(Number::F32(_)) | Value::Number(Number::F64(_)) => {
+            format!("'{}'", to_postgres_text(value)?)
+        }
+        Value::Number(n) => n.to_string(),
+        value => format!("'{}'", to_postgres_text(value)?.replace('\'', "''")),
+    };
+
+    Ok(literal)
+}
+
+fn float_to_postgres_text(f: f64) -> String {
+    if f.is_nan() {
+        "NaN".to_string()
+    } else if f.is_infinite() && f.is_sign_positive() {
+        "Infinity".to_string()
+    } else if f.is_infinite() {
+        "-Infinity".to_string()
+    } else {
+        f.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn row(values: Vec<(&str, Value)>) -> Value {
+        Value::Object(
+            values
+                .into_iter()
+                .map(|(k, v)| (k.to_string(), v))
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn test_to_postgres_literal() {
+        assert_eq!(to_postgres_literal(&Value::Null(())).unwrap(), "NULL");
+        assert_eq!(to_postgres_literal(&Value::Bool(false)).unwrap(), "FALSE");
+        assert_eq!(
+            to_postgres_literal(&Value::Number(f64::INFINITY.into())).unwrap(),
+            "'Infinity'"
+        );
+        assert_eq!(
+            to_postgres_literal(&Value::String("it's a \\ slash".to_string())).unwrap(),
+            "'it''s a \\ slash'"
+        );
+        assert_eq!(
+            to_postgres_literal(&Value::Array(vec![Value::String("a'b".to_string())])).unwrap(),
+            "'{\"a''b\"}'"
+        );
+    }
+
+    #[test]
+    fn test_encode_copy_text() {
+        let collection = vec![
+            row(vec![
+                ("a", Value::Number(1i32.into())),
+                ("b", Value::String("tab\there\nand \\ slash".to_string())),
+                ("c", Value::Null(())),
+            ]),
+            row(vec![
+                ("a", Value::Number(2i32.into())),
+                ("b", Value::Bool(true)),
+                ("c", Value::Number(f64::NAN.into())),
+            ]),
+        ];
+
+        assert_eq!(
+            encode_copy_text(&collection).unwrap(),
+            "1\ttab\\there\\nand \\\\ slash\t\\N\n2\tt\tNaN\n"
+        );
+    }
+
+    #[test]
+    fn test_to_postgres_text_array() {
+        let array = Value::Array(vec![
+            Value::String("with \"quotes\"".to_string()),
+            Value::Null(()),
+            Value::Array(vec![Value::Number(1i64.into())]),
+        ]);
+
+        assert_eq!(
+            to_postgres_text(&array).unwrap(),
+            "{\"with \\\"quotes\\\"\",NULL,{\"1\"}}"
+        );
+    }
+
+    #[test]
+    fn test_decode_type_to_content() {
+        let enum_labels = |type_name: &str| -> Result<Vec<String>> {
+            match type_name {
+                "mood" => Ok(vec!["sad".to_string(), "happy".to_string()]),
+                _ => Ok(Vec::new()),
+            }
+        };
+        let decode = |data_type: &str, numeric_precision_scale: Option<(i32, i32)>| {
+            decode_type_to_content(data_type, None, numeric_precision_scale, &enum_labels)
+        };
+
+        let mut categorical = Categorical::default();
+        categorical.push("sad".to_string());
+        categorical.push("happy".to_string());
+        assert_eq!(
+            decode("_mood", None).unwrap(),
+            Content::Array(ArrayContent {
+                length: Box::new(Content::Number(NumberContent::U64(U64::Range(
+                    RangeStep::new(0, 5, 1)
+                )))),
+                content: Box::new(Content::String(StringContent::Categorical(categorical))),
+            })
+        );
+
+        assert_eq!(
+            decode("numeric", Some((3, 1))).unwrap(),
+            Content::Number(NumberContent::F64(F64::Range(RangeStep::new(
+                -99.9, 99.9, 0.1
+            ))))
+        );
+
+        assert_eq!(
+            decode("jsonb", None).unwrap(),
+            Content::Object(ObjectContent::default())
+        );
+        assert!(decode("point", None).is_err());
+    }
+
+    #[test]
+    fn test_extend_parameterised_query() {
+        let column_info = ColumnInfo {
+            column_name: "moods".to_string(),
+            ordinal_position: 1,
+            is_nullable: false,
+            data_type: "_mood".to_string(),
+            character_maximum_length: None,
+            numeric_precision: None,
+            numeric_scale: None,
+            column_type: None,
+            type_schema: None,
+        };
+
+        let mut query = String::new();
+        PostgresDataSource::extend_parameterised_query(&mut query, 2, &[Some(&column_info), None]);
+
+        assert_eq!(query, "(CAST(CAST($3 AS text) AS \"_mood\"),$4)");
+    }
+
+    #[test]
+    fn test_decode_inet() {
+        assert_eq!(
+            decode_inet(&[2, 32, 0, 4, 192, 168, 0, 1]).unwrap(),
+            "192.168.0.1"
+        );
+        assert_eq!(
+            decode_inet(&[2, 24, 1, 4, 10, 0, 0, 0]).unwrap(),
+            "10.0.0.0/24"
+        );
+
+        let mut ipv6 = vec![3, 128, 0, 16];
+        ipv6.extend([0; 15]);
+        ipv6.push(1);
+        assert_eq!(decode_inet(&ipv6).unwrap(), "::1");
+
+        assert!(decode_inet(&[2, 32, 0, 4, 192]).is_err());
+    }
+
+    #[test]
+    fn test_decode_uuid() {
+        let bytes: Vec<u8> = (0..16).collect();
+
+        assert_eq!(
+            decode_uuid(&bytes).unwrap(),
+            "00010203-0405-0607-0809-0a0b0c0d0e0f"
+        );
+        assert_eq!(encode_bytea(&[1, 2, 255]), "\\x0102ff");
+    }
+
+    #[test]
+    fn test_encode_copy_text_null_byte() {
+        let collection = vec![row(vec![("a", Value::String("\0".to_string()))])];
+
+        assert!(encode_copy_text(&collection).is_err());
+    }
+}
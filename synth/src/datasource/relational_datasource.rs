@@ -1,12 +1,22 @@
-use crate::datasource::DataSource;
+use crate::datasource::ddl::{tables_from_namespace, SqlDialect};
+use crate::datasource::type_check::{check_collection, ColumnKind};
+use crate::datasource::{DataSource, ExportMode, LoadParams, SampleParams};
 use anyhow::Result;
 use async_trait::async_trait;
 use beau_collector::BeauCollector;
-use futures::future::join_all;
-use synth_core::{Content, Value};
-use synth_gen::value::Number;
+use futures::stream::{self, StreamExt};
+use std::str::FromStr;
+use synth_core::{Content, Name, Namespace, Value};
 
-const DEFAULT_INSERT_BATCH_SIZE: usize = 1000;
+/// Both Postgres and MySQL limit the number of bind parameters of a single statement to 65535.
+const MAX_BIND_PARAMETERS: usize = 65535;
+
+/// The number of rows of a table with `columns` columns inserted by a single `INSERT` statement.
+/// Wide tables are inserted in smaller batches than the batch size to stay below the parameter
+/// limit.
+pub(crate) fn insert_batch_size(load: &LoadParams, columns: usize) -> usize {
+    load.batch_size().min(MAX_BIND_PARAMETERS / columns.max(1))
+}
 
 //TODO: Remove this once https://github.com/rust-lang/rust/issues/88900 gets fixed
 #[allow(dead_code)]
@@ -17,6 +27,14 @@ pub struct ColumnInfo {
     pub(crate) is_nullable: bool,
     pub(crate) data_type: String,
     pub(crate) character_maximum_length: Option<i32>,
+    pub(crate) numeric_precision: Option<i32>,
+    pub(crate) numeric_scale: Option<i32>,
+    /// The type of the column including its parameters, e.g. `enum('a','b')`. Only available for
+    /// MySql.
+    pub(crate) column_type: Option<String>,
+    /// The schema in which the type of the column is defined, e.g. for enums. Only available for
+    /// Postgres.
+    pub(crate) type_schema: Option<String>,
 }
 
 #[allow(dead_code)]
@@ -26,7 +44,7 @@ pub struct PrimaryKey {
     pub(crate) type_name: String,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct ForeignKey {
     pub(crate) from_table: String,
     pub(crate) from_column: String,
@@ -45,13 +63,17 @@ pub struct ValueWrapper(pub(crate) Value);
 pub trait RelationalDataSource: DataSource {
     type QueryResult: Send + Sync;
 
+    const DIALECT: SqlDialect;
+
+    /// The settings tuning the load put on the database.
+    fn load_params(&self) -> &LoadParams;
+
     async fn insert_relational_data(
         &self,
         collection_name: &str,
         collection: &[Value],
+        mode: ExportMode,
     ) -> Result<()> {
-        let batch_size = DEFAULT_INSERT_BATCH_SIZE;
-
         if collection.is_empty() {
             println!(
                 "Collection {} generated 0 values. Skipping insertion...",
@@ -65,36 +87,44 @@ pub trait RelationalDataSource: DataSource {
             .as_object()
             .expect("This is always an object (sampler contract)");
 
-        for column_info in column_infos {
-            if let Some(value) = first_valueset.get(&column_info.column_name) {
-                match (value, &*column_info.data_type) {
-                    (
-                        Value::Number(Number::U64(_)),
-                        "int2" | "int4" | "int8" | "int" | "integer" | "smallint" | "bigint",
-                    ) => warn!(
-                        "Trying to put an unsigned u64 into a {} typed column {}.{}",
-                        column_info.data_type, collection_name, column_info.column_name
-                    ),
-                    (
-                        Value::Number(Number::U32(_)),
-                        "int2" | "int4" | "int8" | "int" | "integer" | "smallint" | "bigint",
-                    ) => warn!(
-                        "Trying to put an unsigned u32 into a {} typed column {}.{}",
-                        column_info.data_type, collection_name, column_info.column_name
-                    ),
-                    //TODO: More variants
-                    _ => {}
+        let batch_size = insert_batch_size(self.load_params(), first_valueset.len());
+
+        let column_names = first_valueset.keys().cloned().collect::<Vec<String>>();
+
+        let conflict_clause = match mode {
+            ExportMode::Append | ExportMode::Truncate | ExportMode::Drop => String::new(),
+            ExportMode::Replace | ExportMode::Upsert => {
+                let primary_keys = self
+                    .get_primary_keys(collection_name)
+                    .await?
+                    .into_iter()
+                    .map(|primary_key| primary_key.column_name)
+                    .collect::<Vec<String>>();
+
+                if primary_keys.is_empty() {
+                    bail!(
+                        "Cannot {} into table {} as it does not have a primary key",
+                        mode,
+                        collection_name
+                    );
                 }
+
+                Self::conflict_clause(mode, &primary_keys, &column_names, &column_infos)
             }
-        }
+        };
 
-        let column_names = first_valueset
-            .keys()
-            .cloned()
-            .collect::<Vec<String>>()
-            .join(",");
+        let column_infos: Vec<Option<&ColumnInfo>> = column_names
+            .iter()
+            .map(|column_name| {
+                column_infos
+                    .iter()
+                    .find(|column_info| &column_info.column_name == column_name)
+            })
+            .collect();
 
-        let mut futures = Vec::with_capacity(collection.len());
+        let column_names = column_names.join(",");
+
+        let mut queries = Vec::with_capacity(collection.len() / batch_size + 1);
 
         for rows in collection.chunks(batch_size) {
             let mut query = format!(
@@ -110,22 +140,31 @@ pub trait RelationalDataSource: DataSource {
                     .as_object()
                     .expect("This is always an object (sampler contract)");
 
-                let extend = row_obj.values().len();
-                Self::extend_parameterised_query(&mut query, curr_index, extend);
-                curr_index += extend;
-                query_params.extend(row_obj.values());
+                Self::extend_parameterised_query(&mut query, curr_index, &column_infos);
+                curr_index += column_infos.len();
+                for (value, column_info) in row_obj.values().zip(column_infos.iter()) {
+                    query_params.push(Self::encode_parameter(value, *column_info)?);
+                }
 
                 if i == rows.len() - 1 {
+                    query.push_str(&conflict_clause);
                     query.push_str(";\n");
                 } else {
                     query.push_str(",\n");
                 }
             }
-            let future = self.execute_query(query, query_params);
-            futures.push(future);
+            queries.push((query, query_params));
         }
 
-        let results = join_all(futures).await;
+        let futures = queries.into_iter().map(|(query, query_params)| async move {
+            self.execute_query(query, query_params.iter().collect())
+                .await
+        });
+
+        let results: Vec<_> = stream::iter(futures)
+            .buffer_unordered(self.load_params().concurrency())
+            .collect()
+            .await;
 
         if let Err(e) = results.into_iter().bcollect::<Vec<Self::QueryResult>>() {
             bail!("One or more database inserts failed: {:?}", e)
@@ -135,6 +174,77 @@ pub trait RelationalDataSource: DataSource {
         Ok(())
     }
 
+    /// Checks the generated rows of every table against the columns of the table, and fails with
+    /// a report of all the values which would be refused before anything is inserted.
+    async fn check_relational_data(
+        &self,
+        namespace: &Namespace,
+        collections: &[(&str, &[Value])],
+    ) -> Result<()> {
+        let mut reports = Vec::new();
+
+        for (table_name, collection) in collections {
+            let column_infos = self.get_columns_infos(table_name).await?;
+            if column_infos.is_empty() {
+                reports.push(format!("Table {} does not exist", table_name));
+                continue;
+            }
+
+            let content = Name::from_str(table_name)
+                .ok()
+                .and_then(|name| namespace.get_collection(&name).ok());
+
+            if let Err(e) = check_collection(
+                table_name,
+                collection,
+                content,
+                &column_infos,
+                Self::column_kind,
+            ) {
+                reports.push(e.to_string());
+            }
+        }
+
+        if !reports.is_empty() {
+            bail!("{}", reports.join("\n"));
+        }
+
+        Ok(())
+    }
+
+    /// Deletes all rows of the given tables. Tables are cleared in reverse foreign key order so
+    /// that rows are never deleted while still being referenced.
+    async fn clear_relational_data(&self, table_names: &[String]) -> Result<()> {
+        let foreign_keys = self.get_foreign_keys().await?;
+
+        for table_name in reverse_dependency_order(table_names, &foreign_keys) {
+            self.execute_query(format!("DELETE FROM {}", table_name), vec![])
+                .await?;
+            info!("Deleted existing rows from {}...", table_name);
+        }
+
+        Ok(())
+    }
+
+    /// Creates the tables derived from the given collections of the namespace, referenced tables
+    /// first. Tables which already exist are left untouched.
+    async fn create_relational_tables(
+        &self,
+        namespace: &Namespace,
+        table_names: &[String],
+    ) -> Result<()> {
+        for table in tables_from_namespace(namespace)?
+            .iter()
+            .filter(|table| table_names.contains(&table.name))
+        {
+            self.execute_query(table.create_statement(Self::DIALECT), vec![])
+                .await?;
+            info!("Created table {}...", table.name);
+        }
+
+        Ok(())
+    }
+
     async fn execute_query(
         &self,
         query: String,
@@ -143,18 +253,182 @@ pub trait RelationalDataSource: DataSource {
 
     async fn get_table_names(&self) -> Result<Vec<String>>;
 
+    /// Fetches the rows of the given table which match the condition, with its parameters.
+    async fn get_rows(
+        &self,
+        table_name: &str,
+        condition: &str,
+        query_params: Vec<&Value>,
+    ) -> Result<Vec<Value>>;
+
+    /// Fetches the rows of the given table whose value for the given column is one of `values`.
+    async fn get_rows_in(
+        &self,
+        table_name: &str,
+        column_info: &ColumnInfo,
+        values: &[Value],
+    ) -> Result<Vec<Value>> {
+        let mut rows = Vec::new();
+
+        for values in values.chunks(self.load_params().batch_size()) {
+            let mut condition = format!("{} IN ", column_info.column_name);
+            Self::extend_parameterised_query(
+                &mut condition,
+                0,
+                &vec![Some(column_info); values.len()],
+            );
+
+            let query_params = values
+                .iter()
+                .map(|value| Self::encode_parameter(value, Some(column_info)))
+                .collect::<Result<Vec<Value>>>()?;
+
+            rows.extend(
+                self.get_rows(table_name, &condition, query_params.iter().collect())
+                    .await?,
+            );
+        }
+
+        Ok(rows)
+    }
+
     async fn get_columns_infos(&self, table_name: &str) -> Result<Vec<ColumnInfo>>;
 
     async fn get_primary_keys(&self, table_name: &str) -> Result<Vec<PrimaryKey>>;
 
     async fn get_foreign_keys(&self) -> Result<Vec<ForeignKey>>;
 
-    async fn set_seed(&self) -> Result<()>;
+    /// Seeds the random sampling of [get_deterministic_samples].
+    async fn set_seed(&self, seed: u64) -> Result<()>;
+
+    /// Samples rows of the given table with the given strategy, such that the same seed samples
+    /// the same rows of unchanged tables.
+    async fn get_deterministic_samples(
+        &self,
+        table_name: &str,
+        sample: &SampleParams,
+    ) -> Result<Vec<Value>>;
+
+    fn decode_to_content(&self, column_info: &ColumnInfo) -> Result<Content>;
+
+    /// The values the given column accepts.
+    fn column_kind(column_info: &ColumnInfo) -> ColumnKind;
+
+    /// Appends the parameters of a single row to an `INSERT` statement, starting after
+    /// `curr_index`. `column_infos` are the columns of the row, if they exist in the table.
+    fn extend_parameterised_query(
+        query: &mut String,
+        curr_index: usize,
+        column_infos: &[Option<&ColumnInfo>],
+    );
+
+    /// Converts a value before it is bound as a parameter for the given column.
+    fn encode_parameter(value: &Value, _column_info: Option<&ColumnInfo>) -> Result<Value> {
+        Ok(value.clone())
+    }
+
+    /// The clause appended to an `INSERT` statement to resolve conflicts on the primary keys
+    /// in the given mode. Only called for [ExportMode::Replace] and [ExportMode::Upsert].
+    fn conflict_clause(
+        mode: ExportMode,
+        primary_keys: &[String],
+        column_names: &[String],
+        column_infos: &[ColumnInfo],
+    ) -> String;
+}
 
-    async fn get_deterministic_samples(&self, table_name: &str) -> Result<Vec<Value>>;
+/// Orders tables such that every table comes before the tables it references. Tables which are
+/// part of a reference cycle are put last, in their original order.
+pub(crate) fn reverse_dependency_order<'a>(
+    table_names: &'a [String],
+    foreign_keys: &[ForeignKey],
+) -> Vec<&'a String> {
+    let mut remaining: Vec<&String> = table_names.iter().collect();
+    let mut ordered = Vec::with_capacity(table_names.len());
 
-    fn decode_to_content(&self, data_type: &str, _char_max_len: Option<i32>) -> Result<Content>;
+    while !remaining.is_empty() {
+        let is_referenced = |table_name: &String, remaining: &[&String]| {
+            foreign_keys.iter().any(|fk| {
+                &fk.to_table == table_name
+                    && fk.from_table != fk.to_table
+                    && remaining.contains(&&fk.from_table)
+            })
+        };
 
-    // Returns extended query string + current index
-    fn extend_parameterised_query(query: &mut String, curr_index: usize, extend: usize);
+        let (unreferenced, referenced): (Vec<&String>, Vec<&String>) = remaining
+            .iter()
+            .partition(|table_name| !is_referenced(table_name, &remaining));
+
+        if unreferenced.is_empty() {
+            warn!(
+                "Found a reference cycle between tables {:?}. They will be processed in an arbitrary order.",
+                referenced
+            );
+            ordered.extend(referenced);
+            break;
+        }
+
+        ordered.extend(unreferenced);
+        remaining = referenced;
+    }
+
+    ordered
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fk(from_table: &str, to_table: &str) -> ForeignKey {
+        ForeignKey {
+            from_table: from_table.to_string(),
+            from_column: "id".to_string(),
+            to_table: to_table.to_string(),
+            to_column: "id".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_insert_batch_size() {
+        let load = LoadParams::default();
+        assert_eq!(insert_batch_size(&load, 10), LoadParams::DEFAULT_BATCH_SIZE);
+        assert_eq!(insert_batch_size(&load, 100), 655);
+
+        let load = LoadParams {
+            batch_size: Some(50),
+            ..Default::default()
+        };
+        assert_eq!(insert_batch_size(&load, 10), 50);
+        assert_eq!(insert_batch_size(&load, 0), 50);
+    }
+
+    #[test]
+    fn test_reverse_dependency_order() {
+        let table_names = vec![
+            "hospitals".to_string(),
+            "doctors".to_string(),
+            "patients".to_string(),
+        ];
+        let foreign_keys = vec![
+            fk("doctors", "hospitals"),
+            fk("patients", "doctors"),
+            fk("patients", "patients"),
+        ];
+
+        assert_eq!(
+            reverse_dependency_order(&table_names, &foreign_keys),
+            vec!["patients", "doctors", "hospitals"]
+        );
+    }
+
+    #[test]
+    fn test_reverse_dependency_order_cycle() {
+        let table_names = vec!["a".to_string(), "b".to_string(), "c".to_string()];
+        let foreign_keys = vec![fk("a", "b"), fk("b", "a")];
+
+        assert_eq!(
+            reverse_dependency_order(&table_names, &foreign_keys),
+            vec!["c", "a", "b"]
+        );
+    }
 }
@@ -0,0 +1,304 @@
+use crate::datasource::relational_datasource::{
+    reverse_dependency_order, ColumnInfo, ForeignKey, RelationalDataSource,
+};
+use anyhow::Result;
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use synth_core::Value;
+
+/// The rows of a table collected into a subset.
+struct SubsetTable {
+    column_infos: Vec<ColumnInfo>,
+    primary_keys: Vec<String>,
+    rows: Vec<Value>,
+    /// The key of every collected row, and whether the rows referencing it have been collected
+    seen: HashMap<Value, bool>,
+}
+
+impl SubsetTable {
+    fn key(&self, row: &Value) -> Value {
+        if self.primary_keys.is_empty() {
+            return row.clone();
+        }
+
+        let row = row
+            .as_object()
+            .expect("This is always an object (sampler contract)");
+        Value::Array(
+            self.primary_keys
+                .iter()
+                .map(|primary_key| row.get(primary_key).cloned().unwrap_or(Value::Null(())))
+                .collect(),
+        )
+    }
+
+    /// Adds the rows which were not collected yet, and returns the rows which have to be
+    /// followed. Rows which were only collected as referenced rows are followed again when
+    /// `follow_referencing` is set.
+    fn add(&mut self, rows: Vec<Value>, follow_referencing: bool) -> Vec<Value> {
+        let mut to_follow = Vec::new();
+
+        for row in rows {
+            let key = self.key(&row);
+            match self.seen.get_mut(&key) {
+                None => {
+                    self.seen.insert(key, follow_referencing);
+                    self.rows.push(row.clone());
+                    to_follow.push(row);
+                }
+                Some(followed) if follow_referencing && !*followed => {
+                    *followed = true;
+                    to_follow.push(row);
+                }
+                Some(_) => {}
+            }
+        }
+
+        to_follow
+    }
+}
+
+/// Collects a referentially intact subset of a database, starting from the rows of the `root`
+/// table which match `condition`. Foreign keys are followed in both directions: the rows
+/// referenced by collected rows are always collected, while the rows referencing collected rows
+/// are only collected for the root rows and the rows collected through them. Following both
+/// directions from every row would otherwise pull in most of the database.
+///
+/// The collected tables are returned in the order in which they can be inserted, referenced
+/// tables first.
+pub(crate) async fn collect_subset<T: RelationalDataSource + Sync>(
+    datasource: &T,
+    root: &str,
+    condition: &str,
+) -> Result<Vec<(String, Vec<Value>)>> {
+    let foreign_keys = datasource.get_foreign_keys().await?;
+    let mut tables: BTreeMap<String, SubsetTable> = BTreeMap::new();
+    let mut queue: VecDeque<(String, Vec<Value>, bool)> = VecDeque::new();
+
+    add_table(datasource, &mut tables, root).await?;
+    let root_rows = datasource.get_rows(root, condition, vec![]).await?;
+    info!("Found {} rows in {}...", root_rows.len(), root);
+    let to_follow = tables.get_mut(root).unwrap().add(root_rows, true);
+    queue.push_back((root.to_string(), to_follow, true));
+
+    while let Some((table_name, rows, follow_referencing)) = queue.pop_front() {
+        if rows.is_empty() {
+            continue;
+        }
+
+        // Referenced rows are always needed for the subset to be intact
+        let referenced = foreign_keys
+            .iter()
+            .filter(|fk| fk.from_table == table_name)
+            .map(|fk| (&fk.from_column, &fk.to_table, &fk.to_column, false));
+        let referencing = foreign_keys
+            .iter()
+            .filter(|fk| fk.to_table == table_name && follow_referencing)
+            .map(|fk| (&fk.to_column, &fk.from_table, &fk.from_column, true));
+
+        for (column, target, target_column, follow) in referenced.chain(referencing) {
+            let values = distinct_values(&rows, column);
+            if values.is_empty() {
+                continue;
+            }
+
+            add_table(datasource, &mut tables, target).await?;
+            let target_table = tables.get_mut(target).unwrap();
+            let column_info = target_table
+                .column_infos
+                .iter()
+                .find(|column_info| &column_info.column_name == target_column)
+                .ok_or_else(|| anyhow!("Column {}.{} does not exist", target, target_column))?;
+
+            let found = datasource.get_rows_in(target, column_info, &values).await?;
+            let to_follow = target_table.add(found, follow);
+            queue.push_back((target.clone(), to_follow, follow));
+        }
+    }
+
+    let tables = tables
+        .into_iter()
+        .map(|(table_name, table)| (table_name, table.rows))
+        .collect();
+
+    Ok(insert_order(tables, &foreign_keys))
+}
+
+/// Orders the rows of the given tables such that they can be inserted, referenced tables and
+/// rows first.
+pub(crate) fn insert_order(
+    mut tables: BTreeMap<String, Vec<Value>>,
+    foreign_keys: &[ForeignKey],
+) -> Vec<(String, Vec<Value>)> {
+    let table_names: Vec<String> = tables.keys().cloned().collect();
+    let mut order = reverse_dependency_order(&table_names, foreign_keys);
+    order.reverse();
+
+    order
+        .into_iter()
+        .map(|table_name| {
+            let rows = tables.remove(table_name).unwrap_or_default();
+            let rows = order_self_references(rows, table_name, foreign_keys);
+            info!("Collected {} rows from {}...", rows.len(), table_name);
+            (table_name.clone(), rows)
+        })
+        .collect()
+}
+
+/// Adds the table to the subset, if it isn't part of it yet.
+async fn add_table<T: RelationalDataSource + Sync>(
+    datasource: &T,
+    tables: &mut BTreeMap<String, SubsetTable>,
+    table_name: &str,
+) -> Result<()> {
+    if !tables.contains_key(table_name) {
+        let column_infos = datasource.get_columns_infos(table_name).await?;
+        if column_infos.is_empty() {
+            bail!("Table {} does not exist", table_name);
+        }
+
+        let primary_keys = datasource
+            .get_primary_keys(table_name)
+            .await?
+            .into_iter()
+            .map(|primary_key| primary_key.column_name)
+            .collect();
+
+        tables.insert(
+            table_name.to_string(),
+            SubsetTable {
+                column_infos,
+                primary_keys,
+                rows: Vec::new(),
+                seen: HashMap::new(),
+            },
+        );
+    }
+
+    Ok(())
+}
+
+/// The distinct non null values of a column of the given rows.
+fn distinct_values(rows: &[Value], column: &str) -> Vec<Value> {
+    let mut seen = HashSet::new();
+    rows.iter()
+        .filter_map(|row| row.as_object()?.get(column))
+        .filter(|value| !value.is_null() && seen.insert(*value))
+        .cloned()
+        .collect()
+}
+
+/// Orders the rows of a table which references itself, such that referenced rows come before
+/// the rows referencing them. Rows which are part of a reference cycle are put last.
+pub(crate) fn order_self_references(
+    rows: Vec<Value>,
+    table_name: &str,
+    foreign_keys: &[ForeignKey],
+) -> Vec<Value> {
+    let self_references: Vec<&ForeignKey> = foreign_keys
+        .iter()
+        .filter(|fk| fk.from_table == table_name && fk.to_table == table_name)
+        .collect();
+    if self_references.is_empty() {
+        return rows;
+    }
+
+    let field = |row: &Value, column: &str| {
+        row.as_object()
+            .and_then(|row| row.get(column))
+            .cloned()
+            .unwrap_or(Value::Null(()))
+    };
+
+    // Values of the referenced columns which are still to be inserted
+    let mut pending: Vec<HashSet<Value>> = self_references
+        .iter()
+        .map(|fk| rows.iter().map(|row| field(row, &fk.to_column)).collect())
+        .collect();
+
+    let mut remaining = rows;
+    let mut ordered = Vec::with_capacity(remaining.len());
+
+    while !remaining.is_empty() {
+        let (ready, blocked): (Vec<Value>, Vec<Value>) = remaining.into_iter().partition(|row| {
+            self_references
+                .iter()
+                .zip(pending.iter())
+                .all(|(fk, pending)| {
+                    let value = field(row, &fk.from_column);
+                    value.is_null()
+                        || !pending.contains(&value)
+                        || field(row, &fk.to_column) == value
+                })
+        });
+
+        if ready.is_empty() {
+            warn!(
+                "Found a reference cycle between rows of {}. They will be inserted in an arbitrary order.",
+                table_name
+            );
+            ordered.extend(blocked);
+            break;
+        }
+
+        for row in ready.iter() {
+            for (fk, pending) in self_references.iter().zip(pending.iter_mut()) {
+                pending.remove(&field(row, &fk.to_column));
+            }
+        }
+
+        ordered.extend(ready);
+        remaining = blocked;
+    }
+
+    ordered
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use serde_json::json;
+    use synth_core::graph::json::json_val_to_synth;
+
+    pub(crate) fn rows(value: serde_json::Value) -> Vec<Value> {
+        match json_val_to_synth(value) {
+            Value::Array(rows) => rows,
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn order_self_references() {
+        let employees = rows(json!([
+            { "id": 3, "manager": 2 },
+            { "id": 2, "manager": 1 },
+            { "id": 1, "manager": null },
+            { "id": 4, "manager": 10 },
+        ]));
+        let foreign_keys = vec![ForeignKey {
+            from_table: "employees".to_string(),
+            from_column: "manager".to_string(),
+            to_table: "employees".to_string(),
+            to_column: "id".to_string(),
+        }];
+
+        let ordered = super::order_self_references(employees, "employees", &foreign_keys);
+        let ids: Vec<Value> = ordered
+            .iter()
+            .map(|row| row.as_object().unwrap()["id"].clone())
+            .collect();
+
+        assert_eq!(ids, rows(json!([1, 4, 2, 3])));
+    }
+
+    #[test]
+    fn distinct_values() {
+        let orders = rows(json!([
+            { "id": 1, "user": 1 },
+            { "id": 2, "user": 2 },
+            { "id": 3, "user": 1 },
+            { "id": 4, "user": null },
+        ]));
+
+        assert_eq!(super::distinct_values(&orders, "user"), rows(json!([1, 2])));
+    }
+}
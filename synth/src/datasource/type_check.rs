@@ -0,0 +1,339 @@
+use crate::datasource::relational_datasource::ColumnInfo;
+use anyhow::Result;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::{self, Display, Formatter};
+use synth_core::{Content, Value};
+use synth_gen::value::Number;
+
+/// The values a column accepts, as far as they can be checked before inserting.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ColumnKind {
+    /// Booleans, or the integers 0 and 1
+    Bool,
+    /// Integers within inclusive bounds, or strings of such integers
+    Integer {
+        min: i128,
+        max: i128,
+    },
+    /// Numbers, or strings of numbers
+    Float,
+    /// Strings of at most the given number of characters, or other scalars
+    Text(Option<usize>),
+    /// Date times, or strings
+    DateTime,
+    Array(Box<ColumnKind>),
+    /// Columns which accept values of any type, or whose values aren't checked
+    Any,
+}
+
+impl ColumnKind {
+    pub fn signed(bits: u32) -> Self {
+        Self::Integer {
+            min: -(1 << (bits - 1)),
+            max: (1 << (bits - 1)) - 1,
+        }
+    }
+
+    pub fn unsigned(bits: u32) -> Self {
+        Self::Integer {
+            min: 0,
+            max: (1 << bits) - 1,
+        }
+    }
+
+    fn check(&self, value: &Value) -> Option<Mismatch> {
+        match (self, value) {
+            (Self::Any, _) => None,
+            (Self::Bool, Value::Bool(_)) => None,
+            (Self::Bool, Value::Number(number)) if matches!(as_integer(number), Some(0 | 1)) => {
+                None
+            }
+            (Self::Bool, Value::String(s))
+                if BOOL_LITERALS.contains(&s.to_lowercase().as_str()) =>
+            {
+                None
+            }
+            (Self::Integer { min, max }, Value::Number(_) | Value::String(_)) => {
+                let i = match value {
+                    Value::Number(number) => as_integer(number),
+                    _ => value.as_string().and_then(|s| s.trim().parse().ok()),
+                };
+                match i {
+                    Some(i) if i < *min || i > *max => Some(Mismatch::OutOfRange {
+                        min: *min,
+                        max: *max,
+                    }),
+                    Some(_) => None,
+                    None => Some(Mismatch::NotAnInteger),
+                }
+            }
+            (Self::Float, Value::Number(_)) => None,
+            (Self::Float, Value::String(s)) if s.trim().parse::<f64>().is_ok() => None,
+            (Self::Text(Some(max_length)), Value::String(s)) if s.chars().count() > *max_length => {
+                Some(Mismatch::TooLong(*max_length))
+            }
+            (
+                Self::Text(_),
+                Value::String(_) | Value::Number(_) | Value::Bool(_) | Value::DateTime(_),
+            ) => None,
+            (Self::DateTime, Value::DateTime(_) | Value::String(_)) => None,
+            (Self::Array(element), Value::Array(elements)) => elements
+                .iter()
+                .filter(|element| !element.is_null())
+                .find_map(|value| element.check(value)),
+            (_, value) => Some(Mismatch::Type(value.type_())),
+        }
+    }
+}
+
+/// The strings which both Postgres and MySQL parse as booleans.
+const BOOL_LITERALS: [&str; 4] = ["true", "false", "1", "0"];
+
+fn as_integer(number: &Number) -> Option<i128> {
+    match *number {
+        Number::I8(i) => Some(i as i128),
+        Number::I16(i) => Some(i as i128),
+        Number::I32(i) => Some(i as i128),
+        Number::I64(i) => Some(i as i128),
+        Number::I128(i) => Some(i),
+        Number::U8(u) => Some(u as i128),
+        Number::U16(u) => Some(u as i128),
+        Number::U32(u) => Some(u as i128),
+        Number::U64(u) => Some(u as i128),
+        Number::U128(u) => Some(u as i128),
+        Number::F32(_) | Number::F64(_) => None,
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+enum Mismatch {
+    /// The generated column doesn't exist in the table
+    UnknownColumn,
+    /// The row doesn't have the columns of the first row
+    MissingColumn,
+    Null,
+    Type(&'static str),
+    NotAnInteger,
+    OutOfRange {
+        min: i128,
+        max: i128,
+    },
+    TooLong(usize),
+}
+
+impl Display for Mismatch {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnknownColumn => write!(f, "the column does not exist in the table"),
+            Self::MissingColumn => write!(f, "the column is missing from the row"),
+            Self::Null => write!(f, "null value in a NOT NULL column"),
+            Self::Type(type_) => write!(f, "{} value which the column type does not accept", type_),
+            Self::NotAnInteger => write!(f, "non integral value in an integer column"),
+            Self::OutOfRange { min, max } => {
+                write!(f, "value out of the range [{}, {}] of the column", min, max)
+            }
+            Self::TooLong(max_length) => write!(f, "value longer than {} characters", max_length),
+        }
+    }
+}
+
+/// The values of a column which don't fit into it, grouped by the reason.
+struct Issue {
+    count: usize,
+    first_row: usize,
+}
+
+/// Checks every row generated for a table against the columns of the table, and fails with a
+/// report of all the values which don't fit. Columns of the table which are missing from the
+/// schema or from the generated data are only warned about, as they may have a default.
+///
+/// `content` is the content of the collection, if it is part of the namespace.
+pub(crate) fn check_collection(
+    table_name: &str,
+    collection: &[Value],
+    content: Option<&Content>,
+    column_infos: &[ColumnInfo],
+    column_kind: impl Fn(&ColumnInfo) -> ColumnKind,
+) -> Result<()> {
+    let first_row = match collection.get(0) {
+        Some(Value::Object(first_row)) => first_row,
+        _ => return Ok(()),
+    };
+
+    let schema_fields: Option<BTreeSet<&String>> = match content {
+        Some(Content::Array(array)) => match array.content.as_ref() {
+            Content::Object(object) => Some(object.fields.keys().collect()),
+            _ => None,
+        },
+        _ => None,
+    };
+
+    for column_info in column_infos {
+        let column_name = &column_info.column_name;
+        if first_row.contains_key(column_name) {
+            continue;
+        }
+
+        if matches!(schema_fields, Some(ref fields) if !fields.contains(column_name)) {
+            warn!(
+                "Column {}.{} is not in the schema and is left to its default",
+                table_name, column_name
+            );
+        } else {
+            warn!(
+                "Column {}.{} is not generated and is left to its default",
+                table_name, column_name
+            );
+        }
+    }
+
+    let columns: BTreeMap<&String, Option<(&ColumnInfo, ColumnKind)>> = first_row
+        .keys()
+        .map(|column_name| {
+            let column = column_infos
+                .iter()
+                .find(|column_info| &column_info.column_name == column_name)
+                .map(|column_info| (column_info, column_kind(column_info)));
+            (column_name, column)
+        })
+        .collect();
+
+    let mut issues: BTreeMap<(&String, Mismatch), Issue> = BTreeMap::new();
+    let mut report = |column_name, mismatch, row| {
+        issues
+            .entry((column_name, mismatch))
+            .or_insert(Issue {
+                count: 0,
+                first_row: row,
+            })
+            .count += 1;
+    };
+
+    for (column_name, column) in columns.iter() {
+        if column.is_none() {
+            report(*column_name, Mismatch::UnknownColumn, 0);
+        }
+    }
+
+    for (row_index, row) in collection.iter().enumerate() {
+        let row = row
+            .as_object()
+            .expect("This is always an object (sampler contract)");
+
+        for (column_name, column) in columns.iter() {
+            let (column_info, kind) = match column {
+                Some(column) => column,
+                None => continue,
+            };
+
+            match row.get(*column_name) {
+                None => report(*column_name, Mismatch::MissingColumn, row_index),
+                Some(value) if value.is_null() => {
+                    if !column_info.is_nullable {
+                        report(*column_name, Mismatch::Null, row_index)
+                    }
+                }
+                Some(value) => {
+                    if let Some(mismatch) = kind.check(value) {
+                        report(*column_name, mismatch, row_index)
+                    }
+                }
+            }
+        }
+    }
+
+    if issues.is_empty() {
+        return Ok(());
+    }
+
+    let report: Vec<String> = issues
+        .into_iter()
+        .map(|((column_name, mismatch), issue)| match mismatch {
+            Mismatch::UnknownColumn => format!("{}.{}: {}", table_name, column_name, mismatch),
+            _ => format!(
+                "{}.{}: {} (in {} row(s), first at row {})",
+                table_name, column_name, mismatch, issue.count, issue.first_row
+            ),
+        })
+        .collect();
+
+    bail!(
+        "The generated data does not fit into table {}:\n  {}",
+        table_name,
+        report.join("\n  ")
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use synth_core::graph::json::json_val_to_synth;
+
+    fn column_info(column_name: &str, is_nullable: bool, data_type: &str) -> ColumnInfo {
+        ColumnInfo {
+            column_name: column_name.to_string(),
+            ordinal_position: 0,
+            is_nullable,
+            data_type: data_type.to_string(),
+            character_maximum_length: Some(3),
+            numeric_precision: None,
+            numeric_scale: None,
+            column_type: None,
+            type_schema: None,
+        }
+    }
+
+    fn kind(column_info: &ColumnInfo) -> ColumnKind {
+        match column_info.data_type.as_str() {
+            "int2" => ColumnKind::signed(16),
+            "varchar" => ColumnKind::Text(Some(3)),
+            "bool" => ColumnKind::Bool,
+            _ => ColumnKind::Any,
+        }
+    }
+
+    #[test]
+    fn check_collection() {
+        let column_infos = vec![
+            column_info("id", false, "int2"),
+            column_info("name", true, "varchar"),
+            column_info("active", false, "bool"),
+            column_info("created", true, "timestamp"),
+        ];
+
+        let valid = json_val_to_synth(json!([
+            { "id": 1, "name": "abc", "active": true },
+            { "id": "2", "name": null, "active": 0 },
+        ]));
+        let valid = match valid {
+            Value::Array(rows) => rows,
+            _ => unreachable!(),
+        };
+        assert!(super::check_collection("users", &valid, None, &column_infos, kind).is_ok());
+
+        let invalid = json_val_to_synth(json!([
+            { "id": 40000, "name": "abcd", "active": true, "extra": 1 },
+            { "id": 1.5, "name": "abcd", "active": null, "extra": 1 },
+            { "id": "three", "name": "abc", "active": 2, "extra": 1 },
+        ]));
+        let invalid = match invalid {
+            Value::Array(rows) => rows,
+            _ => unreachable!(),
+        };
+        let report = super::check_collection("users", &invalid, None, &column_infos, kind)
+            .unwrap_err()
+            .to_string();
+
+        assert_eq!(
+            report,
+            "The generated data does not fit into table users:
+  users.active: null value in a NOT NULL column (in 1 row(s), first at row 1)
+  users.active: Number value which the column type does not accept (in 1 row(s), first at row 2)
+  users.extra: the column does not exist in the table
+  users.id: non integral value in an integer column (in 2 row(s), first at row 1)
+  users.id: value out of the range [-32768, 32767] of the column (in 1 row(s), first at row 0)
+  users.name: value longer than 3 characters (in 2 row(s), first at row 0)"
+        );
+    }
+}